@@ -1,12 +1,13 @@
 use anyhow::Result;
 use byte_unit::ByteUnit;
 use cnx::text::*;
+use cnx::theme::Theme;
 use cnx::widgets::*;
 use cnx::{Cnx, Position};
+use cnx_contrib::format::FormatOptions;
 use cnx_contrib::widgets::battery::*;
 use cnx_contrib::widgets::disk_usage::*;
 use cnx_contrib::widgets::*;
-use weathernoaa::weather::WeatherInfo;
 
 fn pango_markup_render(color: Color, start_text: String, text: String) -> String {
     format!(
@@ -41,21 +42,21 @@ fn weather_sky_condition(condition: String) -> &'static str {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let attr = Attributes {
-        font: Font::new("Ubuntu Mono Bold 14"),
-        fg_color: Color::white(),
-        bg_color: None,
-        padding: Padding::new(0.0, 0.0, 0.0, 0.0),
-    };
+    // Widgets created below with `Attributes::default()` use the theme.
+    let theme = Theme::gruvbox().font(Font::new("Ubuntu Mono Bold 14"));
+    let cnx = Cnx::new(Position::Bottom)?.theme(&theme);
 
+    let pager_padding = Padding::new(8.0, 8.0, 0.0, 0.0);
     let pager_attr = Attributes {
-        font: Font::new("Ubuntu Mono Bold 14"),
-        fg_color: Color::white(),
-        bg_color: Some(Color::blue()),
-        padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+        padding: pager_padding.clone(),
+        ..theme.accent_bg_attr()
+    };
+    let p2_attr = Attributes {
+        padding: pager_padding,
+        ..theme.attr()
     };
 
-    // let sensors = Sensors::new(attr.clone(), vec!["Core 0", "Core 1"]);
+    // let sensors = Sensors::new(Attributes::default(), vec!["Core 0", "Core 1"]);
     let battery_render = |battery_info: BatteryInfo| {
         let percentage = battery_info.capacity;
 
@@ -63,8 +64,14 @@ async fn main() -> Result<()> {
         pango_markup_single_render(Color::white(), default_text)
     };
 
-    let battery = Battery::new_with_render(attr.clone(), Color::red(), None, battery_render);
-    let render = |load| {
+    let battery = Battery::new_with_render(
+        Attributes::default(),
+        theme.critical.clone(),
+        None,
+        battery_render,
+    );
+    let render = |info: cpu::CpuInfo| {
+        let load = info.usage;
         let mut color = Color::yellow().to_hex();
         if load < 5 {
             color = Color::green().to_hex();
@@ -77,14 +84,15 @@ async fn main() -> Result<()> {
             color, load
         )
     };
-    let cpu = cpu::Cpu::new_with_render(attr.clone(), render)?;
+    let cpu =
+        cpu::Cpu::new_with_render(Attributes::default(), 0, FormatOptions::default(), render)?;
 
-    let volume = volume::Volume::new(attr.clone());
+    let volume = volume::Volume::new(Attributes::default());
 
     let default_threshold = Threshold::default();
 
     let wireless =
-        wireless::Wireless::new(attr.clone(), "wlp2s0".to_owned(), Some(default_threshold));
+        wireless::Wireless::new(Attributes::default(), "wlp2s0".to_owned(), Some(default_threshold));
 
     let disk_render = |disk_info: DiskInfo| {
         let used = disk_info.used.get_adjusted_unit(ByteUnit::GiB).format(0);
@@ -93,34 +101,41 @@ async fn main() -> Result<()> {
         pango_markup_single_render(Color::white(), disk_text)
     };
 
-    let disk_usage = disk_usage::DiskUsage::new_with_render(attr.clone(), "/home".into(), disk_render);
+    let disk_options = DiskUsageOptions {
+        paths: vec!["/home".into()],
+        ..DiskUsageOptions::default()
+    };
+    let disk_usage =
+        disk_usage::DiskUsage::new_with_render(Attributes::default(), disk_options, disk_render);
 
-    let weather_render = |weather: WeatherInfo| {
+    let weather_render = |weather: weather::WeatherInfo| {
         let sky_condition = weather_sky_condition(weather.sky_condition);
         let weather_text = format!("BLR: {} :", sky_condition);
         let weather_temp = format!(" {}°C", weather.temperature.celsius);
         pango_markup_render(Color::white(), weather_text, weather_temp)
     };
 
-    let weather = weather::Weather::new_with_render(attr.clone(), "VOBL".into(), weather_render);
-
-    let mut p2_attr = pager_attr.clone();
-    p2_attr.bg_color = None;
+    let weather = weather::Weather::new_with_render(Attributes::default(), "VOBL".into(), weather::Units::Metric, None, weather_render);
 
     let time_template = Some("<span foreground=\"#808080\">[</span>%d-%m-%Y %a %I:%M %p<span foreground=\"#808080\">]</span>".into());
 
-    Cnx::new(Position::Bottom)?
+    let cnx = cnx
         .add_widget(Pager::new(pager_attr, p2_attr))?
-        .add_widget(ActiveWindowTitle::new(attr.clone()))?
+        .add_widget(ActiveWindowTitle::new(Attributes::default()))?
         .add_widget(cpu)?
         .add_widget(weather)?
         .add_widget(disk_usage)?
         .add_widget(wireless)?
         .add_widget(volume)?
         .add_widget(battery)?
-        .add_widget(Clock::new(attr, time_template))?
-        .run()
-        .await?;
+        .add_widget(Clock::new(Attributes::default(), time_template))?;
+
+    // `cnx --check` reports whether each widget works, then exits.
+    if std::env::args().any(|arg| arg == "--check") {
+        cnx.check().await?;
+    } else {
+        cnx.run().await?;
+    }
 
     Ok(())
 }
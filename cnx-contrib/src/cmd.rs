@@ -0,0 +1,47 @@
+//! Helpers for widgets that get their data by running system commands.
+
+use anyhow::{anyhow, Context, Result};
+use std::ffi::OsStr;
+use std::process::Command;
+use std::str::FromStr;
+
+/// Runs `command` with `args` and returns its standard output.
+///
+/// Returns an error if the command can't be run or exits unsuccessfully.
+pub(crate) fn command_output<I, S>(command: &str, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new(command)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", command))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .with_context(|| format!("Output of {} was not UTF-8", command))?;
+    Ok(stdout)
+}
+
+/// Runs `command` with `args` and parses its (trimmed) standard output.
+pub(crate) fn from_command_output<T, I, S>(command: &str, args: I) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = command_output(command, args)?;
+    let value = output
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse output of {}", command))?;
+    Ok(value)
+}
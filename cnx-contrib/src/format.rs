@@ -0,0 +1,67 @@
+//! How widgets format the numbers that they show.
+
+use byte_unit::Byte;
+
+/// Whether sizes are shown in binary units (KiB, MiB, GiB: powers of 1024)
+/// or decimal units (KB, MB, GB: powers of 1000).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Units {
+    Binary,
+    Decimal,
+}
+
+/// Controls how numeric widgets format sizes, rates and percentages.
+///
+/// Widgets that show numbers accept a `FormatOptions` for their default
+/// rendering, and pass it on to render closures (e.g. as
+/// [`DiskInfo::format`]), so that values can be formatted the same way
+/// across the bar:
+///
+/// ```
+/// # use cnx_contrib::format::{FormatOptions, Units};
+/// let format = FormatOptions::new(1, Units::Decimal);
+/// assert_eq!(format.percentage(42.42), "42.4%");
+/// ```
+///
+/// The default is whole numbers and binary units.
+///
+/// [`DiskInfo::format`]: ../widgets/disk_usage/struct.DiskInfo.html#structfield.format
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FormatOptions {
+    /// The number of decimal places to show.
+    pub precision: usize,
+    pub units: Units,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            precision: 0,
+            units: Units::Binary,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn new(precision: usize, units: Units) -> Self {
+        FormatOptions { precision, units }
+    }
+
+    /// Formats a size in bytes with the most appropriate unit, e.g.
+    /// `"12 GiB"`.
+    pub fn bytes(&self, bytes: u128) -> String {
+        Byte::from_bytes(bytes)
+            .get_appropriate_unit(self.units == Units::Binary)
+            .format(self.precision)
+    }
+
+    /// Formats a rate in bytes per second, e.g. `"1.2 MB/s"`.
+    pub fn rate(&self, bytes_per_second: u128) -> String {
+        format!("{}/s", self.bytes(bytes_per_second))
+    }
+
+    /// Formats a percentage, e.g. `"42%"`.
+    pub fn percentage(&self, percentage: f64) -> String {
+        format!("{:.*}%", self.precision, percentage)
+    }
+}
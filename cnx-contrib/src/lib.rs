@@ -1 +1,6 @@
+mod cmd;
+/// Shared formatting of sizes, rates and percentages
+pub mod format;
+/// Policies for widgets that poll remote services
+pub mod polling;
 pub mod widgets;
@@ -0,0 +1,96 @@
+//! How widgets poll remote services.
+
+use anyhow::{anyhow, Result};
+use cnx::widgets::clamp_interval;
+use std::cmp;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time;
+
+/// Controls how often a widget fetches data from a remote service.
+///
+/// After a successful fetch, the widget waits for `interval`. After a failed
+/// fetch, it retries after `retry`, doubling the wait after each consecutive
+/// failure up to `max_backoff`. A random delay of up to `jitter` is added to
+/// every wait, so that many bars with identical configs don't all hit the
+/// service at the same moment.
+///
+/// A fetch that takes longer than `timeout` (e.g. because DNS or the
+/// connection has stalled) counts as a failure, so that it is retried rather
+/// than leaving the widget stuck.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polling {
+    pub interval: Duration,
+    pub retry: Duration,
+    pub max_backoff: Duration,
+    pub jitter: Duration,
+    pub timeout: Duration,
+}
+
+impl Polling {
+    /// Creates a policy that polls every `interval`, with the default retry,
+    /// backoff and jitter.
+    pub fn every(interval: Duration) -> Self {
+        Polling {
+            interval,
+            ..Polling::default()
+        }
+    }
+
+    // Raises `interval` and `retry` to at least `minimum`, so that a widget
+    // can't be configured to hammer a remote service.
+    pub(crate) fn clamped(self, widget: &str, minimum: Duration) -> Self {
+        Polling {
+            interval: clamp_interval(widget, self.interval, minimum),
+            retry: clamp_interval(widget, self.retry, minimum),
+            ..self
+        }
+    }
+
+    /// How long to wait before the next fetch, given the number of fetches
+    /// that have failed in a row.
+    pub(crate) fn delay(&self, failures: u32) -> Duration {
+        let delay = match failures {
+            0 => self.interval,
+            n => {
+                let factor = 2u32.saturating_pow(n - 1);
+                let backoff = self.retry.checked_mul(factor).unwrap_or(self.max_backoff);
+                cmp::min(backoff, self.max_backoff)
+            }
+        };
+        delay + self.random_jitter()
+    }
+
+    /// Runs `fetch`, failing if it doesn't complete within `timeout`.
+    pub(crate) async fn fetch<T, F>(&self, fetch: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        match time::timeout(self.timeout, fetch).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("Timed out after {:?}", self.timeout)),
+        }
+    }
+
+    // We don't need good randomness, just enough to spread requests out, so
+    // use the sub-second part of the current time rather than a RNG.
+    fn random_jitter(&self) -> Duration {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.subsec_nanos())
+            .unwrap_or(0);
+        self.jitter.mul_f64(f64::from(nanos) / 1e9)
+    }
+}
+
+impl Default for Polling {
+    fn default() -> Self {
+        Polling {
+            interval: Duration::from_secs(30 * 60),
+            retry: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60 * 60),
+            jitter: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use inotify::{Inotify, WatchMask};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tokio_stream::{Stream, StreamExt};
+
+const BACKLIGHT_CLASS: &str = "/sys/class/backlight";
+
+/// The brightness passed to a [`Backlight`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrightnessInfo {
+    /// The current brightness, in the device's own units.
+    pub current: u32,
+    /// The highest brightness the device supports.
+    pub max: u32,
+    /// The current brightness as a percentage of `max`.
+    pub percent: f32,
+}
+
+// The first backlight device, by name, if there are any.
+fn first_device() -> Option<String> {
+    let mut names: Vec<String> = fs::read_dir(BACKLIGHT_CLASS)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names.into_iter().next()
+}
+
+fn read_value(path: &Path) -> Result<u32> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    contents
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Shows the brightness of a backlight, e.g. `🔆 75%`.
+///
+/// The brightness is read from `/sys/class/backlight/<device>/brightness`,
+/// and re-read whenever that file is written to (by `brightnessctl`, `light`
+/// and the like) rather than polled.
+pub struct Backlight<F: Fn(BrightnessInfo) -> String> {
+    attr: Attributes,
+    device: Option<String>,
+    render: Rc<F>,
+}
+
+// The widget is restarted if the device can't be watched (e.g. before its
+// driver has loaded), so it must be `Clone` however `render` is.
+impl<F: Fn(BrightnessInfo) -> String> Clone for Backlight<F> {
+    fn clone(&self) -> Self {
+        Backlight {
+            attr: self.attr.clone(),
+            device: self.device.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+fn default_render(info: BrightnessInfo) -> String {
+    format!("🔆 {:.0}%", info.percent)
+}
+
+impl Backlight<fn(BrightnessInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        device: Option<String>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Backlight::new_with_render(attr, device, default_render)
+    }
+}
+
+impl<F: Fn(BrightnessInfo) -> String + 'static> Backlight<F> {
+    /// Creates a new [`Backlight`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `device` - The name of the backlight device in
+    /// `/sys/class/backlight/`, e.g. `intel_backlight`. If `None`, the first
+    /// device there is used.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`BrightnessInfo`] holds the current brightness.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::backlight::*;
+    /// let render = |info: BrightnessInfo| format!("Brightness {}/{}", info.current, info.max);
+    /// let backlight = Backlight::new_with_render(Attributes::default(), None, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        device: Option<String>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Backlight {
+                attr,
+                device,
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn tick(&self, brightness: &Path, max: u32) -> Result<Vec<Text>> {
+        let current = read_value(brightness)?;
+        let percent = if max == 0 {
+            0.0
+        } else {
+            current as f32 / max as f32 * 100.0
+        };
+        let text = (self.render)(BrightnessInfo {
+            current,
+            max,
+            percent,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let device = match &self.device {
+            Some(device) => device.clone(),
+            None => first_device()
+                .ok_or_else(|| anyhow!("No backlight devices found in {}", BACKLIGHT_CLASS))?,
+        };
+        let dir = PathBuf::from(BACKLIGHT_CLASS).join(device);
+        let max = read_value(&dir.join("max_brightness"))?;
+        let brightness = dir.join("brightness");
+
+        let mut inotify = Inotify::init().context("Failed to initialise inotify")?;
+        inotify
+            .add_watch(&brightness, WatchMask::MODIFY)
+            .with_context(|| format!("Failed to watch {}", brightness.display()))?;
+        let events = inotify
+            .event_stream([0; 1024])
+            .context("Failed to create inotify stream")?;
+
+        // Show the brightness straight away, then again after each change.
+        let stream = tokio_stream::once(Ok(()))
+            .chain(events.map(|event| event.map(|_| ())))
+            .map(move |event| {
+                event.context("Failed to read inotify event")?;
+                self.tick(&brightness, max)
+            });
+        Ok(stream)
+    }
+}
@@ -1,8 +1,12 @@
-#[cfg(feature = "openbsd")]
+#[cfg(target_os = "openbsd")]
 mod battery_bsd;
 #[cfg(target_os = "linux")]
 mod battery_linux;
-#[cfg(feature = "openbsd")]
+#[cfg(all(target_os = "linux", feature = "upower"))]
+mod battery_upower;
+#[cfg(target_os = "openbsd")]
 pub use battery_bsd::Battery;
 #[cfg(target_os = "linux")]
-pub use battery_linux::{Battery, BatteryInfo, Status};
+pub use battery_linux::{charging_glyph, Battery, BatteryInfo, Status};
+#[cfg(all(target_os = "linux", feature = "upower"))]
+pub use battery_upower::UPowerBattery;
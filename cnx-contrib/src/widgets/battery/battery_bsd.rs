@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use cnx::cmd::{command_output, from_command_output};
+use crate::cmd::{command_output, from_command_output};
 use cnx::text::{Attributes, Color, Text};
 use cnx::widgets::{WidgetStreamI, WidgetStream};
 use std::str::FromStr;
@@ -72,7 +72,6 @@ impl OpenBsd {
     }
 }
 
-// TODO: Gate this on platform once we add the Linux impl back.
 type BatteryInfo = OpenBsd;
 
 /// Shows battery charge percentage and (dis)charge time.
@@ -135,15 +134,10 @@ impl Battery {
             attr.fg_color = self.warning_color.clone()
         }
 
-        Ok(vec![Text {
-            attr,
-            text,
-            stretch: false,
-            markup: false,
-        }])
+        Ok(vec![Text::new(attr, text)])
     }
 
-    fn into_stream(self: Self) -> Result<impl Stream<Item = WidgetStreamItemI>> {
+    fn into_stream(self: Self) -> Result<impl Stream<Item = WidgetStreamI>> {
         let interval = time::interval(self.update_interval);
         let stream = IntervalStream::new(interval).map(move |_| self.tick());
 
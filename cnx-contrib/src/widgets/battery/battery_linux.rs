@@ -1,13 +1,13 @@
 use anyhow::{anyhow, Context, Error, Result};
+use async_stream::stream;
 use cnx::text::{Attributes, Color, Text};
-use cnx::widgets::{WidgetStream, WidgetStreamI};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
 use std::fs::File;
 use std::io::Read;
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::{StreamExt, Stream};
+use tokio_stream::Stream;
 
 /// Represent Battery's operating status
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -36,14 +36,26 @@ impl FromStr for Status {
 ///
 /// This widget shows the battery's current charge percentage.
 ///
-/// When the battery has less than 10% charge remaining, the widget's text will
-/// change to the specified `warning_color`.
-///magical
+/// When the battery is discharging and has 10% charge or less remaining, the
+/// widget's text will change to the specified `warning_color`. The warning
+/// stays on through a briefly `Unknown` status, and until the charge is back
+/// above 12%, so that a flapping status or capacity doesn't make the color
+/// flicker. It goes off as soon as the battery is charging or full.
+///
+/// When the battery is full, the default rendering shows a plug instead of the
+/// percentage. Otherwise, it shows the estimated time until the battery is
+/// empty or full, if the battery reports enough to estimate it.
+///
+/// While charging, the widget updates every second so that the default
+/// rendering can animate a charging glyph. Both update intervals can be
+/// changed with [`Battery::new_with_intervals()`].
+///
 /// Battery charge information is read from [`/sys/class/power_supply/BAT0/`].
 ///
 /// [`/sys/class/power_supply/BAT0/`]: https://www.kernel.org/doc/Documentation/power/power_supply_class.txt
 pub struct Battery<F: Fn(BatteryInfo) -> String> {
     update_interval: Duration,
+    charging_interval: Duration,
     battery: String,
     attr: Attributes,
     warning_color: Color,
@@ -58,10 +70,157 @@ pub struct BatteryInfo {
     pub status: Status,
     /// Capacity in percentage
     pub capacity: u8,
+    /// Incremented on every update while the battery is charging (and reset
+    /// to 0 otherwise), so that renders can animate. See [`charging_glyph`].
+    pub animation_frame: usize,
+    /// Voltage in µV (`voltage_now`), if reported by the battery.
+    pub voltage_now: Option<u64>,
+    /// Current in µA (`current_now`), if reported by the battery. Some
+    /// drivers report a negative current while discharging.
+    pub current_now: Option<i64>,
+    /// Number of charge cycles (`cycle_count`), if reported by the battery.
+    pub cycle_count: Option<u32>,
+    /// Charge when full in µAh (`charge_full`), if reported by the battery.
+    pub charge_full: Option<u64>,
+    /// Charge when full, as designed, in µAh (`charge_full_design`), if
+    /// reported by the battery.
+    pub charge_full_design: Option<u64>,
+    /// The estimated time until the battery is empty while discharging, or
+    /// full while charging. This is worked out from the current rate of
+    /// (dis)charge, from `energy_now` and `power_now` or `charge_now` and
+    /// `current_now`, so is `None` if the battery doesn't report these or
+    /// isn't (dis)charging at all.
+    pub time_remaining: Option<Duration>,
+}
+
+impl BatteryInfo {
+    /// The battery's health: how much charge it holds when full, as a
+    /// percentage of what it was designed to hold.
+    pub fn health(&self) -> Option<f64> {
+        match (self.charge_full, self.charge_full_design) {
+            (Some(full), Some(design)) if design > 0 => Some(full as f64 / design as f64 * 100.0),
+            _ => None,
+        }
+    }
+}
+
+// How often to update by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+// How often to update while charging by default, so that the animation is
+// visible.
+const CHARGING_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// The shortest intervals allowed, as reading sysfs isn't free.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const MIN_CHARGING_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+// The capacity at or below which a discharging battery is warned about.
+const WARNING_CAPACITY: u8 = 10;
+// How far above `WARNING_CAPACITY` the capacity must rise to end a warning.
+const WARNING_HYSTERESIS: u8 = 2;
+// The capacity below which a warned about battery blinks too.
+pub(super) const CRITICAL_CAPACITY: u8 = 5;
+
+// Whether to warn about the battery, given whether we were already warning.
+// Only a discharging battery starts a warning. An `Unknown` status, which
+// some drivers report briefly at low capacity, keeps a warning going, but
+// only while the capacity is low, as batteries held at a charge limit on AC
+// can report `Unknown` indefinitely.
+pub(super) fn should_warn(warning: bool, info: &BatteryInfo) -> bool {
+    match info.status {
+        Status::Discharging | Status::Unknown if warning => {
+            info.capacity <= WARNING_CAPACITY + WARNING_HYSTERESIS
+        }
+        Status::Discharging => info.capacity <= WARNING_CAPACITY,
+        Status::Unknown | Status::Charging | Status::Full => false,
+    }
+}
+
+const CHARGING_GLYPHS: [&str; 5] = ["▁", "▃", "▅", "▇", "█"];
+
+/// Returns the glyph for the given frame of a filling-up charging animation.
+pub fn charging_glyph(animation_frame: usize) -> &'static str {
+    CHARGING_GLYPHS[animation_frame % CHARGING_GLYPHS.len()]
+}
+
+// Units that some drivers append to values that are already in the units
+// that the power supply class documents, so can be dropped.
+const KNOWN_SUFFIXES: [&str; 9] = ["%", "µAh", "uAh", "µWh", "uWh", "µV", "uV", "µA", "uA"];
+
+// Parses the contents of a sysfs file. Numbers are read tolerantly, as the
+// format varies between drivers: if the value doesn't parse as-is, a known
+// unit suffix is stripped and a fractional value is rounded.
+fn parse_value<T>(contents: &str) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Into<Error>,
+{
+    let contents = contents.trim();
+    let err: Error = match T::from_str(contents) {
+        Ok(value) => return Ok(value),
+        Err(err) => err.into(),
+    };
+
+    let number = KNOWN_SUFFIXES
+        .iter()
+        .fold(contents, |s, suffix| s.strip_suffix(suffix).unwrap_or(s))
+        .trim_end()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite());
+    match number {
+        Some(number) => T::from_str(&format!("{}", number.round() as i64))
+            .map_err(|e: <T as FromStr>::Err| e.into())
+            .context("Failed to parse value"),
+        None => Err(err.context("Failed to parse value")),
+    }
+}
+
+// Estimates how long until the battery is empty (or full, while charging),
+// given how much energy or charge it holds `now` and `full`, and the `rate`
+// it's being (dis)charged at, in the same units per hour.
+fn estimate_time_remaining(status: &Status, now: u64, full: u64, rate: i64) -> Option<Duration> {
+    // Some drivers report a negative rate while discharging.
+    let rate = rate.unsigned_abs();
+    if rate == 0 {
+        return None;
+    }
+    let remaining = match status {
+        Status::Discharging => now,
+        Status::Charging => full.saturating_sub(now),
+        Status::Full | Status::Unknown => return None,
+    };
+    let hours = remaining as f64 / rate as f64;
+    Some(Duration::from_secs_f64(hours * 3600.0))
 }
 
-fn render_default(info: BatteryInfo) -> String {
-    format!("({percentage:.0}%)", percentage = info.capacity,)
+// Formats a time remaining as e.g. `1h20m`, or `45m` if it's under an hour.
+fn format_time_remaining(time: Duration) -> String {
+    let minutes = time.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h{:02}m", hours, minutes),
+    }
+}
+
+pub(super) fn render_default(info: BatteryInfo) -> String {
+    let time_remaining = info
+        .time_remaining
+        .map(|time| format!(" {}", format_time_remaining(time)))
+        .unwrap_or_default();
+    match info.status {
+        Status::Full => "(🔌 Full)".to_owned(),
+        Status::Charging => format!(
+            "({glyph} {percentage:.0}%{time_remaining})",
+            glyph = charging_glyph(info.animation_frame),
+            percentage = info.capacity,
+            time_remaining = time_remaining,
+        ),
+        _ => format!(
+            "({percentage:.0}%{time_remaining})",
+            percentage = info.capacity,
+            time_remaining = time_remaining,
+        ),
+    }
 }
 
 impl Battery<fn(BatteryInfo) -> String> {
@@ -72,7 +231,8 @@ impl Battery<fn(BatteryInfo) -> String> {
     ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Battery {
-                update_interval: Duration::from_secs(60),
+                update_interval: UPDATE_INTERVAL,
+                charging_interval: CHARGING_UPDATE_INTERVAL,
                 battery: battery.unwrap_or_else(|| "BAT0".into()),
                 attr,
                 warning_color,
@@ -89,8 +249,8 @@ impl<F: Fn(BatteryInfo) -> String + 'static> Battery<F> {
     ///
     ///  Creates a new `Battery` widget, whose text will be displayed with the
     ///  given [`Attributes`]. The caller can provide use the `warning_color`
-    ///  argument, to control the [`Color`] of the text once the battery has
-    ///  less than 10% charge remaining.
+    ///  argument, to control the [`Color`] of the text once the battery is
+    ///  discharging with 10% charge or less remaining.
     ///
     ///  The [`cnx::Cnx`] instance is borrowed during construction in order to get
     ///  access to handles of its event loop. However, it is not borrowed for
@@ -128,10 +288,42 @@ impl<F: Fn(BatteryInfo) -> String + 'static> Battery<F> {
         warning_color: Color,
         battery: Option<String>,
         render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_intervals(
+            attr,
+            warning_color,
+            battery,
+            UPDATE_INTERVAL,
+            CHARGING_UPDATE_INTERVAL,
+            render,
+        )
+    }
+
+    /// Creates a new Battery widget that updates every `charging_interval`
+    /// while charging and every `update_interval` otherwise, e.g. to poll
+    /// less often while charging if the `render` isn't animated, or more
+    /// often while discharging.
+    ///
+    /// [`Battery::new_with_render()`] uses 60 seconds and 1 second. The
+    /// interval is picked after each update, so a change in status takes
+    /// effect from the next update. Intervals shorter than 1 second (or 250ms
+    /// while charging) are raised to that.
+    pub fn new_with_intervals(
+        attr: Attributes,
+        warning_color: Color,
+        battery: Option<String>,
+        update_interval: Duration,
+        charging_interval: Duration,
+        render: F,
     ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Battery {
-                update_interval: Duration::from_secs(60),
+                update_interval: clamp_interval("Battery", update_interval, MIN_UPDATE_INTERVAL),
+                charging_interval: clamp_interval(
+                    "Battery",
+                    charging_interval,
+                    MIN_CHARGING_UPDATE_INTERVAL,
+                ),
                 battery: battery.unwrap_or_else(|| "BAT0".into()),
                 attr,
                 warning_color,
@@ -151,10 +343,7 @@ impl<F: Fn(BatteryInfo) -> String + 'static> Battery<F> {
         let mut file = File::open(path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        let s = FromStr::from_str(contents.trim())
-            .map_err(|e: <T as FromStr>::Err| e.into())
-            .context("Failed to parse value")?;
-        Ok(s)
+        parse_value(&contents)
     }
 
     fn load_value<T>(&self, file: &str) -> Result<T>
@@ -168,36 +357,213 @@ impl<F: Fn(BatteryInfo) -> String + 'static> Battery<F> {
         Ok(value)
     }
 
-    fn get_value(&self) -> Result<BatteryInfo> {
+    // Loads a value that not every battery reports, treating a missing or
+    // unparseable file as the value being unavailable.
+    fn load_optional_value<T>(&self, file: &str) -> Option<T>
+    where
+        T: FromStr,
+        <T as FromStr>::Err: Into<Error>,
+    {
+        self.load_value_inner(file).ok()
+    }
+
+    // Estimates the time remaining from the battery's energy (in µWh) and
+    // power (in µW), or failing that its charge (in µAh) and current (in
+    // µA), as drivers report one or the other.
+    fn load_time_remaining(
+        &self,
+        status: &Status,
+        charge_full: Option<u64>,
+        current_now: Option<i64>,
+    ) -> Option<Duration> {
+        let energy = (
+            self.load_optional_value("energy_now"),
+            self.load_optional_value("energy_full"),
+            self.load_optional_value("power_now"),
+        );
+        let (now, full, rate) = match energy {
+            (Some(now), Some(full), Some(rate)) => (now, full, rate),
+            _ => (
+                self.load_optional_value("charge_now")?,
+                charge_full?,
+                current_now?,
+            ),
+        };
+        estimate_time_remaining(status, now, full, rate)
+    }
+
+    fn get_value(&self, animation_frame: usize) -> Result<BatteryInfo> {
         let capacity: u8 = self.load_value("capacity")?;
         let status: Status = self.load_value("status")?;
-        Ok(BatteryInfo { capacity, status })
+        let current_now = self.load_optional_value("current_now");
+        let charge_full = self.load_optional_value("charge_full");
+        let time_remaining = self.load_time_remaining(&status, charge_full, current_now);
+        Ok(BatteryInfo {
+            capacity,
+            status,
+            animation_frame,
+            voltage_now: self.load_optional_value("voltage_now"),
+            current_now,
+            cycle_count: self.load_optional_value("cycle_count"),
+            charge_full,
+            charge_full_design: self.load_optional_value("charge_full_design"),
+            time_remaining,
+        })
     }
 
-    fn tick(&self) -> Result<Vec<Text>> {
-        let battery_info = self.get_value()?;
-
-        // If we're discharging and have <=10% left, then render with a
-        // special warning color.
+    fn tick(&self, battery_info: BatteryInfo, warning: bool) -> Vec<Text> {
+        // If we're warning about the battery, then render with a special
+        // warning color. Below 5%, blink as well.
         let mut attr = self.attr.clone();
-        if battery_info.status == Status::Discharging && battery_info.capacity <= 10 {
+        if warning {
             attr.fg_color = self.warning_color.clone()
         }
+        let blink = warning && battery_info.capacity < CRITICAL_CAPACITY;
 
         let text = (self.render)(battery_info);
 
-        Ok(vec![Text {
-            attr,
-            text,
-            stretch: false,
-            markup: self.markup,
-        }])
+        vec![Text::new(attr, text).markup(self.markup).blink(blink)]
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        let stream = stream! {
+            let mut animation_frame = 0;
+            let mut warning = false;
+            loop {
+                let battery_info = self.get_value(animation_frame);
+                let charging = matches!(
+                    battery_info,
+                    Ok(BatteryInfo { status: Status::Charging, .. })
+                );
+                yield battery_info.map(|info| {
+                    warning = should_warn(warning, &info);
+                    self.tick(info, warning)
+                });
+
+                let sleep_for = if charging {
+                    animation_frame = animation_frame.wrapping_add(1);
+                    self.charging_interval
+                } else {
+                    animation_frame = 0;
+                    self.update_interval
+                };
+                time::sleep(sleep_for).await;
+            }
+        };
 
         Ok(stream)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        estimate_time_remaining, format_time_remaining, parse_value, should_warn, BatteryInfo,
+        Status, WARNING_CAPACITY, WARNING_HYSTERESIS,
+    };
+    use std::time::Duration;
+
+    fn battery(status: Status, capacity: u8) -> BatteryInfo {
+        BatteryInfo {
+            status,
+            capacity,
+            animation_frame: 0,
+            voltage_now: None,
+            current_now: None,
+            cycle_count: None,
+            charge_full: None,
+            charge_full_design: None,
+            time_remaining: None,
+        }
+    }
+
+    #[test]
+    fn parses_plain_values() {
+        assert_eq!(parse_value::<u8>("87\n").unwrap(), 87);
+        assert_eq!(parse_value::<i64>("-1234000\n").unwrap(), -1234000);
+        assert_eq!(parse_value::<u64>("4987000\n").unwrap(), 4987000);
+        assert_eq!(parse_value::<Status>("Discharging\n").unwrap(), Status::Discharging);
+    }
+
+    #[test]
+    fn rounds_fractional_values() {
+        assert_eq!(parse_value::<u8>("87.6\n").unwrap(), 88);
+        assert_eq!(parse_value::<u8>("99.4\n").unwrap(), 99);
+        assert_eq!(parse_value::<i64>("-512.5\n").unwrap(), -513);
+    }
+
+    #[test]
+    fn strips_known_suffixes() {
+        assert_eq!(parse_value::<u8>("56%\n").unwrap(), 56);
+        assert_eq!(parse_value::<u8>("56 %\n").unwrap(), 56);
+        assert_eq!(parse_value::<u64>("12224000 uV\n").unwrap(), 12224000);
+        assert_eq!(parse_value::<u64>("3450000µAh\n").unwrap(), 3450000);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse_value::<u8>("\n").is_err());
+        assert!(parse_value::<u8>("N/A\n").is_err());
+        assert!(parse_value::<u8>("12 mV\n").is_err());
+        assert!(parse_value::<u8>("300\n").is_err());
+        assert!(parse_value::<Status>("Sleeping\n").is_err());
+    }
+
+    #[test]
+    fn estimates_time_remaining() {
+        let (now, full) = (30_000_000, 50_000_000);
+        let discharging = estimate_time_remaining(&Status::Discharging, now, full, -15_000_000);
+        assert_eq!(discharging, Some(Duration::from_secs(2 * 60 * 60)));
+        let charging = estimate_time_remaining(&Status::Charging, now, full, 40_000_000);
+        assert_eq!(charging, Some(Duration::from_secs(30 * 60)));
+        assert!(estimate_time_remaining(&Status::Discharging, now, full, 0).is_none());
+        assert!(estimate_time_remaining(&Status::Full, full, full, 1_000).is_none());
+    }
+
+    #[test]
+    fn formats_time_remaining() {
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        assert_eq!(format_time_remaining(minutes(80)), "1h20m");
+        assert_eq!(format_time_remaining(minutes(80) + minutes(1) / 2), "1h20m");
+        assert_eq!(format_time_remaining(minutes(3 * 60 + 5)), "3h05m");
+        assert_eq!(format_time_remaining(minutes(45)), "45m");
+    }
+
+    #[test]
+    fn warns_at_the_warning_capacity() {
+        let threshold = WARNING_CAPACITY;
+        assert!(should_warn(false, &battery(Status::Discharging, threshold)));
+        assert!(should_warn(false, &battery(Status::Discharging, 1)));
+        assert!(!should_warn(
+            false,
+            &battery(Status::Discharging, threshold + 1)
+        ));
+    }
+
+    #[test]
+    fn keeps_warning_until_the_hysteresis_is_passed() {
+        let limit = WARNING_CAPACITY + WARNING_HYSTERESIS;
+        assert!(should_warn(
+            true,
+            &battery(Status::Discharging, WARNING_CAPACITY + 1)
+        ));
+        assert!(should_warn(true, &battery(Status::Discharging, limit)));
+        assert!(!should_warn(true, &battery(Status::Discharging, limit + 1)));
+    }
+
+    #[test]
+    fn charging_ends_the_warning() {
+        assert!(!should_warn(true, &battery(Status::Charging, 1)));
+        assert!(!should_warn(true, &battery(Status::Full, WARNING_CAPACITY)));
+        assert!(!should_warn(false, &battery(Status::Charging, 1)));
+    }
+
+    #[test]
+    fn unknown_status_keeps_a_low_battery_warning() {
+        let limit = WARNING_CAPACITY + WARNING_HYSTERESIS;
+        assert!(should_warn(true, &battery(Status::Unknown, limit)));
+        assert!(!should_warn(true, &battery(Status::Unknown, limit + 1)));
+        assert!(!should_warn(true, &battery(Status::Unknown, 50)));
+        assert!(!should_warn(false, &battery(Status::Unknown, 1)));
+    }
+}
@@ -0,0 +1,263 @@
+use super::battery_linux::{render_default, should_warn, BatteryInfo, Status, CRITICAL_CAPACITY};
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use std::rc::Rc;
+use std::time::Duration;
+use tokio_stream::{Stream, StreamExt};
+use zbus::fdo::DBusProxy;
+use zbus::{Connection, Message, MessageStream, MessageType, Proxy};
+
+const UPOWER_SERVICE: &str = "org.freedesktop.UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+// A composite of all of the system's batteries, maintained by UPower.
+const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+// Converts a device's `State`. UPower's "empty" is treated as discharging,
+// and "pending charge" and "pending discharge" (i.e. plugged in, but not
+// charging) as unknown.
+fn status(state: u32) -> Status {
+    match state {
+        1 => Status::Charging,
+        2 | 3 => Status::Discharging,
+        4 => Status::Full,
+        _ => Status::Unknown,
+    }
+}
+
+// Converts a `TimeToEmpty` or `TimeToFull`, which are 0 if UPower can't
+// estimate them yet.
+fn time_remaining(seconds: i64) -> Option<Duration> {
+    if seconds > 0 {
+        Some(Duration::from_secs(seconds as u64))
+    } else {
+        None
+    }
+}
+
+async fn battery_info(proxy: &Proxy<'_>) -> Result<BatteryInfo> {
+    let percentage: f64 = proxy.get_property("Percentage").await?;
+    let status = status(proxy.get_property("State").await?);
+    let voltage: f64 = proxy.get_property("Voltage").await?;
+    let time_remaining = match status {
+        Status::Charging => time_remaining(proxy.get_property("TimeToFull").await?),
+        Status::Discharging => time_remaining(proxy.get_property("TimeToEmpty").await?),
+        Status::Full | Status::Unknown => None,
+    };
+    Ok(BatteryInfo {
+        status,
+        capacity: percentage.max(0.0).min(100.0).round() as u8,
+        animation_frame: 0,
+        voltage_now: Some((voltage * 1_000_000.0) as u64).filter(|&voltage| voltage > 0),
+        current_now: None,
+        cycle_count: None,
+        charge_full: None,
+        charge_full_design: None,
+        time_remaining,
+    })
+}
+
+fn is_relevant(message: &Message) -> bool {
+    message.message_type() == MessageType::Signal
+        && matches!(message.member(), Ok(Some(member)) if member.as_str() == "PropertiesChanged")
+}
+
+/// Shows battery charge percentage, read from [`UPower`] over D-Bus.
+///
+/// This is an alternative to the [`Battery`] widget, which polls sysfs,
+/// for systems that run UPower. Rather than polling, it listens for UPower
+/// to signal that the battery has changed, so it updates as soon as the
+/// battery starts or stops charging. It takes the same render closure,
+/// which is passed the same [`BatteryInfo`], and warns about a low battery
+/// in the same way.
+///
+/// UPower doesn't report the battery's current, charge cycles or charge
+/// capacity, so those fields of [`BatteryInfo`] are always `None`. As the
+/// widget only updates when UPower signals a change, the charging glyph
+/// isn't animated.
+///
+/// If UPower isn't running, the widget shows an error, and tries again
+/// later. Use [`Battery`] on systems without it.
+///
+/// [`UPower`]: https://upower.freedesktop.org/
+/// [`Battery`]: super::Battery
+pub struct UPowerBattery<F: Fn(BatteryInfo) -> String> {
+    attr: Attributes,
+    warning_color: Color,
+    // The object path of the UPower device to show.
+    device: String,
+    render: Rc<F>,
+    markup: bool,
+}
+
+// The widget is restarted if the D-Bus connection fails, so it must be
+// `Clone` however `render` is.
+impl<F: Fn(BatteryInfo) -> String> Clone for UPowerBattery<F> {
+    fn clone(&self) -> Self {
+        UPowerBattery {
+            attr: self.attr.clone(),
+            warning_color: self.warning_color.clone(),
+            device: self.device.clone(),
+            render: Rc::clone(&self.render),
+            markup: self.markup,
+        }
+    }
+}
+
+// The object path of the UPower device for the battery named `battery`
+// (e.g. `BAT0`), or of the composite of all batteries.
+fn device_path(battery: Option<String>) -> String {
+    match battery {
+        Some(battery) => format!("/org/freedesktop/UPower/devices/battery_{}", battery),
+        None => DISPLAY_DEVICE.to_owned(),
+    }
+}
+
+impl UPowerBattery<fn(BatteryInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        warning_color: Color,
+        battery: Option<String>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            UPowerBattery {
+                attr,
+                warning_color,
+                device: device_path(battery),
+                render: Rc::new(render_default),
+                markup: false,
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+}
+
+impl<F: Fn(BatteryInfo) -> String + 'static> UPowerBattery<F> {
+    /// Creates a new [`UPowerBattery`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `warning_color` - The color of the text once the battery is
+    /// discharging with 10% charge or less remaining.
+    ///
+    /// * `battery` - The battery to show, by its name in sysfs, e.g. `BAT0`.
+    /// Defaults to UPower's composite of all of the system's batteries.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`BatteryInfo`] holds the battery's status.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// # use cnx_contrib::widgets::battery::*;
+    /// let render = |info: BatteryInfo| format!("🔋 {}%", info.capacity);
+    /// let battery =
+    ///     UPowerBattery::new_with_render(Attributes::default(), Color::red(), None, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        warning_color: Color,
+        battery: Option<String>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            UPowerBattery {
+                attr,
+                warning_color,
+                device: device_path(battery),
+                render: Rc::new(render),
+                markup: true,
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn tick(&self, battery_info: BatteryInfo, warning: bool) -> Vec<Text> {
+        let mut attr = self.attr.clone();
+        if warning {
+            attr.fg_color = self.warning_color.clone()
+        }
+        let blink = warning && battery_info.capacity < CRITICAL_CAPACITY;
+
+        vec![Text::new(attr, (self.render)(battery_info))
+            .markup(self.markup)
+            .blink(blink)]
+    }
+
+    async fn connect(&self) -> Result<(Proxy<'_>, MessageStream)> {
+        let connection = Connection::system()
+            .await
+            .context("Failed to connect to the D-Bus system bus")?;
+        let bus = DBusProxy::new(&connection).await?;
+        let rule = format!(
+            "type='signal',sender='{}',interface='org.freedesktop.DBus.Properties',\
+             member='PropertiesChanged',path='{}'",
+            UPOWER_SERVICE, self.device
+        );
+        bus.add_match(&rule).await?;
+        let messages = MessageStream::from(&connection);
+        let proxy = Proxy::new(
+            &connection,
+            UPOWER_SERVICE,
+            self.device.as_str(),
+            DEVICE_INTERFACE,
+        )
+        .await?;
+        Ok((proxy, messages))
+    }
+
+    async fn battery_info(&self, proxy: &Proxy<'_>) -> Result<BatteryInfo> {
+        battery_info(proxy).await.with_context(|| {
+            format!(
+                "Failed to read {} from UPower. Is the upower service running?",
+                self.device
+            )
+        })
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // Errors end the stream, and the widget is restarted.
+        let stream = stream! {
+            let (proxy, mut messages) = match self.connect().await {
+                Ok(connected) => connected,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+            let mut warning = false;
+            loop {
+                match self.battery_info(&proxy).await {
+                    Ok(info) => {
+                        warning = should_warn(warning, &info);
+                        yield Ok(self.tick(info, warning));
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+                // Wait until the battery changes.
+                loop {
+                    match messages.next().await {
+                        Some(Ok(message)) if is_relevant(&message) => break,
+                        Some(Ok(_)) => continue,
+                        Some(Err(err)) => {
+                            yield Err(err.into());
+                            return;
+                        }
+                        None => return,
+                    }
+                }
+            }
+        };
+        Ok(stream)
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::process;
+use tokio::time::{self, MissedTickBehavior};
+use tokio_stream::Stream;
+
+// The shortest interval allowed, so that a typo can't fork a process many
+// times a second.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The output of a run of a [`Command`] widget's command, passed to its
+/// render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// How the command exited. This is always successful, as unsuccessful
+    /// runs are shown as errors instead.
+    pub status: ExitStatus,
+}
+
+/// Shows the output of a program, run periodically, e.g. a script that
+/// prints the number of pending updates.
+///
+/// The program is run directly rather than through a shell, so to use
+/// shell features run `sh` with `-c` and a command line as its arguments.
+/// If it exits unsuccessfully, an error is shown (and logged) until the next
+/// run. A run that takes longer than the interval delays the next one,
+/// rather than overlapping it.
+pub struct Command<F: Fn(CommandOutput) -> String> {
+    attr: Attributes,
+    program: String,
+    args: Vec<String>,
+    interval: Duration,
+    render: F,
+}
+
+fn default_render(output: CommandOutput) -> String {
+    output.stdout.trim().to_owned()
+}
+
+impl Command<fn(CommandOutput) -> String> {
+    /// Creates a new [`Command`] widget, which shows the trimmed standard
+    /// output of `program` run with `args` every `interval`. The output is
+    /// Pango markup, so the program can style it, but must escape `&`, `<`
+    /// and `>`.
+    pub fn new(
+        attr: Attributes,
+        program: String,
+        args: Vec<String>,
+        interval: Duration,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Command::new_with_render(attr, program, args, interval, default_render)
+    }
+}
+
+impl<F: Fn(CommandOutput) -> String + 'static> Command<F> {
+    /// Creates a new [`Command`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `program` - The program to run, e.g. `checkupdates`. It's looked
+    /// up in `PATH`.
+    ///
+    /// * `args` - The arguments to run `program` with.
+    ///
+    /// * `interval` - How often to run `program`. Intervals shorter than 1
+    /// second are raised to that.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`CommandOutput`] holds the program's output.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::command::*;
+    /// let render = |output: CommandOutput| format!("{} updates", output.stdout.lines().count());
+    /// let updates = Command::new_with_render(
+    ///     Attributes::default(),
+    ///     "checkupdates".into(),
+    ///     vec![],
+    ///     Duration::from_secs(60 * 60),
+    ///     render,
+    /// );
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        program: String,
+        args: Vec<String>,
+        interval: Duration,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Command {
+                attr,
+                program,
+                args,
+                interval: clamp_interval("Command", interval, MIN_INTERVAL),
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    async fn run(&self) -> Result<CommandOutput> {
+        let output = process::Command::new(&self.program)
+            .args(&self.args)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {}", self.program))?;
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} exited with {}: {}",
+                self.program,
+                output.status,
+                stderr.trim()
+            ));
+        }
+        Ok(CommandOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr,
+            status: output.status,
+        })
+    }
+
+    async fn tick(&self) -> Result<Vec<Text>> {
+        let text = (self.render)(self.run().await?);
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let mut interval = time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let stream = stream! {
+            loop {
+                interval.tick().await;
+                yield self.tick().await;
+            }
+        };
+        Ok(stream)
+    }
+}
@@ -1,6 +1,8 @@
+use crate::format::FormatOptions;
 use anyhow::{anyhow, Result};
 use cnx::text::{Attributes, Text};
 use cnx::widgets::{WidgetStream, WidgetStreamI};
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -9,24 +11,57 @@ use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
 use tokio_stream::{StreamExt, Stream};
 
+/// The CPU usage passed to a [`Cpu`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpuInfo {
+    /// The current CPU usage, in percent.
+    pub usage: u8,
+    /// The current CPU usage, in percent, before it is rounded down to
+    /// `usage`.
+    pub percentage: f64,
+    /// The most recent usage samples, oldest first and including `usage`.
+    /// This holds up to the `history` passed to the widget, and is empty if
+    /// that was 0.
+    pub history: VecDeque<u8>,
+    /// The formatting options the widget was created with, e.g. to format
+    /// the usage with [`FormatOptions::percentage()`].
+    pub format: FormatOptions,
+}
+
 /// Represents CPU widget used to show current CPU consumptiong
-pub struct Cpu<F: Fn(u64) -> String> {
+pub struct Cpu<F: Fn(CpuInfo) -> String> {
     attr: Attributes,
     cpu_data: CpuData,
+    history: usize,
+    samples: VecDeque<u8>,
+    format: FormatOptions,
     render: F,
 }
 
-fn default_render(usage: u64) -> String {
-    format!("{} %", usage)
+fn default_render(info: CpuInfo) -> String {
+    info.format.percentage(info.percentage)
 }
 
-impl Cpu<fn(u64) -> String> {
-    pub fn new(attr: Attributes) -> Result<WidgetStream<Self, impl Stream<Item = WidgetStreamI>>> {
+impl Cpu<fn(CpuInfo) -> String> {
+    pub fn new(attr: Attributes, history: usize) -> Result<WidgetStream<Self, impl Stream<Item = WidgetStreamI>>> {
+        Self::new_with_format(attr, history, FormatOptions::default())
+    }
+
+    /// Creates a new [`Cpu`] widget that shows the usage formatted with
+    /// `format`, e.g. with a decimal place.
+    pub fn new_with_format(
+        attr: Attributes,
+        history: usize,
+        format: FormatOptions,
+    ) -> Result<WidgetStream<Self, impl Stream<Item = WidgetStreamI>>> {
         let cpu_data = CpuData::get_values()?;
         Ok(WidgetStream::new(
             Cpu {
                 attr,
                 cpu_data,
+                history,
+                samples: VecDeque::with_capacity(history),
+                format,
                 render: default_render
             },
             Self::into_stream
@@ -34,7 +69,7 @@ impl Cpu<fn(u64) -> String> {
     }
 }
 
-impl<F: Fn(u64) -> String + 'static> Cpu<F> {
+impl<F: Fn(CpuInfo) -> String + 'static> Cpu<F> {
     /// Creates a new [`Cpu`] widget.
     ///
     /// Arguments
@@ -42,8 +77,14 @@ impl<F: Fn(u64) -> String + 'static> Cpu<F> {
     /// * `attr` - Represents `Attributes` which controls properties like
     /// `Font`, foreground and background color etc.
     ///
+    /// * `history` - How many of the most recent samples to keep in
+    /// [`CpuInfo::history`], e.g. to draw a graph. Use 0 to keep none.
+    ///
+    /// * `format` - How the usage is formatted, e.g. with a decimal place.
+    /// It's passed to `render` as [`CpuInfo::format`].
+    ///
     /// * `render` - We use the closure to control the way output is
-    /// displayed in the bar. `u64` represents the current CPU usage
+    /// displayed in the bar. [`CpuInfo`] holds the current CPU usage
     /// in percentage.
     ///
     /// # Examples
@@ -66,17 +107,25 @@ impl<F: Fn(u64) -> String + 'static> Cpu<F> {
     /// };
     ///
     /// let mut cnx = Cnx::new(Position::Top);
-    /// cnx.add_widget(Cpu::new(attr, None)?);
+    /// cnx.add_widget(Cpu::new(attr, 0)?);
     /// # Ok(())
     /// # }
     /// # fn main() { run().unwrap(); }
     /// ```
-    pub fn new_with_render(attr: Attributes, render: F) -> Result<WidgetStream<Self, impl Stream<Item = WidgetStreamI>>> {
+    pub fn new_with_render(
+        attr: Attributes,
+        history: usize,
+        format: FormatOptions,
+        render: F,
+    ) -> Result<WidgetStream<Self, impl Stream<Item = WidgetStreamI>>> {
         let cpu_data = CpuData::get_values()?;
         Ok(WidgetStream::new(
             Cpu {
                 attr,
                 cpu_data,
+                history,
+                samples: VecDeque::with_capacity(history),
+                format,
                 render,
             },
             Self::into_stream
@@ -100,15 +149,21 @@ impl<F: Fn(u64) -> String + 'static> Cpu<F> {
             _ => (current.total_time - previous.total_time) as f64 / diff_total as f64,
         };
 
-        let cpu_usage = (percentage * 100.0) as u64;
-        let text = (self.render)(cpu_usage);
+        let usage = (percentage * 100.0) as u8;
+        if self.history > 0 {
+            if self.samples.len() == self.history {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(usage);
+        }
+        let text = (self.render)(CpuInfo {
+            usage,
+            percentage: percentage * 100.0,
+            history: self.samples.clone(),
+            format: self.format,
+        });
         self.cpu_data = current;
-        let texts = vec![Text {
-            attr: self.attr.clone(),
-            text,
-            stretch: false,
-            markup: true,
-        }];
+        let texts = vec![Text::new(self.attr.clone(), text).markup(true)];
         Ok(texts)
     }
 
@@ -1,65 +1,193 @@
-use anyhow::Result;
-use byte_unit::{Byte, ByteUnit};
-use cnx::text::{Attributes, Text};
-use cnx::widgets::{WidgetStream, WidgetStreamI};
+use crate::format::FormatOptions;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use byte_unit::Byte;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
 use nix::sys::statvfs::statvfs;
 use std::time::Duration;
-use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::{StreamExt, Stream};
+use tokio::task;
+use tokio::time::{self, MissedTickBehavior};
+use tokio_stream::Stream;
+
+// How often to update by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// The shortest interval allowed.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// How long to wait for a filesystem's usage before giving up on it, e.g.
+// because it's an unreachable network mount.
+const STATVFS_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Represent Information about the mounted filesystem
 #[derive(Debug)]
 pub struct DiskInfo {
+    /// The path the widget was given for this filesystem, e.g. `/home`.
+    pub path: String,
     /// Total size of the filesystem
     pub total: Byte,
     /// Total used space of the filesystem
     pub used: Byte,
     /// Total free space of the filesystem
     pub free: Byte,
+    /// The percentage of the filesystem in use. As with `df`, this is of
+    /// the space available to ordinary users, so excludes space reserved
+    /// for root.
+    pub percent: f32,
+    /// The formatting options the widget was created with, e.g. to format
+    /// the sizes with [`FormatOptions::bytes()`].
+    pub format: FormatOptions,
 }
 
 impl DiskInfo {
-    fn new(path: &str) -> Result<Self> {
-        let stat = statvfs(path)?;
+    fn new(path: &str, format: FormatOptions) -> Result<Self> {
+        let stat =
+            statvfs(path).with_context(|| format!("Failed to read the disk usage of {}", path))?;
         let total_size = stat.blocks() * stat.fragment_size();
         let used = (stat.blocks() - stat.blocks_free()) * stat.fragment_size();
         let available = stat.blocks_available() * stat.fragment_size();
         let total = byte_unit::Byte::from_bytes(total_size as u128);
         let used = byte_unit::Byte::from_bytes(used as u128);
         let free: Byte = byte_unit::Byte::from_bytes(available as u128);
+        let usable = used.get_bytes() + free.get_bytes();
+        let percent = if usable == 0 {
+            0.0
+        } else {
+            used.get_bytes() as f32 / usable as f32 * 100.0
+        };
 
-        let disk_info = DiskInfo { total, used, free };
+        let disk_info = DiskInfo {
+            path: path.to_owned(),
+            total,
+            used,
+            free,
+            percent,
+            format,
+        };
         Ok(disk_info)
     }
+
+    // Reads the usage like `new()`, but on a blocking thread, as `statvfs()`
+    // can block for a long time (e.g. on a hung NFS mount), and gives up
+    // after `STATVFS_TIMEOUT`.
+    async fn load(path: String, format: FormatOptions) -> Result<Self> {
+        let owned = path.clone();
+        let task = task::spawn_blocking(move || DiskInfo::new(&owned, format));
+        match time::timeout(STATVFS_TIMEOUT, task).await {
+            Ok(result) => result.context("Failed to read disk usage")?,
+            Err(_) => Err(anyhow!("Timed out reading the disk usage of {}", path)),
+        }
+    }
+}
+
+/// Options controlling what the [`DiskUsage`] widget shows.
+///
+/// Set the options you need and take the rest from the defaults:
+///
+/// ```
+/// # use cnx::text::Color;
+/// # use cnx_contrib::widgets::disk_usage::DiskUsageOptions;
+/// let options = DiskUsageOptions {
+///     paths: vec!["/".into(), "/home".into()],
+///     warning: Some((90.0, Color::red())),
+///     ..DiskUsageOptions::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct DiskUsageOptions {
+    /// Pathnames of any file within each of the mounted filesystems to
+    /// show, e.g. `/`, `/home` and `/data`. The widget shows a [`Text`] for
+    /// each of them, in order. Defaults to `/`.
+    pub paths: Vec<String>,
+    /// How often to update the usage, by default every hour. Set it shorter
+    /// to e.g. watch a scratch disk that fills up quickly. Intervals shorter
+    /// than 1 second are raised to that.
+    ///
+    /// Reading the usage of a network filesystem (e.g. NFS) can block while
+    /// the server is slow or unreachable. This doesn't stall the bar, but the
+    /// widget shows an error if it takes longer than 10 seconds, and with a
+    /// short interval this may keep a thread busy waiting on the server.
+    pub update_interval: Duration,
+    /// How sizes are formatted, e.g. in GB rather than GiB. It's passed to
+    /// the render closure as [`DiskInfo::format`].
+    pub format: FormatOptions,
+    /// The percentage in use (see [`DiskInfo::percent`]) at or above which
+    /// to show a filesystem in a warning color, and the color, e.g.
+    /// `Some((90.0, Color::red()))`. There's no warning if this is `None`,
+    /// the default.
+    pub warning: Option<(f32, Color)>,
+}
+
+impl Default for DiskUsageOptions {
+    fn default() -> Self {
+        DiskUsageOptions {
+            paths: vec!["/".to_owned()],
+            update_interval: UPDATE_INTERVAL,
+            format: FormatOptions::default(),
+            warning: None,
+        }
+    }
 }
 
 /// Disk usage widget to show current usage and remaining free space
 /// in the mounted filesystem.
+///
+/// Several filesystems can be shown by one widget, see
+/// [`DiskUsageOptions::paths`]. Filesystems that are getting full can be
+/// shown in a warning color, see [`DiskUsageOptions::warning`]. A filesystem
+/// that can't be read (e.g. an unmounted network share) is shown greyed-out
+/// as `<path>: ?`, while the others are still shown.
+///
+/// The usage is updated every hour by default, see
+/// [`DiskUsageOptions::update_interval`].
 pub struct DiskUsage<F: Fn(DiskInfo) -> String> {
     attr: Attributes,
-    path: String,
+    paths: Vec<String>,
+    update_interval: Duration,
+    format: FormatOptions,
+    // The percentage used at or above which to warn, and the color to warn
+    // with.
+    warning: Option<(f32, Color)>,
     render: F,
 }
 
+// Whether to warn about a filesystem that is `percent` used.
+fn should_warn(percent: f32, warning_threshold: Option<f32>) -> bool {
+    warning_threshold.map_or(false, |threshold| percent >= threshold)
+}
+
 fn default_render(info: DiskInfo) -> String {
     format!(
         "Disk: {}/{}",
-        info.used.get_adjusted_unit(ByteUnit::GiB).format(0),
-        info.total.get_adjusted_unit(ByteUnit::GiB).format(0)
+        info.format.bytes(info.used.get_bytes()),
+        info.format.bytes(info.total.get_bytes())
+    )
+}
+
+// As `default_render`, but naming the filesystem, for widgets showing more
+// than one.
+fn default_render_with_path(info: DiskInfo) -> String {
+    format!(
+        "{}: {}/{}",
+        info.path,
+        info.format.bytes(info.used.get_bytes()),
+        info.format.bytes(info.total.get_bytes())
     )
 }
 
 impl DiskUsage<fn(DiskInfo) -> String> {
-    pub fn new(attr: Attributes, path: String) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
-        WidgetStream::new(
-            Self {
-                attr,
-                path,
-                render: default_render
-            },
-            Self::into_stream
-        )
+    /// Creates a new [`DiskUsage`] widget showing the filesystems in
+    /// `options`, e.g. `Disk: 12 GiB/50 GiB`. If there's more than one, each
+    /// is prefixed with its path instead, e.g. `/home: 12 GiB/50 GiB`.
+    pub fn new(
+        attr: Attributes,
+        options: DiskUsageOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        let render: fn(DiskInfo) -> String = if options.paths.len() > 1 {
+            default_render_with_path
+        } else {
+            default_render
+        };
+        Self::new_with_render(attr, options, render)
     }
 }
 
@@ -71,11 +199,13 @@ impl<F: Fn(DiskInfo) -> String + 'static> DiskUsage<F> {
     /// * `attr` - Represents `Attributes` which controls properties like
     /// `Font`, foreground and background color etc.
     ///
-    /// * `path` - Pathname of any file within the mounted filesystem.
-
+    /// * `options` - Which filesystems to show, and how, see
+    /// [`DiskUsageOptions`].
+    ///
     /// * `render` - We use the closure to control the way output is
     /// displayed in the bar. [`DiskInfo`] represents the details
-    /// about the mounted filesystem.
+    /// about the mounted filesystem. It's called for each filesystem, which
+    /// can be told apart by [`DiskInfo::path`].
     ///
     /// # Examples
     ///
@@ -95,42 +225,122 @@ impl<F: Fn(DiskInfo) -> String + 'static> DiskUsage<F> {
     ///     bg_color: None,
     ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
     /// };
+    /// let options = DiskUsageOptions {
+    ///     paths: vec!["/".into(), "/home".into(), "/data".into()],
+    ///     ..DiskUsageOptions::default()
+    /// };
+    /// let render = |info: DiskInfo| {
+    ///     let free = info.format.bytes(info.free.get_bytes());
+    ///     format!("{} {} free", info.path, free)
+    /// };
     ///
     /// let mut cnx = Cnx::new(Position::Top);
-    /// cnx.add_widget(DiskUsage::new(attr, "/home".into(), None));
+    /// cnx.add_widget(DiskUsage::new_with_render(attr, options, render));
     /// # Ok(())
     /// # }
     /// # fn main() { run().unwrap(); }
     /// ```
     pub fn new_with_render(
         attr: Attributes,
-        path: String,
+        options: DiskUsageOptions,
         render: F,
     ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
-            Self { attr, render, path },
+            Self {
+                attr,
+                render,
+                paths: options.paths,
+                update_interval: clamp_interval(
+                    "DiskUsage",
+                    options.update_interval,
+                    MIN_UPDATE_INTERVAL,
+                ),
+                format: options.format,
+                warning: options.warning,
+            },
             Self::into_stream
         )
     }
 
-    fn tick(&self) -> Result<Vec<Text>> {
-        let disk_info = DiskInfo::new(self.path.as_ref())?;
-
-        let text: String = (self.render)(disk_info);
-        let texts = vec![Text {
-            attr: self.attr.clone(),
-            text,
-            stretch: false,
-            markup: true,
-        }];
-        Ok(texts)
+    // Every filesystem is read at once, so that a slow one (e.g. a hung
+    // network mount) doesn't hold up the others. A filesystem that can't be
+    // read is shown dimmed, as `<path>: ?`, unless none of them can be, in
+    // which case the error is returned.
+    async fn tick(&self) -> Result<Vec<Text>> {
+        let loads: Vec<_> = self
+            .paths
+            .iter()
+            .map(|path| task::spawn(DiskInfo::load(path.clone(), self.format)))
+            .collect();
+        let mut texts = Vec::with_capacity(loads.len());
+        let mut first_error = None;
+        let mut failed = 0;
+        for (path, load) in self.paths.iter().zip(loads) {
+            let result = load
+                .await
+                .context("Failed to read disk usage")
+                .and_then(|result| result);
+            match result {
+                Ok(disk_info) => texts.push(self.text(disk_info)),
+                Err(err) => {
+                    println!("Error reading disk usage: {:#}", err);
+                    let text = format!("{}: ?", path);
+                    texts.push(Text::new(self.attr.clone(), text).dim(true));
+                    first_error.get_or_insert(err);
+                    failed += 1;
+                }
+            }
+        }
+        match first_error {
+            Some(err) if failed == texts.len() => Err(err),
+            _ => Ok(texts),
+        }
+    }
+
+    fn text(&self, disk_info: DiskInfo) -> Text {
+        let mut attr = self.attr.clone();
+        if let Some((threshold, color)) = &self.warning {
+            if should_warn(disk_info.percent, Some(*threshold)) {
+                attr.fg_color = color.clone();
+            }
+        }
+        Text::new(attr, (self.render)(disk_info)).markup(true)
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        let one_hour = Duration::from_secs(3600);
-        let interval = time::interval(one_hour);
-        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        let mut interval = time::interval(self.update_interval);
+        // A slow update delays the next one, rather than them piling up.
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let stream = stream! {
+            loop {
+                interval.tick().await;
+                yield self.tick().await;
+            }
+        };
 
         Ok(stream)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::should_warn;
+
+    #[test]
+    fn warns_at_the_threshold() {
+        assert!(should_warn(90.0, Some(90.0)));
+        assert!(should_warn(90.1, Some(90.0)));
+        assert!(should_warn(100.0, Some(90.0)));
+    }
+
+    #[test]
+    fn does_not_warn_below_the_threshold() {
+        assert!(!should_warn(89.9, Some(90.0)));
+        assert!(!should_warn(0.0, Some(90.0)));
+    }
+
+    #[test]
+    fn does_not_warn_without_a_threshold() {
+        assert!(!should_warn(100.0, None));
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use inotify::{Inotify, WatchMask};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tokio_stream::{Stream, StreamExt};
+
+/// Shows the contents of a file, e.g. a status written by a script, and
+/// updates whenever the file changes.
+///
+/// The file is watched with inotify rather than polled. Its directory is
+/// watched rather than the file itself, so that the widget keeps working
+/// when the file is replaced (e.g. written to a temporary file and renamed
+/// over it, as many programs do to update files atomically), deleted or
+/// created. While the file is missing, an error is shown.
+pub struct FileWatch<F: Fn(String) -> String> {
+    attr: Attributes,
+    path: PathBuf,
+    render: Rc<F>,
+}
+
+// The widget is restarted if the file's directory can't be watched (e.g.
+// before it's created), so it must be `Clone` however `render` is.
+impl<F: Fn(String) -> String> Clone for FileWatch<F> {
+    fn clone(&self) -> Self {
+        FileWatch {
+            attr: self.attr.clone(),
+            path: self.path.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+impl FileWatch<fn(String) -> String> {
+    /// Creates a new [`FileWatch`] widget that shows the trimmed contents
+    /// of the file at `path`. The contents are Pango markup, so `&`, `<` and
+    /// `>` must be escaped.
+    pub fn new(
+        attr: Attributes,
+        path: PathBuf,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        FileWatch::new_with_render(attr, path, |contents| contents)
+    }
+}
+
+impl<F: Fn(String) -> String + 'static> FileWatch<F> {
+    /// Creates a new [`FileWatch`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `path` - The file to show.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It's passed the file's trimmed contents.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::file::*;
+    /// let render = |contents: String| format!("Build: {}", contents.lines().next().unwrap_or(""));
+    /// let build = FileWatch::new_with_render(Attributes::default(), "/tmp/build-status".into(), render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        path: PathBuf,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            FileWatch {
+                attr,
+                path,
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let text = (self.render)(contents.trim().to_owned());
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let name = self
+            .path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} is not a file", self.path.display()))?
+            .to_owned();
+        let dir = match self.path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+
+        let mut inotify = Inotify::init().context("Failed to initialise inotify")?;
+        let mask = WatchMask::CLOSE_WRITE
+            | WatchMask::MODIFY
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO;
+        inotify
+            .add_watch(dir, mask)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        let events = inotify
+            .event_stream([0; 1024])
+            .context("Failed to create inotify stream")?;
+
+        // Show the contents straight away, then again after each change to
+        // the file (but not to others in the same directory).
+        let changes = events.filter_map(move |event| match event {
+            Ok(event) if event.name.as_deref() != Some(name.as_os_str()) => None,
+            Ok(_) => Some(Ok(())),
+            Err(err) => Some(Err(err)),
+        });
+        let stream = tokio_stream::once(Ok(())).chain(changes).map(move |event| {
+            event.context("Failed to read inotify event")?;
+            self.tick()
+        });
+        Ok(stream)
+    }
+}
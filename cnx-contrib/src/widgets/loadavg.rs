@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::fs;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+// How often to update by default. The kernel only recalculates the load
+// averages every 5 seconds.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The load averages passed to a [`LoadAverage`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadInfo {
+    /// The load average over the last minute.
+    pub one: f64,
+    /// The load average over the last 5 minutes.
+    pub five: f64,
+    /// The load average over the last 15 minutes.
+    pub fifteen: f64,
+    /// The number of CPUs, to put the load in context: a load above this
+    /// means that processes are waiting for a CPU.
+    pub cpu_count: usize,
+}
+
+// Parses the first three fields of `/proc/loadavg`, e.g.
+// `0.42 0.55 0.60 1/467 12345`.
+fn parse_loadavg(contents: &str, cpu_count: usize) -> Result<LoadInfo> {
+    let mut fields = contents.split_whitespace().map(str::parse::<f64>);
+    let mut next = || -> Result<f64> {
+        let field = fields
+            .next()
+            .ok_or_else(|| anyhow!("Missing data in /proc/loadavg"))?;
+        field.context("Failed to parse /proc/loadavg")
+    };
+    Ok(LoadInfo {
+        one: next()?,
+        five: next()?,
+        fifteen: next()?,
+        cpu_count,
+    })
+}
+
+// Counts the CPUs listed in `/proc/cpuinfo`, assuming one if it can't be read.
+fn cpu_count() -> usize {
+    fs::read_to_string("/proc/cpuinfo")
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| line.starts_with("processor"))
+                .count()
+        })
+        .unwrap_or(0)
+        .max(1)
+}
+
+/// Shows the system load averages, e.g. `0.42 0.55 0.60`.
+///
+/// The load averages are read from `/proc/loadavg`, every 5 seconds by
+/// default.
+pub struct LoadAverage<F: Fn(LoadInfo) -> String> {
+    attr: Attributes,
+    update_interval: Duration,
+    cpu_count: usize,
+    render: F,
+}
+
+fn default_render(info: LoadInfo) -> String {
+    format!("{:.2} {:.2} {:.2}", info.one, info.five, info.fifteen)
+}
+
+impl LoadAverage<fn(LoadInfo) -> String> {
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, UPDATE_INTERVAL, default_render)
+    }
+}
+
+impl<F: Fn(LoadInfo) -> String + 'static> LoadAverage<F> {
+    /// Creates a new [`LoadAverage`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`LoadInfo`] holds the load averages and the
+    /// number of CPUs.
+    ///
+    /// # Examples
+    ///
+    /// Show the load in red when there are more runnable processes than
+    /// CPUs:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// # use cnx_contrib::widgets::loadavg::*;
+    /// let render = |info: LoadInfo| {
+    ///     let color = if info.one > info.cpu_count as f64 {
+    ///         Color::red()
+    ///     } else {
+    ///         Color::white()
+    ///     };
+    ///     format!("<span foreground=\"{}\">{:.2}</span>", color.to_hex(), info.one)
+    /// };
+    /// let load = LoadAverage::new_with_render(Attributes::default(), render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, UPDATE_INTERVAL, render)
+    }
+
+    /// Creates a new [`LoadAverage`] widget that updates every
+    /// `update_interval` rather than every 5 seconds. Intervals shorter than
+    /// 1 second are raised to that.
+    pub fn new_with_interval(
+        attr: Attributes,
+        update_interval: Duration,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            LoadAverage {
+                attr,
+                update_interval: clamp_interval(
+                    "LoadAverage",
+                    update_interval,
+                    MIN_UPDATE_INTERVAL,
+                ),
+                cpu_count: cpu_count(),
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let contents =
+            fs::read_to_string("/proc/loadavg").context("Failed to read /proc/loadavg")?;
+        let text = (self.render)(parse_loadavg(&contents, self.cpu_count)?);
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        Ok(stream)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::format::{FormatOptions, Units};
+use anyhow::{anyhow, Context, Result};
+use byte_unit::Byte;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::fs;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+// How often to update by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+// The shortest interval allowed, as reading /proc isn't free.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The memory usage passed to a [`Memory`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryInfo {
+    /// Total usable RAM.
+    pub total: Byte,
+    /// RAM in use, as `total - available`. This matches the "used" column
+    /// of `free`.
+    pub used: Byte,
+    /// RAM that is completely unused.
+    pub free: Byte,
+    /// RAM available for starting new applications without swapping,
+    /// including caches that can be reclaimed.
+    pub available: Byte,
+    /// Total swap space. This is zero if the system has no swap.
+    pub swap_total: Byte,
+    /// Swap space in use.
+    pub swap_used: Byte,
+    /// The formatting options the widget was created with, e.g. to format
+    /// the sizes with [`FormatOptions::bytes()`].
+    pub format: FormatOptions,
+}
+
+// Parses the contents of `/proc/meminfo`, whose values are in KiB.
+pub(crate) fn parse_meminfo(contents: &str, format: FormatOptions) -> Result<MemoryInfo> {
+    let field = |name: &str| -> Option<u128> {
+        contents.lines().find_map(|line| {
+            let value = line.strip_prefix(name)?.strip_prefix(':')?;
+            let kib: u128 = value.trim().trim_end_matches("kB").trim().parse().ok()?;
+            Some(kib * 1024)
+        })
+    };
+    let required =
+        |name: &str| field(name).ok_or_else(|| anyhow!("Missing {} in /proc/meminfo", name));
+
+    let total = required("MemTotal")?;
+    let free = required("MemFree")?;
+    // Kernels before 3.14 don't report MemAvailable, so estimate it as
+    // `free` does.
+    let available = match field("MemAvailable") {
+        Some(available) => available,
+        None => free + field("Buffers").unwrap_or(0) + field("Cached").unwrap_or(0),
+    };
+    let swap_total = field("SwapTotal").unwrap_or(0);
+    let swap_free = field("SwapFree").unwrap_or(swap_total);
+
+    Ok(MemoryInfo {
+        total: Byte::from_bytes(total),
+        used: Byte::from_bytes(total.saturating_sub(available)),
+        free: Byte::from_bytes(free),
+        available: Byte::from_bytes(available),
+        swap_total: Byte::from_bytes(swap_total),
+        swap_used: Byte::from_bytes(swap_total.saturating_sub(swap_free)),
+        format,
+    })
+}
+
+/// Shows the RAM in use, e.g. `Mem: 3.2/15.6 GiB`.
+///
+/// Memory information is read from `/proc/meminfo`, every 5 seconds by
+/// default.
+pub struct Memory<F: Fn(MemoryInfo) -> String> {
+    attr: Attributes,
+    update_interval: Duration,
+    format: FormatOptions,
+    render: F,
+}
+
+// Shows used and total memory in the unit that suits the total.
+fn default_render(info: MemoryInfo) -> String {
+    let total = info
+        .total
+        .get_appropriate_unit(info.format.units == Units::Binary);
+    let used = info.used.get_adjusted_unit(total.get_unit());
+    format!(
+        "Mem: {:.*}/{:.*} {}",
+        info.format.precision,
+        used.get_value(),
+        info.format.precision,
+        total.get_value(),
+        total.get_unit()
+    )
+}
+
+impl Memory<fn(MemoryInfo) -> String> {
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_format(attr, FormatOptions::new(1, Units::Binary))
+    }
+
+    /// Creates a new [`Memory`] widget that shows sizes formatted with
+    /// `format`, e.g. in GB rather than GiB.
+    pub fn new_with_format(
+        attr: Attributes,
+        format: FormatOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Memory {
+                attr,
+                update_interval: UPDATE_INTERVAL,
+                format,
+                render: default_render,
+            },
+            Self::into_stream,
+        )
+    }
+}
+
+impl<F: Fn(MemoryInfo) -> String + 'static> Memory<F> {
+    /// Creates a new [`Memory`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `format` - How sizes are formatted, e.g. in GB rather than GiB.
+    /// It's passed to `render` as [`MemoryInfo::format`].
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`MemoryInfo`] holds the current RAM and swap
+    /// usage.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::format::{FormatOptions, Units};
+    /// # use cnx_contrib::widgets::memory::*;
+    /// let format = FormatOptions::new(1, Units::Decimal);
+    /// let render = |info: MemoryInfo| {
+    ///     let percentage = info.used.get_bytes() as f64 / info.total.get_bytes() as f64 * 100.0;
+    ///     format!("RAM {}", info.format.percentage(percentage))
+    /// };
+    /// let memory = Memory::new_with_render(Attributes::default(), format, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, UPDATE_INTERVAL, format, render)
+    }
+
+    /// Creates a new [`Memory`] widget that updates every `update_interval`
+    /// rather than every 5 seconds. Intervals shorter than 1 second are
+    /// raised to that.
+    pub fn new_with_interval(
+        attr: Attributes,
+        update_interval: Duration,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Memory {
+                attr,
+                update_interval: clamp_interval("Memory", update_interval, MIN_UPDATE_INTERVAL),
+                format,
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let contents =
+            fs::read_to_string("/proc/meminfo").context("Failed to read /proc/meminfo")?;
+        let info = parse_meminfo(&contents, self.format)?;
+        let text = (self.render)(info);
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        Ok(stream)
+    }
+}
@@ -1,11 +1,39 @@
+/// Backlight widget to show the screen brightness as it changes
+#[cfg(target_os = "linux")]
+pub mod backlight;
 /// Battery widget to shows the current capacity
 pub mod battery;
+/// Command widget to show the output of a program run periodically
+pub mod command;
 /// CPU widget to show the current CPU consumption
 pub mod cpu;
 /// Disk usage widget to show current usage and remaining free space
 pub mod disk_usage;
+/// File widget to show the contents of a file as it changes
+#[cfg(target_os = "linux")]
+pub mod file;
+/// Load average widget to show the 1, 5 and 15 minute system load
+pub mod loadavg;
+/// Memory widget to show the current RAM usage
+pub mod memory;
+/// MPD widget to show the song that is playing
+pub mod mpd;
+/// MPRIS widget to show the track of a media player over D-Bus
+#[cfg(feature = "mpris")]
+pub mod mpris;
+/// Network widget to show the rates an interface is receiving and sending at
+pub mod net;
+/// RSS widget to cycle through the latest items of a feed
+#[cfg(feature = "rss")]
+pub mod rss;
 /// Sensor widget to periodically parses and displays the output of the sensors provided by the system.
 pub mod sensors;
+/// Swap widget to show the current swap usage
+pub mod swap;
+/// Temperature widget to show the temperature of a thermal zone
+pub mod temperature;
+/// Uptime widget to show how long the system has been running
+pub mod uptime;
 /// Volume widget to show the current volume/mute status of the default output device.
 pub mod volume;
 /// Weather widget to show temperature of your location
@@ -13,3 +41,11 @@ pub mod weather;
 /// Wireless widget to show wireless strength of your SSID
 #[cfg(feature = "wireless")]
 pub mod wireless;
+
+// Escapes plain text, such as a song or headline title, for use in Pango
+// markup.
+pub(crate) fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,257 @@
+use super::escape_markup;
+use crate::polling::Polling;
+use anyhow::{anyhow, bail, Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time;
+use tokio_stream::Stream;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:6600";
+// Shown, dimmed, while MPD can't be reached.
+const PLACEHOLDER: &str = "♫";
+
+/// Whether MPD is playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayState {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// The current song passed to an [`Mpd`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MpdInfo {
+    pub state: PlayState,
+    /// The artist of the current song, if it's tagged with one.
+    pub artist: Option<String>,
+    /// The title of the current song, if it's tagged with one.
+    pub title: Option<String>,
+    /// How far into the current song playback is, as of the last change.
+    /// This isn't updated while the song plays.
+    pub elapsed: Option<Duration>,
+    /// The length of the current song.
+    pub duration: Option<Duration>,
+}
+
+// A connection to MPD, speaking its line-based protocol.
+struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    async fn connect(address: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address)
+            .await
+            .with_context(|| format!("Failed to connect to MPD at {}", address))?;
+        let mut connection = Connection {
+            stream: BufReader::new(stream),
+        };
+        let greeting = connection.read_line().await?;
+        if !greeting.starts_with("OK MPD ") {
+            bail!("Unexpected greeting from MPD: {}", greeting);
+        }
+        Ok(connection)
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            bail!("MPD closed the connection");
+        }
+        Ok(line.trim_end_matches('\n').to_owned())
+    }
+
+    // Sends `command` and returns the `key: value` pairs of the response.
+    async fn command(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        self.stream
+            .write_all(format!("{}\n", command).as_bytes())
+            .await?;
+        let mut pairs = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if line.starts_with("ACK ") {
+                bail!("MPD command '{}' failed: {}", command, line);
+            }
+            let (key, value) = line
+                .split_once(": ")
+                .ok_or_else(|| anyhow!("Unexpected response from MPD: {}", line))?;
+            pairs.push((key.to_owned(), value.to_owned()));
+        }
+    }
+
+    async fn info(&mut self) -> Result<MpdInfo> {
+        let status = self.command("status").await?;
+        let song = self.command("currentsong").await?;
+        Ok(parse_info(&status, &song))
+    }
+}
+
+fn field<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn seconds(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn parse_info(status: &[(String, String)], song: &[(String, String)]) -> MpdInfo {
+    let state = match field(status, "state") {
+        Some("play") => PlayState::Playing,
+        Some("pause") => PlayState::Paused,
+        _ => PlayState::Stopped,
+    };
+    // Older versions of MPD only report `time: <elapsed>:<duration>`, in
+    // whole seconds.
+    let time = field(status, "time").and_then(|time| time.split_once(':'));
+    MpdInfo {
+        state,
+        artist: field(song, "Artist").map(str::to_owned),
+        title: field(song, "Title").map(str::to_owned),
+        elapsed: field(status, "elapsed")
+            .or_else(|| time.map(|(elapsed, _)| elapsed))
+            .and_then(seconds),
+        duration: field(status, "duration")
+            .or_else(|| time.map(|(_, duration)| duration))
+            .and_then(seconds),
+    }
+}
+
+/// Shows the song MPD is playing, e.g. `▶ Artist - Title`.
+///
+/// Rather than polling, the widget waits for MPD to report that the song or
+/// play state has changed. If the connection drops, a dimmed placeholder is
+/// shown while it reconnects, with backoff.
+pub struct Mpd<F: Fn(MpdInfo) -> String> {
+    attr: Attributes,
+    address: String,
+    render: F,
+}
+
+fn default_render(info: MpdInfo) -> String {
+    let symbol = match info.state {
+        PlayState::Playing => "▶",
+        PlayState::Paused => "⏸",
+        PlayState::Stopped => return "⏹".to_owned(),
+    };
+    let song = match (info.artist, info.title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        (None, Some(title)) => title,
+        (Some(artist), None) => artist,
+        (None, None) => "Unknown".to_owned(),
+    };
+    format!("{} {}", symbol, escape_markup(&song))
+}
+
+impl Mpd<fn(MpdInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        address: Option<String>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Mpd::new_with_render(attr, address, default_render)
+    }
+}
+
+impl<F: Fn(MpdInfo) -> String + 'static> Mpd<F> {
+    /// Creates a new [`Mpd`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `address` - The `host:port` MPD listens on. Defaults to
+    /// `127.0.0.1:6600`.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`MpdInfo`] holds the current song and play
+    /// state. The output is Pango markup, so tags need escaping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::mpd::*;
+    /// let render = |info: MpdInfo| match (info.state, info.title) {
+    ///     (PlayState::Playing, Some(title)) => title.replace('&', "&amp;"),
+    ///     _ => String::new(),
+    /// };
+    /// let mpd = Mpd::new_with_render(Attributes::default(), None, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        address: Option<String>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Mpd {
+                attr,
+                address: address.unwrap_or_else(|| DEFAULT_ADDRESS.to_owned()),
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn text(&self, text: String, dim: bool) -> Vec<Text> {
+        vec![Text::new(self.attr.clone(), text).markup(true).dim(dim)]
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // Reconnect after a second, backing off to a minute.
+        let reconnect = Polling {
+            retry: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            jitter: Duration::from_secs(0),
+            ..Polling::default()
+        };
+        let stream = stream! {
+            let mut failures = 0;
+            loop {
+                let mut connection = match Connection::connect(&self.address).await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        failures += 1;
+                        println!("{:#}", err);
+                        yield Ok(self.text(PLACEHOLDER.to_owned(), true));
+                        time::sleep(reconnect.delay(failures)).await;
+                        continue;
+                    }
+                };
+                failures = 0;
+
+                // Show the current song, then again each time it changes.
+                loop {
+                    match connection.info().await {
+                        Ok(info) => yield Ok(self.text((self.render)(info), false)),
+                        Err(err) => {
+                            println!("Lost connection to MPD: {:#}", err);
+                            break;
+                        }
+                    }
+                    if let Err(err) = connection.command("idle player").await {
+                        println!("Lost connection to MPD: {:#}", err);
+                        break;
+                    }
+                }
+                failures += 1;
+                yield Ok(self.text(PLACEHOLDER.to_owned(), true));
+                time::sleep(reconnect.delay(failures)).await;
+            }
+        };
+        Ok(stream)
+    }
+}
@@ -0,0 +1,265 @@
+use super::escape_markup;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use std::collections::HashMap;
+use std::rc::Rc;
+use tokio_stream::{Stream, StreamExt};
+use zbus::fdo::DBusProxy;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{Connection, Message, MessageStream, MessageType, Proxy};
+
+// Every MPRIS player owns a bus name starting with this, e.g.
+// `org.mpris.MediaPlayer2.spotify`.
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+// The signals that mean the shown player may have changed: a player's
+// properties changing, and players starting or quitting.
+const MATCH_RULES: [&str; 2] = [
+    "type='signal',interface='org.freedesktop.DBus.Properties',\
+     member='PropertiesChanged',path='/org/mpris/MediaPlayer2'",
+    "type='signal',interface='org.freedesktop.DBus',member='NameOwnerChanged',\
+     arg0namespace='org.mpris.MediaPlayer2'",
+];
+
+/// Whether a player is playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// The player passed to an [`Mpris`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerInfo {
+    /// The player's name, from its bus name, e.g. `spotify` or
+    /// `firefox.instance1234`.
+    pub player_name: String,
+    pub status: PlaybackStatus,
+    /// The title of the current track, if the player reports one.
+    pub metadata_title: Option<String>,
+    /// The artists of the current track, separated by commas, if the player
+    /// reports any.
+    pub metadata_artist: Option<String>,
+}
+
+fn string(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.as_str().to_owned()),
+        _ => None,
+    }
+}
+
+// `xesam:artist` is a list of strings, though some players send just one.
+fn strings(value: &Value) -> Option<String> {
+    match value {
+        Value::Array(array) => {
+            let artists: Vec<String> = array.get().iter().filter_map(string).collect();
+            Some(artists.join(", ")).filter(|artists| !artists.is_empty())
+        }
+        value => string(value),
+    }
+}
+
+async fn player_info(connection: &Connection, bus_name: &str) -> Result<PlayerInfo> {
+    let proxy = Proxy::new(connection, bus_name, OBJECT_PATH, PLAYER_INTERFACE).await?;
+    let status: String = proxy.get_property("PlaybackStatus").await?;
+    let metadata: HashMap<String, OwnedValue> = proxy.get_property("Metadata").await?;
+    Ok(PlayerInfo {
+        player_name: bus_name.trim_start_matches(BUS_PREFIX).to_owned(),
+        status: match status.as_str() {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        },
+        metadata_title: metadata.get("xesam:title").and_then(|value| string(value)),
+        metadata_artist: metadata
+            .get("xesam:artist")
+            .and_then(|value| strings(value)),
+    })
+}
+
+// The player to show: the first one that's playing, or else the first one.
+async fn active_player(connection: &Connection) -> Result<Option<PlayerInfo>> {
+    let mut names: Vec<String> = DBusProxy::new(connection)
+        .await?
+        .list_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(BUS_PREFIX))
+        .collect();
+    names.sort();
+
+    let mut players = Vec::new();
+    for name in names {
+        // A player may quit between listing and querying it.
+        match player_info(connection, &name).await {
+            Ok(player) => players.push(player),
+            Err(err) => println!("Failed to query MPRIS player {}: {:#}", name, err),
+        }
+    }
+    let playing = players
+        .iter()
+        .position(|player| player.status == PlaybackStatus::Playing);
+    Ok(match playing {
+        Some(index) => Some(players.swap_remove(index)),
+        None => players.into_iter().next(),
+    })
+}
+
+fn is_relevant(message: &Message) -> bool {
+    message.message_type() == MessageType::Signal
+        && matches!(
+            message.member(),
+            Ok(Some(member)) if matches!(member.as_str(), "PropertiesChanged" | "NameOwnerChanged")
+        )
+}
+
+/// Shows the track of a media player that supports MPRIS, such as Spotify,
+/// VLC or a web browser, e.g. `▶ Artist - Title`.
+///
+/// If several players are running, the one that's playing is shown. The
+/// widget listens for changes over D-Bus rather than polling, and shows
+/// nothing while no player is running.
+pub struct Mpris<F: Fn(PlayerInfo) -> String> {
+    attr: Attributes,
+    render: Rc<F>,
+}
+
+// The widget is restarted if the D-Bus connection fails, so it must be
+// `Clone` however `render` is.
+impl<F: Fn(PlayerInfo) -> String> Clone for Mpris<F> {
+    fn clone(&self) -> Self {
+        Mpris {
+            attr: self.attr.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+fn default_render(info: PlayerInfo) -> String {
+    let symbol = match info.status {
+        PlaybackStatus::Playing => "▶",
+        PlaybackStatus::Paused => "⏸",
+        PlaybackStatus::Stopped => return "⏹".to_owned(),
+    };
+    let track = match (info.metadata_artist, info.metadata_title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        (None, Some(title)) => title,
+        (Some(artist), None) => artist,
+        (None, None) => info.player_name,
+    };
+    format!("{} {}", symbol, escape_markup(&track))
+}
+
+impl Mpris<fn(PlayerInfo) -> String> {
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Mpris::new_with_render(attr, default_render)
+    }
+}
+
+impl<F: Fn(PlayerInfo) -> String + 'static> Mpris<F> {
+    /// Creates a new [`Mpris`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`PlayerInfo`] holds the player's status and
+    /// current track. The output is Pango markup, so metadata needs
+    /// escaping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::mpris::*;
+    /// let render = |info: PlayerInfo| {
+    ///     let title = info.metadata_title.unwrap_or_default();
+    ///     format!("{}: {}", info.player_name, title.replace('&', "&amp;"))
+    /// };
+    /// let mpris = Mpris::new_with_render(Attributes::default(), render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Mpris {
+                attr,
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn tick(&self, player: Option<PlayerInfo>) -> Vec<Text> {
+        vec![Text::new(
+            self.attr.clone(),
+            player
+                .map(|player| (self.render)(player))
+                .unwrap_or_default(),
+        )
+        .markup(true)]
+    }
+
+    async fn connect() -> Result<(Connection, MessageStream)> {
+        let connection = Connection::session()
+            .await
+            .context("Failed to connect to the D-Bus session bus")?;
+        let bus = DBusProxy::new(&connection).await?;
+        for rule in MATCH_RULES {
+            bus.add_match(rule).await?;
+        }
+        let messages = MessageStream::from(&connection);
+        Ok((connection, messages))
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // Errors end the stream, and the widget is restarted.
+        let stream = stream! {
+            let (connection, mut messages) = match Self::connect().await {
+                Ok(connected) => connected,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+            match active_player(&connection).await {
+                Ok(player) => yield Ok(self.tick(player)),
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            }
+            while let Some(message) = messages.next().await {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(err) => {
+                        yield Err(err.into());
+                        return;
+                    }
+                };
+                if !is_relevant(&message) {
+                    continue;
+                }
+                match active_player(&connection).await {
+                    Ok(player) => yield Ok(self.tick(player)),
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        };
+        Ok(stream)
+    }
+}
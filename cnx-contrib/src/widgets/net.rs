@@ -0,0 +1,220 @@
+use crate::format::{FormatOptions, Units};
+use anyhow::{Context, Result};
+use byte_unit::Byte;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::fs;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+// How often to sample the counters by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+// The shortest interval allowed. Rates over shorter intervals are too jumpy to
+// read anyway.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The network throughput passed to a [`NetThroughput`] widget's render
+/// closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThroughputInfo {
+    /// Bytes received per second, since the previous update.
+    pub rx_per_sec: u64,
+    /// Bytes sent per second, since the previous update.
+    pub tx_per_sec: u64,
+    /// The formatting options the widget was created with, e.g. to format
+    /// the rates with [`FormatOptions::rate()`].
+    pub format: FormatOptions,
+}
+
+// A reading of an interface's byte counters.
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    at: Instant,
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+// The rate of change of a counter. Counters go backwards if they wrap or the
+// interface is reset, which is treated as no traffic.
+fn per_second(previous: u64, current: u64, elapsed: Duration) -> u64 {
+    let delta = current.saturating_sub(previous);
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => (delta as f64 / secs).round() as u64,
+        _ => 0,
+    }
+}
+
+/// Shows the rates at which a network interface is receiving and sending
+/// data, e.g. `↓1.2M ↑300K`.
+///
+/// The rates are worked out from the interface's byte counters in
+/// `/sys/class/net/<interface>/statistics/`, sampled every 2 seconds by
+/// default. If the interface disappears, an error is shown until it's back.
+pub struct NetThroughput<F: Fn(ThroughputInfo) -> String> {
+    attr: Attributes,
+    interface: String,
+    update_interval: Duration,
+    format: FormatOptions,
+    previous: Option<Sample>,
+    render: F,
+}
+
+// Formats a rate compactly, e.g. `1.2M`, with a decimal place only for small
+// values so that the text doesn't jump around in width too much.
+fn compact_rate(bytes_per_sec: u64, format: &FormatOptions) -> String {
+    let adjusted = Byte::from_bytes(u128::from(bytes_per_sec))
+        .get_appropriate_unit(format.units == Units::Binary);
+    let unit = adjusted.get_unit().to_string();
+    let value = adjusted.get_value();
+    let precision = if value < 10.0 { format.precision } else { 0 };
+    format!("{:.*}{}", precision, value, &unit[..1])
+}
+
+fn default_render(info: ThroughputInfo) -> String {
+    format!(
+        "↓{} ↑{}",
+        compact_rate(info.rx_per_sec, &info.format),
+        compact_rate(info.tx_per_sec, &info.format)
+    )
+}
+
+impl NetThroughput<fn(ThroughputInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        interface: String,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_format(attr, interface, FormatOptions::new(1, Units::Binary))
+    }
+
+    /// Creates a new [`NetThroughput`] widget that shows rates formatted
+    /// with `format`, e.g. in decimal rather than binary units.
+    pub fn new_with_format(
+        attr: Attributes,
+        interface: String,
+        format: FormatOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            NetThroughput {
+                attr,
+                interface,
+                update_interval: UPDATE_INTERVAL,
+                format,
+                previous: None,
+                render: default_render,
+            },
+            Self::into_stream,
+        )
+    }
+}
+
+impl<F: Fn(ThroughputInfo) -> String + 'static> NetThroughput<F> {
+    /// Creates a new [`NetThroughput`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `interface` - The name of the network interface, e.g. `wlp2s0`.
+    /// You can list them with `ip link`.
+    ///
+    /// * `format` - How rates are formatted, e.g. in decimal rather than
+    /// binary units. It's passed to `render` as [`ThroughputInfo::format`].
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`ThroughputInfo`] holds the current rates.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::format::{FormatOptions, Units};
+    /// # use cnx_contrib::widgets::net::*;
+    /// let format = FormatOptions::new(1, Units::Decimal);
+    /// let render = |info: ThroughputInfo| {
+    ///     format!("{} down", info.format.rate(u128::from(info.rx_per_sec)))
+    /// };
+    /// let net =
+    ///     NetThroughput::new_with_render(Attributes::default(), "wlp2s0".into(), format, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        interface: String,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, interface, UPDATE_INTERVAL, format, render)
+    }
+
+    /// Creates a new [`NetThroughput`] widget that samples the counters
+    /// every `update_interval` rather than every 2 seconds. Intervals
+    /// shorter than 500ms are raised to that.
+    pub fn new_with_interval(
+        attr: Attributes,
+        interface: String,
+        update_interval: Duration,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            NetThroughput {
+                attr,
+                interface,
+                update_interval: clamp_interval(
+                    "NetThroughput",
+                    update_interval,
+                    MIN_UPDATE_INTERVAL,
+                ),
+                format,
+                previous: None,
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn read_counter(&self, name: &str) -> Result<u64> {
+        let path = format!("/sys/class/net/{}/statistics/{}", self.interface, name);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {} of {}", name, self.interface))?;
+        contents
+            .trim()
+            .parse()
+            .with_context(|| format!("Failed to parse {} of {}", name, self.interface))
+    }
+
+    fn sample(&self) -> Result<Sample> {
+        Ok(Sample {
+            at: Instant::now(),
+            rx_bytes: self.read_counter("rx_bytes")?,
+            tx_bytes: self.read_counter("tx_bytes")?,
+        })
+    }
+
+    fn tick(&mut self) -> Result<Vec<Text>> {
+        let current = self.sample()?;
+        // The first sample is only something to work out the rates from.
+        let previous = match self.previous.replace(current) {
+            Some(previous) => previous,
+            None => return Ok(vec![]),
+        };
+        let elapsed = current.at - previous.at;
+        let text = (self.render)(ThroughputInfo {
+            rx_per_sec: per_second(previous.rx_bytes, current.rx_bytes, elapsed),
+            tx_per_sec: per_second(previous.tx_bytes, current.tx_bytes, elapsed),
+            format: self.format,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(mut self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // The first tick takes a sample straight away, so that there's a
+        // rate to show after one interval. If the interface isn't up yet,
+        // that's an error like any other, shown until it's back.
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        Ok(stream)
+    }
+}
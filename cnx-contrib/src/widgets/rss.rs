@@ -0,0 +1,227 @@
+use super::escape_markup;
+use crate::polling::Polling;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::cmp;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tokio_stream::Stream;
+
+// Feeds rarely update more than every few minutes, and the servers are
+// someone else's, so don't poll them (or retry failures) more often than this.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(60);
+// The shortest time each item is shown for.
+const MIN_CYCLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which feed an [`Rss`] widget shows, and how.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RssOptions {
+    /// The URL of an RSS, Atom or JSON feed.
+    pub url: String,
+    /// How many of the latest items to cycle through.
+    pub items: usize,
+    /// How long each item is shown for before moving on to the next.
+    pub cycle_interval: Duration,
+}
+
+impl RssOptions {
+    /// Cycles through the latest 5 items of the feed at `url`, showing each
+    /// for 10 seconds.
+    pub fn new(url: String) -> Self {
+        RssOptions {
+            url,
+            items: 5,
+            cycle_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// An item of a feed, e.g. a headline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedItem {
+    pub title: String,
+    /// The item's link, if it has one.
+    pub link: Option<String>,
+}
+
+/// The feed passed to an [`Rss`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedInfo {
+    /// The latest items of the feed, newest first.
+    pub items: Vec<FeedItem>,
+    /// The index in `items` of the item to show now. This moves on to the
+    /// next item every `cycle_interval`, wrapping around.
+    pub current: usize,
+}
+
+impl FeedInfo {
+    /// The item to show now.
+    pub fn current_item(&self) -> Option<&FeedItem> {
+        self.items.get(self.current)
+    }
+}
+
+/// Shows the latest items of an RSS or Atom feed, one at a time.
+///
+/// The feed is fetched periodically, as configured by [`Polling`]. If a fetch
+/// fails, the items fetched last are still shown (dimmed, as they're stale)
+/// and the fetch is retried with backoff.
+pub struct Rss<F: Fn(FeedInfo) -> String> {
+    attr: Attributes,
+    options: RssOptions,
+    polling: Polling,
+    render: F,
+}
+
+fn default_render(info: FeedInfo) -> String {
+    info.current_item()
+        .map(|item| escape_markup(&item.title))
+        .unwrap_or_default()
+}
+
+impl Rss<fn(FeedInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        options: RssOptions,
+        polling: Option<Polling>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Rss::new_with_render(attr, options, polling, default_render)
+    }
+}
+
+impl<F: Fn(FeedInfo) -> String + 'static> Rss<F> {
+    /// Creates a new [`Rss`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `options` - Which feed to show, how many of its items to cycle
+    /// through and how quickly.
+    ///
+    /// * `polling` - Controls how often the feed is fetched, how quickly to
+    /// retry after failures and how long to wait for a response. Defaults to
+    /// every 30 minutes, with a 10 second timeout. Intervals shorter than a
+    /// minute are raised to that.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`FeedInfo`] holds the latest items and which
+    /// of them to show now. The output is Pango markup, so titles need
+    /// escaping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::rss::*;
+    /// let options = RssOptions::new("https://blog.rust-lang.org/feed.xml".into());
+    /// let render = |info: FeedInfo| match info.current_item() {
+    ///     Some(item) => format!("{}/{}: {}", info.current + 1, info.items.len(), item.title),
+    ///     None => "No news".to_owned(),
+    /// };
+    /// let rss = Rss::new_with_render(Attributes::default(), options, None, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        options: RssOptions,
+        polling: Option<Polling>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        let options = RssOptions {
+            cycle_interval: clamp_interval("Rss", options.cycle_interval, MIN_CYCLE_INTERVAL),
+            ..options
+        };
+        WidgetStream::new(
+            Rss {
+                attr,
+                options,
+                polling: polling.unwrap_or_default().clamped("Rss", MIN_POLL_INTERVAL),
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    async fn fetch(&self) -> Result<Vec<FeedItem>> {
+        let body = reqwest::get(&self.options.url)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let feed = feed_rs::parser::parse(&body[..]).context("Failed to parse feed")?;
+        let items = feed
+            .entries
+            .into_iter()
+            .take(self.options.items)
+            .map(|entry| FeedItem {
+                title: entry
+                    .title
+                    .map(|title| title.content.trim().to_owned())
+                    .unwrap_or_default(),
+                link: entry.links.into_iter().next().map(|link| link.href),
+            })
+            .collect();
+        Ok(items)
+    }
+
+    fn tick(&self, items: &[FeedItem], current: usize, stale: bool) -> Vec<Text> {
+        let text = (self.render)(FeedInfo {
+            items: items.to_vec(),
+            current,
+        });
+        vec![Text::new(self.attr.clone(), text).markup(true).dim(stale)]
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let stream = stream! {
+            let mut items: Vec<FeedItem> = Vec::new();
+            let mut current = 0;
+            let mut stale = false;
+            let mut failures = 0;
+            let mut next_fetch = Instant::now();
+            let mut next_cycle = Instant::now() + self.options.cycle_interval;
+            loop {
+                if Instant::now() >= next_fetch {
+                    match self.polling.fetch(self.fetch()).await {
+                        Ok(fetched) => {
+                            failures = 0;
+                            stale = false;
+                            if fetched != items {
+                                items = fetched;
+                                current = 0;
+                            }
+                        }
+                        // Keep showing the last items rather than an error if
+                        // we've gone offline.
+                        Err(err) if !items.is_empty() => {
+                            failures += 1;
+                            stale = true;
+                            println!("Error fetching feed: {:#}", err);
+                        }
+                        Err(err) => {
+                            failures += 1;
+                            yield Err(err);
+                        }
+                    }
+                    next_fetch = Instant::now() + self.polling.delay(failures);
+                }
+
+                if Instant::now() >= next_cycle {
+                    if !items.is_empty() {
+                        current = (current + 1) % items.len();
+                    }
+                    next_cycle = Instant::now() + self.options.cycle_interval;
+                }
+
+                if !items.is_empty() || failures == 0 {
+                    yield Ok(self.tick(&items, current, stale));
+                }
+                time::sleep_until(cmp::min(next_fetch, next_cycle)).await;
+            }
+        };
+        Ok(stream)
+    }
+}
@@ -1,8 +1,30 @@
-#[cfg(feature = "openbsd")]
+#[cfg(target_os = "openbsd")]
 mod sensors_bsd;
 #[cfg(target_os = "linux")]
 mod sensors_linux;
-#[cfg(feature = "openbsd")]
+#[cfg(target_os = "openbsd")]
 pub use sensors_bsd::Sensors;
 #[cfg(target_os = "linux")]
 pub use sensors_linux::Sensors;
+
+/// A reading from one of the sensors passed to a `Sensors` widget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensorReading {
+    /// The name of the sensor, as passed to the widget.
+    pub name: String,
+    /// The sensor's value, or `None` if the sensor wasn't found.
+    pub value: Option<f64>,
+    /// The units of the value, e.g. `°C` or ` RPM`.
+    pub units: String,
+}
+
+fn default_render(readings: Vec<SensorReading>) -> String {
+    readings
+        .into_iter()
+        .map(|reading| match reading.value {
+            Some(value) => format!("{:.1}{}", value, reading.units),
+            None => "Invalid".to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,16 +1,13 @@
-#[cfg(target_os = "openbsd")]
-use anyhow::{Context, Error, Result};
-use std::collections::HashMap;
-use std::process::Command;
+use anyhow::{anyhow, Context, Result};
 use std::time::Duration;
 
 use regex::Regex;
 
+use super::{default_render, SensorReading};
 use crate::cmd::command_output;
-use crate::text::{Attributes, Text};
-use crate::widgets::{WidgetStream, WidgetStreamI};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
 use lazy_static::lazy_static;
-// use regex::Regex;
 use std::str::FromStr;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
@@ -70,7 +67,6 @@ impl OpenBsd {
     }
 }
 
-// TODO: Use config flag when we re-implement Linux version.
 type SensorsInfo = OpenBsd;
 
 /// Shows the value from one or more hardware sensors.
@@ -84,18 +80,36 @@ type SensorsInfo = OpenBsd;
 ///
 /// [`lm_sensors`]: https://wiki.archlinux.org/index.php/lm_sensors
 /// [`sysctl`]: https://man.openbsd.org/sysctl.8
-pub struct Sensors {
+pub struct Sensors<F: Fn(Vec<SensorReading>) -> String> {
     update_interval: Duration,
     attr: Attributes,
     sensors: Vec<String>,
     info: SensorsInfo,
+    render: F,
 }
 
-impl Sensors {
+impl Sensors<fn(Vec<SensorReading>) -> String> {
     /// Creates a new Sensors widget.
     ///
     /// A list of sensor names should be passed as the `sensors` argument.
-    pub fn new<S: Into<String>>(attr: Attributes, sensors: Vec<S>) -> WidgetStream<Sensors, impl Stream<Item = WidgetStreamI>> {
+    pub fn new<S: Into<String>>(attr: Attributes, sensors: Vec<S>) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Sensors::new_with_render(attr, sensors, default_render)
+    }
+}
+
+impl<F: Fn(Vec<SensorReading>) -> String + 'static> Sensors<F> {
+    /// Creates a new Sensors widget with a custom render closure.
+    ///
+    /// `render` is passed a [`SensorReading`] for each of `sensors`, in the
+    /// same order, and returns the text to display (which may use Pango
+    /// markup).
+    ///
+    /// [`SensorReading`]: struct.SensorReading.html
+    pub fn new_with_render<S: Into<String>>(
+        attr: Attributes,
+        sensors: Vec<S>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         let sensors = sensors.into_iter().map(Into::into).collect();
         WidgetStream::new(
             Sensors {
@@ -103,6 +117,7 @@ impl Sensors {
                 attr,
                 sensors,
                 info: SensorsInfo::default(),
+                render,
             },
             Self::into_stream
         )
@@ -114,20 +129,20 @@ impl Sensors {
             .load_values(&self.sensors)
             .context("Failed to get sensor information")?;
 
-        let texts = values
-            .into_iter()
-            .map(|Value { value, units }| {
-                let text = format!("{}{}", value, units);
-                Text {
-                    attr: self.attr.clone(),
-                    text,
-                    stretch: false,
-                    markup: false,
-                }
+        let readings = self
+            .sensors
+            .iter()
+            .zip(values)
+            .map(|(name, Value { value, units })| SensorReading {
+                name: name.clone(),
+                value: Some(value as f64),
+                units,
             })
             .collect();
 
-        Ok(texts)
+        Ok(vec![
+            Text::new(self.attr.clone(), (self.render)(readings)).markup(true)
+        ])
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
@@ -1,10 +1,10 @@
+use super::{default_render, SensorReading};
+use crate::cmd::command_output;
 use anyhow::{anyhow, Context, Result};
-#[cfg(target_os = "linux")]
 use cnx::text::{Attributes, Text};
 use cnx::widgets::{WidgetStream, WidgetStreamI};
 use regex::Regex;
 use std::collections::HashMap;
-use std::process::Command;
 use std::time::Duration;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
@@ -48,13 +48,14 @@ fn parse_sensors_output(output: &str) -> Result<HashMap<&str, Value<'_>>> {
 /// It expects the `sensors` executable to be available in the `PATH`.
 ///
 /// [`lm_sensors`]: https://wiki.archlinux.org/index.php/lm_sensors
-pub struct Sensors {
+pub struct Sensors<F: Fn(Vec<SensorReading>) -> String> {
     update_interval: Duration,
     attr: Attributes,
     sensors: Vec<String>,
+    render: F,
 }
 
-impl Sensors {
+impl Sensors<fn(Vec<SensorReading>) -> String> {
     /// Creates a new Sensors widget.
     ///
     /// Creates a new `Sensors` widget, whose text will be displayed with the
@@ -63,11 +64,6 @@ impl Sensors {
     /// A list of sensor names should be passed as the `sensors` argument. (You
     /// can discover the names by running the `sensors` utility in a terminal).
     ///
-    /// The [`cnx::Cnx`] instance is borrowed during construction in order to get
-    /// access to handles of its event loop. However, it is not borrowed for the
-    /// lifetime of the widget. See the [`cnx::Cnx::add_widget`] for more discussion
-    /// about the lifetime of the borrow.
-    ///
     /// # Examples
     ///
     /// ```
@@ -97,38 +93,59 @@ impl Sensors {
     /// # fn main() { run().unwrap(); }
     /// ```
     pub fn new<S: Into<String>>(attr: Attributes, sensors: Vec<S>) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Sensors::new_with_render(attr, sensors, default_render)
+    }
+}
+
+impl<F: Fn(Vec<SensorReading>) -> String + 'static> Sensors<F> {
+    /// Creates a new Sensors widget with a custom render closure.
+    ///
+    /// `render` is passed a [`SensorReading`] for each of `sensors`, in the
+    /// same order, and returns the text to display (which may use Pango
+    /// markup).
+    ///
+    /// [`SensorReading`]: struct.SensorReading.html
+    pub fn new_with_render<S: Into<String>>(
+        attr: Attributes,
+        sensors: Vec<S>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Sensors {
                 update_interval: Duration::from_secs(60),
                 attr,
                 sensors: sensors.into_iter().map(Into::into).collect(),
+                render,
             },
             Self::into_stream
         )
     }
 
-    fn tick(&self) -> Result<Vec<Text>> {
-        let output = Command::new("sensors")
-            .output()
-            .context("Failed to run `sensors`")?;
-        let string = String::from_utf8(output.stdout).context("Invalid UTF-8 in sensors output")?;
-        let parsed = parse_sensors_output(&string).context("Failed to parse `sensors` output")?;
-        self.sensors
+    fn readings(&self) -> Result<Vec<SensorReading>> {
+        let output = command_output("sensors", &[] as &[&str])?;
+        let parsed = parse_sensors_output(&output).context("Failed to parse `sensors` output")?;
+        let readings = self
+            .sensors
             .iter()
-            .map(|sensor_name| {
-                let text = parsed
-                    .get::<str>(sensor_name)
-                    .map_or("Invalid".to_owned(), |&Value { temp, units }| {
-                        format!("{}°{}", temp, units)
-                    });
-                Ok(Text {
-                    attr: self.attr.clone(),
-                    text,
-                    stretch: false,
-                    markup: false,
-                })
+            .map(|sensor_name| match parsed.get::<str>(sensor_name) {
+                Some(&Value { temp, units }) => SensorReading {
+                    name: sensor_name.clone(),
+                    value: temp.parse().ok(),
+                    units: format!("°{}", units),
+                },
+                None => SensorReading {
+                    name: sensor_name.clone(),
+                    value: None,
+                    units: String::new(),
+                },
             })
-            .collect()
+            .collect();
+        Ok(readings)
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let text = (self.render)(self.readings()?);
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
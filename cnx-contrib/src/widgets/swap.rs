@@ -0,0 +1,163 @@
+use super::memory::parse_meminfo;
+use crate::format::{FormatOptions, Units};
+use anyhow::{Context, Result};
+use byte_unit::Byte;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::fs;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+// How often to update by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+// The shortest interval allowed, as reading /proc isn't free.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The swap usage passed to a [`Swap`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapInfo {
+    /// Total swap space. This is zero if the system has no swap.
+    pub total: Byte,
+    /// Swap space in use.
+    pub used: Byte,
+    /// The percentage of swap space in use, or 0 if there's no swap.
+    pub percent: f32,
+    /// The formatting options the widget was created with, e.g. to format
+    /// the sizes with [`FormatOptions::bytes()`].
+    pub format: FormatOptions,
+}
+
+/// Shows the swap space in use, e.g. `Swap: 0.5/8.0 GiB`, or `swap off` if
+/// the system has no swap.
+///
+/// This is the swap counterpart of the [`Memory`] widget. Swap usage is read
+/// from `/proc/meminfo`, every 5 seconds by default.
+///
+/// [`Memory`]: super::memory::Memory
+pub struct Swap<F: Fn(SwapInfo) -> String> {
+    attr: Attributes,
+    update_interval: Duration,
+    format: FormatOptions,
+    render: F,
+}
+
+// Shows used and total swap in the unit that suits the total.
+fn default_render(info: SwapInfo) -> String {
+    if info.total.get_bytes() == 0 {
+        return "swap off".to_owned();
+    }
+    let total = info
+        .total
+        .get_appropriate_unit(info.format.units == Units::Binary);
+    let used = info.used.get_adjusted_unit(total.get_unit());
+    format!(
+        "Swap: {:.*}/{:.*} {}",
+        info.format.precision,
+        used.get_value(),
+        info.format.precision,
+        total.get_value(),
+        total.get_unit()
+    )
+}
+
+impl Swap<fn(SwapInfo) -> String> {
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_format(attr, FormatOptions::new(1, Units::Binary))
+    }
+
+    /// Creates a new [`Swap`] widget that shows sizes formatted with
+    /// `format`, e.g. in GB rather than GiB.
+    pub fn new_with_format(
+        attr: Attributes,
+        format: FormatOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Swap {
+                attr,
+                update_interval: UPDATE_INTERVAL,
+                format,
+                render: default_render,
+            },
+            Self::into_stream,
+        )
+    }
+}
+
+impl<F: Fn(SwapInfo) -> String + 'static> Swap<F> {
+    /// Creates a new [`Swap`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `format` - How sizes are formatted, e.g. in GB rather than GiB.
+    /// It's passed to `render` as [`SwapInfo::format`].
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`SwapInfo`] holds the current swap usage.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::format::FormatOptions;
+    /// # use cnx_contrib::widgets::swap::*;
+    /// let render = |info: SwapInfo| format!("Swap {:.0}%", info.percent);
+    /// let swap = Swap::new_with_render(Attributes::default(), FormatOptions::default(), render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, UPDATE_INTERVAL, format, render)
+    }
+
+    /// Creates a new [`Swap`] widget that updates every `update_interval`
+    /// rather than every 5 seconds. Intervals shorter than 1 second are
+    /// raised to that.
+    pub fn new_with_interval(
+        attr: Attributes,
+        update_interval: Duration,
+        format: FormatOptions,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Swap {
+                attr,
+                update_interval: clamp_interval("Swap", update_interval, MIN_UPDATE_INTERVAL),
+                format,
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let contents =
+            fs::read_to_string("/proc/meminfo").context("Failed to read /proc/meminfo")?;
+        let memory = parse_meminfo(&contents, self.format)?;
+        let (total, used) = (memory.swap_total, memory.swap_used);
+        let percent = if total.get_bytes() == 0 {
+            0.0
+        } else {
+            used.get_bytes() as f32 / total.get_bytes() as f32 * 100.0
+        };
+        let text = (self.render)(SwapInfo {
+            total,
+            used,
+            percent,
+            format: self.format,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        Ok(stream)
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{clamp_interval, WidgetStream, WidgetStreamI};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+const THERMAL_CLASS: &str = "/sys/class/thermal";
+// How often to update by default.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The temperature passed to a [`Temperature`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempInfo {
+    /// The temperature, in degrees Celsius.
+    pub celsius: f32,
+    /// The thermal zone the temperature is from, e.g. `thermal_zone0`.
+    pub zone: String,
+}
+
+// Finds the directory of a thermal zone, given its index (`1`), its
+// directory name (`thermal_zone1`) or its type (`x86_pkg_temp`).
+fn find_zone(zone: &str) -> Result<String> {
+    if zone.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(format!("thermal_zone{}", zone));
+    }
+    if zone.starts_with("thermal_zone") {
+        return Ok(zone.to_owned());
+    }
+    let mut names: Vec<String> = fs::read_dir(THERMAL_CLASS)
+        .with_context(|| format!("Failed to list {}", THERMAL_CLASS))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with("thermal_zone"))
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .find(|name| {
+            let path = Path::new(THERMAL_CLASS).join(name).join("type");
+            fs::read_to_string(path).map_or(false, |kind| kind.trim() == zone)
+        })
+        .ok_or_else(|| anyhow!("No thermal zone has type {}", zone))
+}
+
+// Parses a `temp` file, which holds millidegrees Celsius.
+fn parse_temp(contents: &str) -> Result<f32> {
+    let millidegrees: i64 = contents
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse temperature {:?}", contents.trim()))?;
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+/// Shows the temperature of a thermal zone, e.g. `🌡 52°C`.
+///
+/// The temperature is read from `/sys/class/thermal/<zone>/temp`, every 5
+/// seconds by default.
+pub struct Temperature<F: Fn(TempInfo) -> String> {
+    attr: Attributes,
+    zone: Option<String>,
+    update_interval: Duration,
+    render: F,
+}
+
+fn default_render(info: TempInfo) -> String {
+    format!("🌡 {:.0}°C", info.celsius)
+}
+
+impl Temperature<fn(TempInfo) -> String> {
+    pub fn new(
+        attr: Attributes,
+        zone: Option<String>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, zone, UPDATE_INTERVAL, default_render)
+    }
+}
+
+impl<F: Fn(TempInfo) -> String + 'static> Temperature<F> {
+    /// Creates a new [`Temperature`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `zone` - Which thermal zone to show, by index (`"1"`), directory
+    /// name (`"thermal_zone1"`) or type (`"x86_pkg_temp"`). The types are
+    /// listed by `cat /sys/class/thermal/thermal_zone*/type`. Defaults to
+    /// `thermal_zone0`.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`TempInfo`] holds the current temperature.
+    ///
+    /// # Examples
+    ///
+    /// Show the temperature in red when it's hot:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// # use cnx_contrib::widgets::temperature::*;
+    /// let render = |info: TempInfo| {
+    ///     let color = if info.celsius >= 80.0 {
+    ///         Color::red()
+    ///     } else {
+    ///         Color::white()
+    ///     };
+    ///     format!("<span foreground=\"{}\">{:.0}°C</span>", color.to_hex(), info.celsius)
+    /// };
+    /// let temperature = Temperature::new_with_render(Attributes::default(), None, render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        zone: Option<String>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interval(attr, zone, UPDATE_INTERVAL, render)
+    }
+
+    /// Creates a new [`Temperature`] widget that updates every
+    /// `update_interval` rather than every 5 seconds. Intervals shorter than
+    /// 1 second are raised to that.
+    pub fn new_with_interval(
+        attr: Attributes,
+        zone: Option<String>,
+        update_interval: Duration,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Temperature {
+                attr,
+                zone,
+                update_interval: clamp_interval(
+                    "Temperature",
+                    update_interval,
+                    MIN_UPDATE_INTERVAL,
+                ),
+                render,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn tick(&self, zone: &str, path: &Path) -> Result<Vec<Text>> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let text = (self.render)(TempInfo {
+            celsius: parse_temp(&contents)?,
+            zone: zone.to_owned(),
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let zone = match &self.zone {
+            Some(zone) => find_zone(zone)?,
+            None => "thermal_zone0".to_owned(),
+        };
+        let path: PathBuf = [THERMAL_CLASS, &zone, "temp"].iter().collect();
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick(&zone, &path));
+        Ok(stream)
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use std::fs;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// The uptime passed to an [`Uptime`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UptimeInfo {
+    /// How long the system has been running, including time spent
+    /// suspended.
+    pub total: Duration,
+}
+
+// Parses `/proc/uptime`, which holds the uptime and the idle time, in
+// seconds, e.g. `350735.47 234388.90`.
+fn parse_uptime(contents: &str) -> Result<Duration> {
+    let seconds: f64 = contents
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("Missing data in /proc/uptime"))?
+        .parse()
+        .context("Failed to parse /proc/uptime")?;
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Shows how long the system has been up, e.g. `up 3d 4h`.
+///
+/// The uptime is read from `/proc/uptime` once a minute.
+pub struct Uptime<F: Fn(UptimeInfo) -> String> {
+    attr: Attributes,
+    render: F,
+}
+
+// Shows the two largest units of the uptime, leaving out the smaller one if
+// it's zero, e.g. `up 3d 4h`, `up 2h 15m` or `up 5m`.
+fn default_render(info: UptimeInfo) -> String {
+    let minutes = info.total.as_secs() / 60;
+    let units = [
+        (minutes / (24 * 60), "d"),
+        (minutes / 60 % 24, "h"),
+        (minutes % 60, "m"),
+    ];
+    let largest = units
+        .iter()
+        .position(|&(value, _)| value > 0)
+        .unwrap_or(units.len() - 1);
+    let shown: Vec<String> = units[largest..]
+        .iter()
+        .take(2)
+        .enumerate()
+        .filter(|&(i, &(value, _))| i == 0 || value > 0)
+        .map(|(_, (value, unit))| format!("{}{}", value, unit))
+        .collect();
+    format!("up {}", shown.join(" "))
+}
+
+impl Uptime<fn(UptimeInfo) -> String> {
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_render(attr, default_render)
+    }
+}
+
+impl<F: Fn(UptimeInfo) -> String + 'static> Uptime<F> {
+    /// Creates a new [`Uptime`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`UptimeInfo`] holds the uptime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::uptime::*;
+    /// let render = |info: UptimeInfo| format!("{} hours", info.total.as_secs() / 3600);
+    /// let uptime = Uptime::new_with_render(Attributes::default(), render);
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(Uptime { attr, render }, Self::into_stream)
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let contents = fs::read_to_string("/proc/uptime").context("Failed to read /proc/uptime")?;
+        let text = (self.render)(UptimeInfo {
+            total: parse_uptime(&contents)?,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // As we're not showing seconds, we only need to update once a minute.
+        let interval = time::interval(Duration::from_secs(60));
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        Ok(stream)
+    }
+}
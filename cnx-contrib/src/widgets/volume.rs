@@ -4,9 +4,85 @@ mod volume_bsd;
 #[cfg(target_os = "linux")]
 #[cfg(feature = "volume")]
 mod volume_linux;
+#[cfg(target_os = "linux")]
+#[cfg(feature = "volume-pipewire")]
+mod volume_pipewire;
 #[cfg(target_os = "openbsd")]
 #[cfg(feature = "volume")]
 pub use volume_bsd::Volume;
 #[cfg(target_os = "linux")]
 #[cfg(feature = "volume")]
 pub use volume_linux::Volume;
+#[cfg(target_os = "linux")]
+#[cfg(feature = "volume-pipewire")]
+pub use volume_pipewire::PipeWireVolume;
+
+// The state of an output device, for backends that are told about changes
+// rather than querying the mixer themselves.
+#[cfg(any(
+    all(target_os = "openbsd", feature = "volume"),
+    all(target_os = "linux", feature = "volume-pipewire")
+))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum State {
+    Unknown,
+    Muted,
+    Unmuted { percentage: f32 },
+}
+
+#[cfg(any(
+    all(target_os = "openbsd", feature = "volume"),
+    all(target_os = "linux", feature = "volume-pipewire")
+))]
+impl State {
+    // Renders the state as a speaker glyph followed by the volume.
+    pub(crate) fn text(&self, icons: &VolumeIcons) -> String {
+        match *self {
+            State::Unknown => "?".to_owned(),
+            State::Muted => icons.icon(true, 0.0).to_owned(),
+            State::Unmuted { percentage } => {
+                let percentage = f64::from(percentage);
+                format!("{} {:.0}%", icons.icon(false, percentage), percentage)
+            }
+        }
+    }
+}
+
+/// The glyphs used to show the volume level, from muted up to loud.
+///
+/// [`VolumeIcons::icon()`] picks the glyph for a volume, so custom widgets
+/// can reuse the same ramp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolumeIcons {
+    pub muted: String,
+    pub low: String,
+    pub medium: String,
+    pub high: String,
+}
+
+impl VolumeIcons {
+    /// Returns the glyph for the given state. `percentage` is from 0 to 100,
+    /// split evenly between `low`, `medium` and `high`.
+    pub fn icon(&self, muted: bool, percentage: f64) -> &str {
+        if muted {
+            &self.muted
+        } else if percentage < 100.0 / 3.0 {
+            &self.low
+        } else if percentage < 200.0 / 3.0 {
+            &self.medium
+        } else {
+            &self.high
+        }
+    }
+}
+
+impl Default for VolumeIcons {
+    fn default() -> Self {
+        VolumeIcons {
+            muted: "🔇".to_owned(),
+            low: "🔈".to_owned(),
+            medium: "🔉".to_owned(),
+            high: "🔊".to_owned(),
+        }
+    }
+}
@@ -2,19 +2,13 @@ use std::u8;
 
 use anyhow::Result;
 use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
 use sioctl::Sioctl;
-use tokio::stream::{self, Stream, StreamExt};
 use tokio::sync::mpsc;
-
-use crate::text::{Attributes, Text};
-use crate::widgets::{WidgetStream, WidgetStreamI};
-
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum State {
-    Unknown,
-    Muted,
-    Unmuted { percentage: f32 },
-}
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{self as stream, Stream, StreamExt};
+use super::{State, VolumeIcons};
 
 struct OpenBsd;
 
@@ -35,6 +29,7 @@ impl OpenBsd {
             }
         });
 
+        let receiver = UnboundedReceiverStream::new(receiver);
         let mut stream = stream::iter(controls).chain(receiver);
         stream! {
             // Move watcher into stream! {} to keep it alive.
@@ -79,29 +74,29 @@ type VolumeInfo = OpenBsd;
 
 pub struct Volume {
     attr: Attributes,
+    icons: VolumeIcons,
 }
 
 impl Volume {
     /// Creates a new Volume widget.
     pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_icons(attr, VolumeIcons::default())
+    }
+
+    /// Creates a new Volume widget that shows the volume level with the given
+    /// glyphs.
+    pub fn new_with_icons(
+        attr: Attributes,
+        icons: VolumeIcons,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
-            Self { attr },
-            Self::on_change
+            Self { attr, icons },
+            Self::into_stream
         )
     }
 
     fn on_change(&self, state: State) -> Result<Vec<Text>> {
-        let text = match state {
-            State::Unknown => "?".to_owned(),
-            State::Muted => "M".to_owned(),
-            State::Unmuted { percentage } => format!("{:.0}%", percentage),
-        };
-
-        Ok(vec![Text {
-            attr: self.attr.clone(),
-            text,
-            stretch: false,
-        }])
+        Ok(vec![Text::new(self.attr.clone(), state.text(&self.icons))])
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
@@ -2,7 +2,8 @@ use alsa::mixer::{SelemChannelId, SelemId};
 use alsa::{self, Mixer, PollDescriptors};
 use anyhow::{anyhow, Context, Result};
 use cnx::text::{Attributes, Text};
-use cnx::widgets::{WidgetStream, WidgetStreamI};
+use cnx::widgets::{ScrollDirection, WidgetStream, WidgetStreamI};
+use super::VolumeIcons;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::RawFd;
 use std::pin::Pin;
@@ -10,17 +11,31 @@ use std::task::Poll;
 use tokio::io::unix::AsyncFd;
 use tokio_stream::{Stream, StreamExt};
 
+const MIXER_NAME: &str = "default";
+// How much scrolling over the widget changes the volume by, as a percentage
+// of the Master channel's range.
+const SCROLL_STEP: i64 = 5;
+
 /// Shows the current volume of the default ALSA output.
 ///
-/// This widget shows the current volume of the default ALSA output, or '`M`' if
-/// the output is muted.
+/// This widget shows the current volume of the default ALSA output, with a
+/// speaker glyph from its [`VolumeIcons`] that reflects the volume level.
+///
+/// [`VolumeIcons`]: struct.VolumeIcons.html
 ///
 /// The widget uses `alsa-lib` to receive events when the volume changes,
 /// avoiding expensive polling. If you do not have `alsa-lib` installed, you
 /// can disable the `volume-widget` feature on the `cnx` crate to avoid
 /// compiling this widget.
+///
+/// Scrolling over the widget raises or lowers the volume by 5%.
+///
+/// If the mixer goes away (e.g. the sound card is unplugged), the widget's
+/// stream is re-created once it is available again.
+#[derive(Clone)]
 pub struct Volume {
     attr: Attributes,
+    icons: VolumeIcons,
 }
 
 impl Volume {
@@ -29,14 +44,7 @@ impl Volume {
     /// Creates a new `Volume` widget, whose text will be displayed
     /// with the given [`Attributes`].
     ///
-    /// The [`Cnx`] instance is borrowed during construction in order to get
-    /// access to handles of its event loop. However, it is not borrowed for the
-    /// lifetime of the widget. See the [`cnx_add_widget!()`] for more discussion
-    /// about the lifetime of the borrow.
-    ///
     /// [`Attributes`]: ../text/struct.Attributes.html
-    /// [`Cnx`]: ../struct.Cnx.html
-    /// [`cnx_add_widget!()`]: ../macro.cnx_add_widget.html
     ///
     /// # Examples
     ///
@@ -65,14 +73,25 @@ impl Volume {
     /// # fn main() { run().unwrap(); }
     /// ```
     pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_icons(attr, VolumeIcons::default())
+    }
+
+    /// Creates a new Volume widget that shows the volume level with the given
+    /// glyphs.
+    pub fn new_with_icons(
+        attr: Attributes,
+        icons: VolumeIcons,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
-            Volume { attr },
+            Volume { attr, icons },
             Self::into_stream
         )
+        .restart_on_end()
+        .on_scroll(|_, direction| change_volume(direction))
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        let mixer_name = "default";
+        let mixer_name = MIXER_NAME;
         // We don't attempt to use the same mixer to listen for events and to
         // recompute the mixer state (in the callback below) as the Mixer seems
         // to cache the state from when it was created. It's relatively cheap
@@ -94,23 +113,39 @@ impl Volume {
                 let volume = master.get_playback_volume(channel)?;
                 let (min, max) = master.get_playback_volume_range();
                 let percentage = (volume as f64 / (max as f64 - min as f64)) * 100.0;
-                format!("<span foreground=\"#808080\">[</span>🔈 {:.0}%<span foreground=\"#808080\">]</span>", percentage)
+                let icon = self.icons.icon(false, percentage);
+                format!("<span foreground=\"#808080\">[</span>{} {:.0}%<span foreground=\"#808080\">]</span>", icon, percentage)
             } else {
-                "🔇".to_owned()
+                self.icons.icon(true, 0.0).to_owned()
             };
 
-            Ok(vec![Text {
-                attr: self.attr.clone(),
-                text,
-                stretch: false,
-                markup: true,
-            }])
+            Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
         });
 
         Ok(stream)
     }
 }
 
+// Raises or lowers the Master volume by `SCROLL_STEP`. The widget is told
+// about the change by ALSA, like any other.
+fn change_volume(direction: ScrollDirection) -> Result<()> {
+    let mixer = Mixer::new(MIXER_NAME, false)
+        .with_context(|| format!("Failed to open ALSA mixer: {}", MIXER_NAME))?;
+    let master = mixer
+        .find_selem(&SelemId::new("Master", 0))
+        .ok_or_else(|| anyhow!("Couldn't open Master channel"))?;
+
+    let (min, max) = master.get_playback_volume_range();
+    let step = ((max - min) * SCROLL_STEP / 100).max(1);
+    let volume = master.get_playback_volume(SelemChannelId::FrontLeft)?;
+    let volume = match direction {
+        ScrollDirection::Up => volume + step,
+        ScrollDirection::Down => volume - step,
+    };
+    master.set_playback_volume_all(volume.clamp(min, max))?;
+    Ok(())
+}
+
 struct AlsaEvented(Mixer);
 
 impl AlsaEvented {
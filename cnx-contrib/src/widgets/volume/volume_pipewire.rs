@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{WidgetStream, WidgetStreamI};
+use libspa::pod::deserialize::PodDeserializer;
+use libspa::pod::{Value, ValueArray};
+use pipewire as pw;
+use pw::metadata::{Metadata, MetadataListener};
+use pw::node::{Node, NodeListener};
+use pw::spa::param::ParamType;
+use pw::types::ObjectType;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::thread;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use super::{State, VolumeIcons};
+
+/// Shows the current volume of the default PipeWire sink.
+///
+/// This is an alternative to the ALSA-based [`Volume`] widget for systems
+/// using PipeWire without its ALSA or PulseAudio compatibility layers. It
+/// follows the default sink (as chosen in e.g. `pavucontrol` or `wpctl`),
+/// and is told about volume and mute changes by PipeWire rather than polling.
+///
+/// The widget requires the `volume-pipewire` feature of `cnx-contrib`. If the
+/// connection to PipeWire is lost, the widget's stream is re-created.
+///
+/// [`Volume`]: struct.Volume.html
+#[derive(Clone)]
+pub struct PipeWireVolume {
+    attr: Attributes,
+    icons: VolumeIcons,
+}
+
+impl PipeWireVolume {
+    /// Creates a new PipeWire Volume widget.
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_icons(attr, VolumeIcons::default())
+    }
+
+    /// Creates a new PipeWire Volume widget that shows the volume level with
+    /// the given glyphs.
+    pub fn new_with_icons(
+        attr: Attributes,
+        icons: VolumeIcons,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self { attr, icons },
+            Self::into_stream
+        )
+        .restart_on_end()
+    }
+
+    fn on_change(&self, state: State) -> Result<Vec<Text>> {
+        Ok(vec![Text::new(self.attr.clone(), state.text(&self.icons))])
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        // PipeWire's main loop blocks, so runs on its own thread. When it
+        // exits, the sender is dropped and the stream ends.
+        let (sender, receiver) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("cnx-pipewire".to_owned())
+            .spawn(move || {
+                if let Err(err) = watch_default_sink(sender) {
+                    println!("Error watching PipeWire volume: {:#}", err);
+                }
+            })?;
+
+        let stream = UnboundedReceiverStream::new(receiver).map(move |state| self.on_change(state));
+        Ok(stream)
+    }
+}
+
+// A sink that we've bound to, so that we're told about its volume.
+struct Sink {
+    name: String,
+    muted: bool,
+    // Unknown until PipeWire tells us.
+    percentage: Option<f32>,
+    // Dropping these would stop the updates.
+    _node: Node,
+    _listener: NodeListener,
+}
+
+impl Sink {
+    fn state(&self) -> State {
+        match (self.muted, self.percentage) {
+            (true, _) => State::Muted,
+            (false, Some(percentage)) => State::Unmuted { percentage },
+            (false, None) => State::Unknown,
+        }
+    }
+}
+
+// What we know about the PipeWire graph.
+#[derive(Default)]
+struct Graph {
+    // The `node.name` of the default sink, from the "default" metadata.
+    default_sink: Option<String>,
+    sinks: HashMap<u32, Sink>,
+    metadata: Option<(Metadata, MetadataListener)>,
+    // The last state sent, so that we only send changes.
+    sent: Option<State>,
+}
+
+impl Graph {
+    fn default_state(&self) -> State {
+        self.default_sink
+            .as_ref()
+            .and_then(|name| self.sinks.values().find(|sink| &sink.name == name))
+            .map_or(State::Unknown, Sink::state)
+    }
+
+    // Sends the default sink's state, if it has changed. Returns false if
+    // the widget has gone away.
+    fn send(&mut self, sender: &mpsc::UnboundedSender<State>) -> bool {
+        let state = self.default_state();
+        if self.sent == Some(state) {
+            return true;
+        }
+        self.sent = Some(state);
+        sender.send(state).is_ok()
+    }
+}
+
+fn watch_default_sink(sender: mpsc::UnboundedSender<State>) -> Result<()> {
+    pw::init();
+    let mainloop = pw::MainLoop::new().map_err(|e| anyhow!("Failed to create main loop: {}", e))?;
+    let context = pw::Context::new(&mainloop).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+    let core = context
+        .connect(None)
+        .map_err(|e| anyhow!("Failed to connect to PipeWire: {}", e))?;
+    let registry = Rc::new(
+        core.get_registry()
+            .map_err(|e| anyhow!("Failed to get registry: {}", e))?,
+    );
+    let graph = Rc::new(RefCell::new(Graph::default()));
+
+    // Stop the main loop if the connection to PipeWire fails, so that the
+    // widget's stream is re-created.
+    let _core_listener = core
+        .add_listener_local()
+        .error({
+            let mainloop = mainloop.clone();
+            move |_, _, _, message| {
+                println!("PipeWire error: {}", message);
+                mainloop.quit();
+            }
+        })
+        .register();
+
+    let _registry_listener = registry
+        .add_listener_local()
+        .global({
+            let registry = Rc::clone(&registry);
+            let graph = Rc::clone(&graph);
+            let sender = sender.clone();
+            let mainloop = mainloop.clone();
+            move |global| {
+                let props = match &global.props {
+                    Some(props) => props,
+                    None => return,
+                };
+                match global.type_ {
+                    ObjectType::Node if props.get("media.class") == Some("Audio/Sink") => {
+                        let name = props.get("node.name").unwrap_or_default().to_owned();
+                        let node: Node = match registry.bind(global) {
+                            Ok(node) => node,
+                            Err(err) => return println!("Failed to bind PipeWire node: {}", err),
+                        };
+                        let id = global.id;
+                        let listener = node
+                            .add_listener_local()
+                            .param({
+                                let graph = Rc::clone(&graph);
+                                let sender = sender.clone();
+                                let mainloop = mainloop.clone();
+                                move |_, _, _, _, param| {
+                                    let param = match param {
+                                        Some(param) => param,
+                                        None => return,
+                                    };
+                                    let mut graph = graph.borrow_mut();
+                                    if let Some(sink) = graph.sinks.get_mut(&id) {
+                                        let (muted, percentage) = parse_props(param.as_bytes());
+                                        sink.muted = muted.unwrap_or(sink.muted);
+                                        sink.percentage = percentage.or(sink.percentage);
+                                    }
+                                    if !graph.send(&sender) {
+                                        mainloop.quit();
+                                    }
+                                }
+                            })
+                            .register();
+                        node.subscribe_params(&[ParamType::Props]);
+                        graph.borrow_mut().sinks.insert(
+                            id,
+                            Sink {
+                                name,
+                                muted: false,
+                                percentage: None,
+                                _node: node,
+                                _listener: listener,
+                            },
+                        );
+                    }
+                    ObjectType::Metadata if props.get("metadata.name") == Some("default") => {
+                        let metadata: Metadata = match registry.bind(global) {
+                            Ok(metadata) => metadata,
+                            Err(err) => return println!("Failed to bind PipeWire metadata: {}", err),
+                        };
+                        let listener = metadata
+                            .add_listener_local()
+                            .property({
+                                let graph = Rc::clone(&graph);
+                                let sender = sender.clone();
+                                let mainloop = mainloop.clone();
+                                move |_, key, _, value| {
+                                    if key == Some("default.audio.sink") {
+                                        let mut graph = graph.borrow_mut();
+                                        graph.default_sink = value.and_then(parse_sink_name);
+                                        if !graph.send(&sender) {
+                                            mainloop.quit();
+                                        }
+                                    }
+                                    0
+                                }
+                            })
+                            .register();
+                        graph.borrow_mut().metadata = Some((metadata, listener));
+                    }
+                    _ => (),
+                }
+            }
+        })
+        .global_remove({
+            let graph = Rc::clone(&graph);
+            let sender = sender.clone();
+            let mainloop = mainloop.clone();
+            move |id| {
+                let mut graph = graph.borrow_mut();
+                if graph.sinks.remove(&id).is_some() && !graph.send(&sender) {
+                    mainloop.quit();
+                }
+            }
+        })
+        .register();
+
+    mainloop.run();
+    Ok(())
+}
+
+// Reads whether a node is muted and its volume as a percentage from its
+// `Props` param, if the param includes them.
+//
+// PipeWire stores channel volumes cubed, so (like `wpctl`) we show the cube
+// root of the average as the percentage.
+fn parse_props(bytes: &[u8]) -> (Option<bool>, Option<f32>) {
+    let properties = match PodDeserializer::deserialize_any_from(bytes) {
+        Ok((_, Value::Object(object))) => object.properties,
+        _ => return (None, None),
+    };
+
+    let (mut muted, mut percentage) = (None, None);
+    for property in properties {
+        match (property.key, property.value) {
+            (libspa_sys::SPA_PROP_mute, Value::Bool(mute)) => muted = Some(mute),
+            (libspa_sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(volumes)))
+                if !volumes.is_empty() =>
+            {
+                let average = volumes.iter().sum::<f32>() / volumes.len() as f32;
+                percentage = Some(average.cbrt() * 100.0);
+            }
+            _ => (),
+        }
+    }
+    (muted, percentage)
+}
+
+// The default sink metadata is JSON, e.g. `{ "name": "alsa_output.pci" }`.
+// It's simple enough to pick the name out without a JSON parser.
+fn parse_sink_name(value: &str) -> Option<String> {
+    let rest = &value[value.find("\"name\"")? + "\"name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_owned())
+}
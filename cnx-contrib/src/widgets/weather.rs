@@ -1,31 +1,183 @@
+mod forecast;
+mod openweathermap;
+
+use crate::polling::Polling;
 use anyhow::Result;
-use async_stream::try_stream;
+use async_stream::stream;
 use cnx::text::{Attributes, Text};
 use cnx::widgets::{WidgetStream, WidgetStreamI};
-use tokio_stream::Stream;
 use std::time::Duration;
-use weathernoaa::weather::*;
+use tokio_stream::Stream;
+use weathernoaa::weather::get_weather;
+
+pub use forecast::{Forecast, ForecastPeriod};
+pub use openweathermap::OpenWeatherMap;
+
+// The weather doesn't change quickly, and the services are shared, so don't
+// poll them (or retry failures) more often than this.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A temperature, in both units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Temperature {
+    pub celsius: f64,
+    pub fahrenheit: f64,
+}
+
+impl Temperature {
+    pub fn from_celsius(celsius: f64) -> Self {
+        Temperature {
+            celsius,
+            fahrenheit: celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f64) -> Self {
+        Temperature {
+            celsius: (fahrenheit - 32.0) * 5.0 / 9.0,
+            fahrenheit,
+        }
+    }
+
+    /// The temperature in the given units, i.e. in Celsius or Fahrenheit.
+    pub fn in_units(&self, units: Units) -> f64 {
+        match units {
+            Units::Metric => self.celsius,
+            Units::Imperial => self.fahrenheit,
+        }
+    }
+}
+
+/// Which units a [`Weather`] widget shows temperatures in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Units {
+    /// Degrees Celsius.
+    Metric,
+    /// Degrees Fahrenheit.
+    Imperial,
+}
+
+impl Units {
+    /// The symbol for temperatures in these units, i.e. `°C` or `°F`.
+    pub fn temperature_symbol(self) -> &'static str {
+        match self {
+            Units::Metric => "°C",
+            Units::Imperial => "°F",
+        }
+    }
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units::Metric
+    }
+}
+
+/// The wind speed and direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wind {
+    pub speed_kmh: f64,
+    /// The compass direction the wind is blowing from, e.g. "NW", if it isn't
+    /// variable.
+    pub direction: Option<String>,
+}
+
+/// The weather passed to a [`Weather`] widget's render closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeatherInfo {
+    /// The current sky condition, e.g. "partly cloudy".
+    pub sky_condition: String,
+    /// The current temperature.
+    pub temperature: Temperature,
+    /// The current wind, if reported.
+    pub wind: Option<Wind>,
+    /// The relative humidity as a percentage, if reported.
+    pub humidity: Option<f64>,
+    /// The air pressure in hPa, if reported.
+    pub pressure: Option<f64>,
+    /// The upcoming forecast periods, soonest first. This is empty unless the
+    /// widget was created with [`Weather::new_with_forecast()`], or if the
+    /// forecast couldn't be fetched.
+    pub forecast: Vec<ForecastPeriod>,
+    /// The units the widget was created with, e.g. to show the temperature
+    /// with [`Temperature::in_units()`]. Temperatures are available in both
+    /// units regardless.
+    pub units: Units,
+}
+
+// NOAA reports fail to parse as numbers when a reading is missing, so treat
+// anything that isn't a number as missing.
+fn parse_reading(reading: impl ToString) -> Option<f64> {
+    reading.to_string().trim().parse().ok()
+}
+
+impl From<weathernoaa::weather::WeatherInfo> for WeatherInfo {
+    fn from(info: weathernoaa::weather::WeatherInfo) -> Self {
+        let wind = parse_reading(&info.wind.kmh).map(|speed_kmh| Wind {
+            speed_kmh,
+            direction: Some(info.wind.cardinal.clone()).filter(|d| !d.is_empty()),
+        });
+        WeatherInfo {
+            sky_condition: info.sky_condition,
+            temperature: Temperature {
+                celsius: f64::from(info.temperature.celsius),
+                fahrenheit: f64::from(info.temperature.fahrenheit),
+            },
+            wind,
+            humidity: parse_reading(&info.humidity),
+            pressure: parse_reading(&info.pressure),
+            forecast: Vec::new(),
+            units: Units::default(),
+        }
+    }
+}
+
+/// Where a [`Weather`] widget fetches the current weather from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeatherSource {
+    /// A NOAA weather station, by its code, e.g. `"VOBL"`. See
+    /// [`Weather::new_with_render()`] for how to find it.
+    Noaa(String),
+    /// OpenWeatherMap, for a latitude and longitude.
+    OpenWeatherMap(OpenWeatherMap),
+}
 
 /// Represents Weather widget used to show current weather information.
+///
+/// The weather comes from a NOAA weather station by default, or from
+/// another [`WeatherSource`] with [`Weather::new_with_source()`]. If a fetch
+/// fails, the last weather fetched is still shown, dimmed.
 pub struct Weather<F: Fn(WeatherInfo) -> String> {
     attr: Attributes,
-    station_code: String,
+    source: WeatherSource,
+    units: Units,
+    forecast: Option<Forecast>,
+    polling: Polling,
     render: F,
 }
 
 fn default_render(info: WeatherInfo) -> String {
-    format!("Temp: {}°C", info.temperature.celsius)
+    format!(
+        "Temp: {:.0}{}",
+        info.temperature.in_units(info.units),
+        info.units.temperature_symbol()
+    )
 }
 
 impl Weather<fn(WeatherInfo) -> String> {
     pub fn new(
         attr: Attributes,
-        station_code: String
+        station_code: String,
+        units: Units,
+        polling: Option<Polling>,
     ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Weather {
                 attr,
-                station_code,
+                source: WeatherSource::Noaa(station_code),
+                units,
+                forecast: None,
+                polling: polling.unwrap_or_default().clamped("Weather", MIN_POLL_INTERVAL),
                 render: default_render,
             },
             Self::into_stream
@@ -49,6 +201,15 @@ impl<F: Fn(WeatherInfo) -> String + 'static> Weather<F> {
     /// data](https://web.archive.org/web/20210522235412/https://www1.ncdc.noaa.gov/pub/data/noaa/isd-history.txt)
     /// of the same link.
     ///
+    /// * `units` - Which units the default rendering shows the temperature
+    /// in. The [`WeatherInfo`] passed to `render` has both, and says which
+    /// was chosen.
+    ///
+    /// * `polling` - Controls how often the weather is fetched, how
+    /// quickly to retry after failures and how long to wait for a response.
+    /// Defaults to every 30 minutes, with a 10 second timeout. Intervals
+    /// shorter than 30 seconds are raised to that.
+    ///
     /// * `render` - We use the closure to control the way output is
     /// displayed in the bar. [`WeatherInfo`] represents the current
     /// weather details of the particular station.
@@ -73,7 +234,7 @@ impl<F: Fn(WeatherInfo) -> String + 'static> Weather<F> {
     /// };
     ///
     /// let mut cnx = Cnx::new(Position::Top);
-    /// cnx.add_widget(Weather::new(attr, "VOBL".into(),  None));
+    /// cnx.add_widget(Weather::new(attr, "VOBL".into(), Units::Metric, None));
     /// # Ok(())
     /// # }
     /// # fn main() { run().unwrap(); }
@@ -81,34 +242,167 @@ impl<F: Fn(WeatherInfo) -> String + 'static> Weather<F> {
     pub fn new_with_render(
         attr: Attributes,
         station_code: String,
+        units: Units,
+        polling: Option<Polling>,
         render: F,
     ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Weather {
                 attr,
-                station_code,
+                source: WeatherSource::Noaa(station_code),
+                units,
+                forecast: None,
+                polling: polling.unwrap_or_default().clamped("Weather", MIN_POLL_INTERVAL),
                 render,
             },
             Self::into_stream
         )
     }
 
+    /// Creates a new [`Weather`] widget that also fetches a forecast.
+    ///
+    /// This is like [`Weather::new_with_render()`], but the [`WeatherInfo`]
+    /// passed to `render` includes the next `forecast.periods` periods of the
+    /// forecast for the given location, e.g. to show today's high and
+    /// tonight's low:
+    ///
+    /// ```no_run
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::weather::*;
+    /// let forecast = Forecast {
+    ///     latitude: 40.7128,
+    ///     longitude: -74.0060,
+    ///     periods: 2,
+    /// };
+    /// let render = |weather: WeatherInfo| {
+    ///     let periods: Vec<String> = weather
+    ///         .forecast
+    ///         .iter()
+    ///         .map(|period| format!("{}: {:.0}°C", period.name, period.temperature.celsius))
+    ///         .collect();
+    ///     format!("{:.0}°C ({})", weather.temperature.celsius, periods.join(", "))
+    /// };
+    /// let weather = Weather::new_with_forecast(
+    ///     Attributes::default(),
+    ///     "KNYC".into(),
+    ///     Units::Metric,
+    ///     forecast,
+    ///     None,
+    ///     render,
+    /// );
+    /// ```
+    pub fn new_with_forecast(
+        attr: Attributes,
+        station_code: String,
+        units: Units,
+        forecast: Forecast,
+        polling: Option<Polling>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Weather {
+                attr,
+                source: WeatherSource::Noaa(station_code),
+                units,
+                forecast: Some(forecast),
+                polling: polling.unwrap_or_default().clamped("Weather", MIN_POLL_INTERVAL),
+                render,
+            },
+            Self::into_stream
+        )
+    }
+
+    /// Creates a new [`Weather`] widget that fetches the weather from
+    /// `source`, e.g. from OpenWeatherMap:
+    ///
+    /// ```no_run
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::weather::*;
+    /// let source = WeatherSource::OpenWeatherMap(OpenWeatherMap {
+    ///     api_key: "0123456789abcdef".into(),
+    ///     latitude: 51.5072,
+    ///     longitude: -0.1276,
+    /// });
+    /// let render = |weather: WeatherInfo| {
+    ///     format!("{} {:.0}°C", weather.sky_condition, weather.temperature.celsius)
+    /// };
+    /// let weather =
+    ///     Weather::new_with_source(Attributes::default(), source, Units::Metric, None, render);
+    /// ```
+    ///
+    /// The other arguments are as for [`Weather::new_with_render()`].
+    pub fn new_with_source(
+        attr: Attributes,
+        source: WeatherSource,
+        units: Units,
+        polling: Option<Polling>,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Weather {
+                attr,
+                source,
+                units,
+                forecast: None,
+                polling: polling.unwrap_or_default().clamped("Weather", MIN_POLL_INTERVAL),
+                render,
+            },
+            Self::into_stream
+        )
+    }
+
+    async fn fetch(&self) -> Result<WeatherInfo> {
+        let mut info = match &self.source {
+            WeatherSource::Noaa(station_code) => {
+                WeatherInfo::from(get_weather(station_code.clone()).await?)
+            }
+            WeatherSource::OpenWeatherMap(location) => openweathermap::fetch(location).await?,
+        };
+        // A failed forecast shouldn't hide the current weather, so it's
+        // shown without one.
+        if let Some(forecast) = &self.forecast {
+            match forecast::fetch(forecast).await {
+                Ok(periods) => info.forecast = periods,
+                Err(err) => println!("Error fetching forecast: {:#}", err),
+            }
+        }
+        info.units = self.units;
+        Ok(info)
+    }
+
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        let stream = try_stream! {
+        let stream = stream! {
+            let mut last: Option<Vec<Text>> = None;
+            let mut failures = 0;
             loop {
-                let weather = get_weather(self.station_code.clone()).await?;
-                let text = (self.render)(weather);
-                let texts = vec![Text {
-                    attr: self.attr.clone(),
-                    text,
-                    stretch: false,
-                    markup: true,
-                }];
-                yield texts;
-
-                let thirty_minutes = 30 * 60;
-                let sleep_for = Duration::from_secs(thirty_minutes);
-                tokio::time::sleep(sleep_for).await;
+                match self.polling.fetch(self.fetch()).await {
+                    Ok(weather) => {
+                        failures = 0;
+                        let text = (self.render)(weather);
+                        let texts = vec![Text::new(self.attr.clone(), text).markup(true)];
+                        last = Some(texts.clone());
+                        yield Ok(texts);
+                    }
+                    // Keep showing the last known weather (dimmed, as it's
+                    // stale) rather than an error if we've gone offline.
+                    Err(err) => {
+                        failures += 1;
+                        match &last {
+                            Some(texts) => {
+                                println!("Error fetching weather: {:#}", err);
+                                let dimmed = texts
+                                    .iter()
+                                    .cloned()
+                                    .map(|text| text.dim(true))
+                                    .collect();
+                                yield Ok(dimmed);
+                            }
+                            None => yield Err(err),
+                        }
+                    }
+                }
+
+                tokio::time::sleep(self.polling.delay(failures)).await;
             }
         };
         Ok(stream)
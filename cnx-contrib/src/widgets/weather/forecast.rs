@@ -0,0 +1,114 @@
+use super::Temperature;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Where and how far ahead to fetch a forecast for.
+///
+/// Forecasts come from the US National Weather Service
+/// (<https://api.weather.gov>), so are only available for locations in the
+/// US.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Forecast {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// How many periods to fetch. Each period is half a day, e.g. "Tonight"
+    /// followed by "Tuesday".
+    pub periods: usize,
+}
+
+/// A single period of a forecast.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForecastPeriod {
+    /// The name of the period, e.g. "Tonight" or "Tuesday".
+    pub name: String,
+    /// Whether this is a daytime period. The temperature of a daytime period
+    /// is its high and the temperature of a night period is its low.
+    pub is_daytime: bool,
+    pub temperature: Temperature,
+    /// A short description of the conditions, e.g. "Partly Cloudy".
+    pub short_forecast: String,
+}
+
+#[derive(Deserialize)]
+struct Points {
+    properties: PointsProperties,
+}
+
+#[derive(Deserialize)]
+struct PointsProperties {
+    forecast: String,
+}
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+    properties: ForecastProperties,
+}
+
+#[derive(Deserialize)]
+struct ForecastProperties {
+    periods: Vec<Period>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Period {
+    name: String,
+    is_daytime: bool,
+    temperature: f64,
+    temperature_unit: String,
+    short_forecast: String,
+}
+
+impl From<Period> for ForecastPeriod {
+    fn from(period: Period) -> Self {
+        let temperature = match &period.temperature_unit[..] {
+            "F" => Temperature::from_fahrenheit(period.temperature),
+            _ => Temperature::from_celsius(period.temperature),
+        };
+        ForecastPeriod {
+            name: period.name,
+            is_daytime: period.is_daytime,
+            temperature,
+            short_forecast: period.short_forecast,
+        }
+    }
+}
+
+// The API rejects requests without a User-Agent.
+const USER_AGENT: &str = concat!("cnx-contrib/", env!("CARGO_PKG_VERSION"));
+
+pub(super) async fn fetch(forecast: &Forecast) -> Result<Vec<ForecastPeriod>> {
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+
+    // The forecast is published per grid square, so first look up which
+    // square the location is in.
+    let url = format!(
+        "https://api.weather.gov/points/{:.4},{:.4}",
+        forecast.latitude, forecast.longitude
+    );
+    let points: Points = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to look up forecast location")?;
+
+    let response: ForecastResponse = client
+        .get(&points.properties.forecast)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to fetch forecast")?;
+
+    Ok(response
+        .properties
+        .periods
+        .into_iter()
+        .take(forecast.periods)
+        .map(ForecastPeriod::from)
+        .collect())
+}
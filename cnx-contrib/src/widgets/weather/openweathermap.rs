@@ -0,0 +1,117 @@
+use super::{Temperature, Units, WeatherInfo, Wind};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt;
+
+/// Where to fetch the weather for from [OpenWeatherMap], and the API key to
+/// fetch it with.
+///
+/// The weather is fetched with the [One Call API], which works anywhere in
+/// the world, but needs an API key with a One Call subscription. The
+/// subscription includes 1,000 free calls a day, plenty for the default
+/// polling interval.
+///
+/// [OpenWeatherMap]: https://openweathermap.org
+/// [One Call API]: https://openweathermap.org/api/one-call-3
+#[derive(Clone, PartialEq)]
+pub struct OpenWeatherMap {
+    pub api_key: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+// The API key is a secret, so keep it out of logs.
+impl fmt::Debug for OpenWeatherMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpenWeatherMap")
+            .field("api_key", &"<redacted>")
+            .field("latitude", &self.latitude)
+            .field("longitude", &self.longitude)
+            .finish()
+    }
+}
+
+const ONE_CALL_URL: &str = "https://api.openweathermap.org/data/3.0/onecall";
+
+#[derive(Deserialize)]
+struct OneCall {
+    current: Current,
+}
+
+// The current weather, in metric units. Readings that a location doesn't
+// report are left out.
+#[derive(Deserialize)]
+struct Current {
+    temp: f64,
+    humidity: Option<f64>,
+    pressure: Option<f64>,
+    // In metres per second.
+    wind_speed: Option<f64>,
+    // The direction the wind is blowing from, in degrees clockwise from
+    // north.
+    wind_deg: Option<f64>,
+    #[serde(default)]
+    weather: Vec<Condition>,
+}
+
+#[derive(Deserialize)]
+struct Condition {
+    // e.g. "broken clouds".
+    description: String,
+}
+
+const CARDINALS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+// The nearest compass direction to `degrees`, e.g. "NW" for 310°.
+fn cardinal(degrees: f64) -> String {
+    let index = (degrees.rem_euclid(360.0) / 45.0).round() as usize % CARDINALS.len();
+    CARDINALS[index].to_owned()
+}
+
+impl From<Current> for WeatherInfo {
+    fn from(current: Current) -> Self {
+        let wind = current.wind_speed.map(|speed| Wind {
+            speed_kmh: speed * 3.6,
+            direction: current.wind_deg.map(cardinal),
+        });
+        let sky_condition = current
+            .weather
+            .into_iter()
+            .map(|condition| condition.description)
+            .next()
+            .unwrap_or_default();
+        WeatherInfo {
+            sky_condition,
+            temperature: Temperature::from_celsius(current.temp),
+            wind,
+            humidity: current.humidity,
+            pressure: current.pressure,
+            forecast: Vec::new(),
+            units: Units::default(),
+        }
+    }
+}
+
+pub(super) async fn fetch(location: &OpenWeatherMap) -> Result<WeatherInfo> {
+    let query = [
+        ("lat", location.latitude.to_string()),
+        ("lon", location.longitude.to_string()),
+        ("exclude", "minutely,hourly,daily,alerts".to_owned()),
+        ("units", "metric".to_owned()),
+        ("appid", location.api_key.clone()),
+    ];
+    // Errors are stripped of the URL, as it contains the API key.
+    let response: OneCall = reqwest::Client::new()
+        .get(ONE_CALL_URL)
+        .query(&query)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(reqwest::Error::without_url)
+        .context("Failed to fetch weather from OpenWeatherMap")?
+        .json()
+        .await
+        .map_err(reqwest::Error::without_url)
+        .context("Failed to parse weather from OpenWeatherMap")?;
+    Ok(WeatherInfo::from(response.current))
+}
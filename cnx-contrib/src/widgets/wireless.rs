@@ -2,15 +2,72 @@ use anyhow::Result;
 use cnx::text::{Attributes, Text, Threshold};
 use cnx::widgets::{WidgetStream, WidgetStreamI};
 use iwlib::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
 use std::time::Duration;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
 use tokio_stream::{StreamExt, Stream};
 
+// Where the kernel lists network interfaces.
+const NET_CLASS: &str = "/sys/class/net";
+
+/// Which network interface a [`Wireless`] widget shows.
+///
+/// Interface names can change, e.g. after a kernel update, so rather than
+/// naming an interface exactly, it can be found by type or by pattern. The
+/// interface is found again whenever the one in use disappears.
+#[derive(Clone, Debug)]
+pub enum Interface {
+    /// The interface with exactly this name.
+    Name(String),
+    /// The first wireless interface, by name.
+    FirstWireless,
+    /// The first interface, by name, whose name matches the pattern, e.g.
+    /// `^wl`.
+    Matching(Regex),
+}
+
+impl Interface {
+    fn find(&self) -> Option<String> {
+        match self {
+            Interface::Name(name) => Some(name.clone()),
+            Interface::FirstWireless => interfaces().into_iter().find(|name| is_wireless(name)),
+            Interface::Matching(pattern) => {
+                interfaces().into_iter().find(|name| pattern.is_match(name))
+            }
+        }
+    }
+}
+
+// The names of the network interfaces, sorted.
+fn interfaces() -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(NET_CLASS) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+fn is_wireless(name: &str) -> bool {
+    Path::new(NET_CLASS).join(name).join("wireless").exists()
+}
+
+fn exists(name: &str) -> bool {
+    Path::new(NET_CLASS).join(name).exists()
+}
+
 /// Wireless widget to show wireless information for a particular ESSID
 pub struct Wireless {
     attr: Attributes,
-    interface: String,
+    interface: Interface,
+    // The name of the interface currently in use, if one was found.
+    resolved: Option<String>,
     update_interval: Duration,
     threshold: Option<Threshold>,
 }
@@ -56,10 +113,30 @@ impl Wireless {
     /// # fn main() { run().unwrap(); }
     /// ```
     pub fn new(attr: Attributes, interface: String, threshold: Option<Threshold>) -> WidgetStream<Wireless, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_interface(attr, Interface::Name(interface), threshold)
+    }
+
+    /// Creates a new [`Wireless`] widget for the given [`Interface`], which
+    /// can be found by name, type or pattern, e.g. to show whichever
+    /// wireless interface the system has:
+    ///
+    /// ```
+    /// # use cnx::text::Attributes;
+    /// # use cnx_contrib::widgets::wireless::{Interface, Wireless};
+    /// let wireless = Wireless::new_with_interface(Attributes::default(), Interface::FirstWireless, None);
+    /// ```
+    ///
+    /// The other arguments are as for [`Wireless::new()`].
+    pub fn new_with_interface(
+        attr: Attributes,
+        interface: Interface,
+        threshold: Option<Threshold>,
+    ) -> WidgetStream<Wireless, impl Stream<Item = WidgetStreamI>> {
         WidgetStream::new(
             Wireless {
                 update_interval: Duration::from_secs(3600),
                 interface,
+                resolved: None,
                 attr,
                 threshold,
             },
@@ -67,19 +144,39 @@ impl Wireless {
         )
     }
 
-    fn tick(&self) -> Vec<Text> {
-        let wireless_info = get_wireless_info(self.interface.clone());
+    // Returns the name of the interface to show, finding it again if the one
+    // in use has disappeared.
+    fn resolve(&mut self) -> Option<String> {
+        if let Some(name) = self.resolved.as_ref().filter(|name| exists(name)) {
+            return Some(name.clone());
+        }
+        let found = self.interface.find();
+        if found != self.resolved {
+            match &found {
+                Some(name) => println!("Wireless: using interface {}", name),
+                None => println!("Wireless: no interface matches {:?}", self.interface),
+            }
+        }
+        self.resolved = found;
+        self.resolved.clone()
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        let wireless_info = self.resolve().and_then(get_wireless_info);
 
+        let mut blink = false;
         let text = match wireless_info {
             Some(info) => match &self.threshold {
                 Some(thold) => {
-                    let color = if info.wi_quality <= thold.low.threshold {
-                        &thold.low.color
+                    let level = if info.wi_quality <= thold.low.threshold {
+                        &thold.low
                     } else if info.wi_quality <= thold.normal.threshold {
-                        &thold.normal.color
+                        &thold.normal
                     } else {
-                        &thold.high.color
+                        &thold.high
                     };
+                    let color = &level.color;
+                    blink = level.blink;
                     format!(
                         "<span foreground=\"#808080\">[</span>{} <span foreground=\"{}\">{}%</span><span foreground=\"#808080\">]</span>",
                         info.wi_essid,
@@ -91,15 +188,12 @@ impl Wireless {
             },
             None => "NA".to_owned(),
         };
-        vec![Text {
-            attr: self.attr.clone(),
-            text,
-            stretch: false,
-            markup: self.threshold.is_some(),
-        }]
+        vec![Text::new(self.attr.clone(), text)
+            .markup(self.threshold.is_some())
+            .blink(blink)]
     }
 
-    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+    fn into_stream(mut self) -> Result<impl Stream<Item = WidgetStreamI>> {
         let interval = time::interval(self.update_interval);
         let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
 
@@ -1,12 +1,18 @@
+use std::collections::BTreeMap;
 use std::f64;
+use std::mem;
+use std::rc::Rc;
 
 use anyhow::{anyhow, Context, Result};
 use ordered_float::OrderedFloat;
 use xcb_util::ewmh;
 
-use crate::text::{ComputedText, Text};
-// use crate::widgets::{Widget, WidgetList};
-// use crate::xcb::XcbEventStream;
+use crate::text::{Attributes, Background, Color, ComputedText, Text};
+use crate::widgets::{ClickEvent, MouseButton, WindowState};
+use crate::xcb::{
+    create_hand_cursor, grab_hotkey, hotkey_modifiers, monitors, primary_monitor,
+    screen_change_size, select_screen_change_events, Monitor, ALL_DESKTOPS,
+};
 
 fn get_root_visual_type(conn: &xcb::Connection, screen: &xcb::Screen<'_>) -> xcb::Visualtype {
     for root in conn.get_setup().roots() {
@@ -21,10 +27,26 @@ fn get_root_visual_type(conn: &xcb::Connection, screen: &xcb::Screen<'_>) -> xcb
     panic!("No visual type found");
 }
 
+// Finds a 32-bit TrueColor visual, whose alpha channel lets the bar be
+// translucent under a compositor.
+fn get_argb_visual_type(screen: &xcb::Screen<'_>) -> Option<xcb::Visualtype> {
+    for allowed_depth in screen.allowed_depths() {
+        if allowed_depth.depth() != 32 {
+            continue;
+        }
+        for visual in allowed_depth.visuals() {
+            if visual.class() == xcb::VISUAL_CLASS_TRUE_COLOR as u8 {
+                return Some(visual);
+            }
+        }
+    }
+    None
+}
+
 /// Creates a `cairo::Surface` for the XCB window with the given `id`.
 fn cairo_surface_for_xcb_window(
     conn: &xcb::Connection,
-    screen: &xcb::Screen<'_>,
+    mut visual_type: xcb::Visualtype,
     id: u32,
     width: i32,
     height: i32,
@@ -34,7 +56,7 @@ fn cairo_surface_for_xcb_window(
     };
     let visual = unsafe {
         cairo::XCBVisualType::from_raw_none(
-            &mut get_root_visual_type(conn, screen).base as *mut xcb::ffi::xcb_visualtype_t
+            &mut visual_type.base as *mut xcb::ffi::xcb_visualtype_t
                 as *mut cairo_sys::xcb_visualtype_t,
         )
     };
@@ -44,27 +66,110 @@ fn cairo_surface_for_xcb_window(
     Ok(surface)
 }
 
+// Creates the off-screen surface that the bar is drawn into before being
+// copied to the window. It has an alpha channel, for translucent colors,
+// which is dropped when copying to a window without one.
+fn create_buffer(width: u16, height: u16) -> Result<cairo::ImageSurface> {
+    cairo::ImageSurface::create(cairo::Format::ARgb32, i32::from(width), i32::from(height))
+        .map_err(|status| anyhow!("ImageSurface::create: {}", status))
+}
+
+/// Renders `texts` to an image `width` by `height` pixels, as they would be
+/// drawn by a bar of that size with a single widget.
+///
+/// This uses the bar's own layout and drawing code, but doesn't need an X
+/// server, so is useful for testing widgets and for screenshots:
+///
+/// ```no_run
+/// # use cnx::render_to_image;
+/// # use cnx::text::{Attributes, Text};
+/// # fn run() -> anyhow::Result<()> {
+/// let text = Text::new(Attributes::default(), "Hello".to_owned());
+/// let image = render_to_image(vec![text], 200, 24)?;
+/// let mut file = std::fs::File::create("hello.png")?;
+/// image.write_to_png(&mut file)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Blinking texts are drawn in their visible state, and attributes left as
+/// defaults (see [`Attributes::default()`]) are those the bar starts with.
+///
+/// [`Attributes::default()`]: text/struct.Attributes.html#impl-Default
+pub fn render_to_image(texts: Vec<Text>, width: u16, height: u16) -> Result<cairo::ImageSurface> {
+    let image = create_buffer(width, height)?;
+    let background = Background::default();
+    let context = cairo::Context::new(&image);
+    background.apply_to_context(&context, f64::from(width), f64::from(height));
+    context.paint();
+
+    let default_attr = Attributes::initial();
+    let mut texts = texts
+        .into_iter()
+        .map(|text| text.inherit(&default_attr).compute(&image, None, 1.0))
+        .collect::<Result<Vec<_>>>()?;
+    for text in &mut texts {
+        text.height = f64::from(height);
+    }
+    let mut contents = vec![texts];
+    lay_out(&mut contents, &[Zone::Left], &[0.0], f64::from(width));
+
+    for text in &contents[0] {
+        text.render(&image, true, &background)?;
+    }
+    image.flush();
+    Ok(image)
+}
+
 fn create_surface(
     conn: &xcb::Connection,
     screen_idx: usize,
     window_id: u32,
+    width: u16,
     height: u16,
-) -> Result<(u16, cairo::XCBSurface)> {
+) -> Result<cairo::XCBSurface> {
     let screen = conn
         .get_setup()
         .roots()
         .nth(screen_idx)
         .ok_or_else(|| anyhow!("Invalid screen"))?;
-    let values = [
+    let mut values = vec![
         (xcb::CW_BACK_PIXEL, screen.black_pixel()),
-        (xcb::CW_EVENT_MASK, xcb::EVENT_MASK_EXPOSURE),
+        (
+            xcb::CW_EVENT_MASK,
+            xcb::EVENT_MASK_EXPOSURE
+                | xcb::EVENT_MASK_BUTTON_PRESS
+                | xcb::EVENT_MASK_POINTER_MOTION
+                | xcb::EVENT_MASK_ENTER_WINDOW
+                | xcb::EVENT_MASK_LEAVE_WINDOW,
+        ),
     ];
 
-    let width = screen.width_in_pixels();
+    // Use a visual with an alpha channel if there is one. A window whose
+    // depth differs from the root's needs its own colormap and border.
+    let (depth, visual_type) = match get_argb_visual_type(&screen) {
+        Some(visual_type) => {
+            let colormap = conn.generate_id();
+            xcb::create_colormap(
+                conn,
+                xcb::COLORMAP_ALLOC_NONE as u8,
+                colormap,
+                screen.root(),
+                visual_type.visual_id(),
+            );
+            values.push((xcb::CW_BORDER_PIXEL, 0));
+            values.push((xcb::CW_COLORMAP, colormap));
+            (32, visual_type)
+        }
+        None => (
+            xcb::COPY_FROM_PARENT as u8,
+            get_root_visual_type(conn, &screen),
+        ),
+    };
 
     xcb::create_window(
         &conn,
-        xcb::COPY_FROM_PARENT as u8,
+        depth,
         window_id,
         screen.root(),
         0,
@@ -73,19 +178,19 @@ fn create_surface(
         height,
         0,
         xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
-        screen.root_visual(),
+        visual_type.visual_id(),
         &values,
     );
 
     let surface = cairo_surface_for_xcb_window(
         &conn,
-        &screen,
+        visual_type,
         window_id,
         i32::from(width),
         i32::from(height),
     )?;
 
-    Ok((width, surface))
+    Ok(surface)
 }
 
 /// An enum specifying the position of the Cnx bar.
@@ -107,48 +212,372 @@ pub enum Position {
     Top,
     /// Position the Cnx bar at the bottom of the screen.
     Bottom,
+    /// Position the Cnx bar down the left side of the screen.
+    ///
+    /// Widgets are stacked from top to bottom rather than laid out from left
+    /// to right, so the [`Zone::Left`] zone is at the top of the bar and the
+    /// [`Zone::Right`] zone at the bottom. The bar is as wide as its widest
+    /// text.
+    Left,
+    /// Position the Cnx bar down the right side of the screen, with widgets
+    /// stacked as for [`Position::Left`].
+    Right,
+}
+
+impl Position {
+    // Whether the bar runs down the side of the screen, with its widgets
+    // stacked vertically.
+    fn is_vertical(&self) -> bool {
+        matches!(self, Position::Left | Position::Right)
+    }
+}
+
+/// Which part of the bar a widget is shown in.
+///
+/// Passed to [`Cnx::add_widget_in_zone()`]. Widgets in each zone are laid out
+/// left to right in the order they were added. The left zone starts at the
+/// left edge of the bar, the right zone ends at the right edge and the center
+/// zone is centered on the bar, regardless of how wide the other zones are
+/// (unless they would overlap). On a vertical bar ([`Position::Left`] or
+/// [`Position::Right`]), widgets are laid out top to bottom instead, and the
+/// left zone is at the top.
+///
+/// [`Cnx::add_widget_in_zone()`]: struct.Cnx.html#method.add_widget_in_zone
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Left,
+    Center,
+    Right,
+}
+
+/// Where the bar is stacked relative to other windows, such as other docks.
+///
+/// Passed to [`Cnx::stacking()`].
+///
+/// [`Cnx::stacking()`]: struct.Cnx.html#method.stacking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stacking {
+    /// Raise the bar when it is configured, but leave its stacking to the
+    /// window manager otherwise.
+    Default,
+    /// Keep the bar above other windows (`_NET_WM_STATE_ABOVE`).
+    Above,
+    /// Keep the bar below other windows (`_NET_WM_STATE_BELOW`).
+    Below,
+}
+
+impl Default for Stacking {
+    fn default() -> Stacking {
+        Stacking::Default
+    }
+}
+
+/// Which fullscreen windows hide the bar.
+///
+/// Passed to [`Cnx::hide_on_fullscreen()`].
+///
+/// [`Cnx::hide_on_fullscreen()`]: struct.Cnx.html#method.hide_on_fullscreen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HideOnFullscreen {
+    /// Hide the bar while the focused window is fullscreen.
+    Focused,
+    /// Hide the bar while any window on the current desktop is fullscreen.
+    Any,
+}
+
+/// Gaps between the bar and the edges of the screen, in pixels.
+///
+/// Passed to [`Cnx::margins()`]. `left` and `right` inset the bar from the
+/// sides of the screen. `top` is the gap above a bar at the top of the
+/// screen and `bottom` the gap below a bar at the bottom; the other is
+/// ignored.
+///
+/// For a vertical bar, it's the other way around: `top` and `bottom` inset
+/// the bar from the top and bottom of the screen, `left` is the gap beside
+/// a bar on the left of the screen and `right` the gap beside a bar on the
+/// right.
+///
+/// [`Cnx::margins()`]: struct.Cnx.html#method.margins
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Margins {
+    pub left: u16,
+    pub right: u16,
+    pub top: u16,
+    pub bottom: u16,
+}
+
+impl Margins {
+    pub fn new(left: u16, right: u16, top: u16, bottom: u16) -> Margins {
+        Margins {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+}
+
+/// A key combination that triggers a [`HotkeyAction`], wherever the
+/// keyboard focus is. See [`Cnx::hotkey()`].
+///
+/// `keysym` is an X keysym, as listed in `X11/keysymdef.h`, e.g. `0x62` for
+/// `b` or `0xff1b` for Escape. `modifiers` combines the constants below,
+/// e.g. `Hotkey::SUPER | Hotkey::SHIFT`. Caps Lock and Num Lock are ignored.
+///
+/// [`HotkeyAction`]: enum.HotkeyAction.html
+/// [`Cnx::hotkey()`]: struct.Cnx.html#method.hotkey
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Hotkey {
+    pub modifiers: u16,
+    pub keysym: u32,
+}
+
+impl Hotkey {
+    pub const SHIFT: u16 = xcb::MOD_MASK_SHIFT as u16;
+    pub const CONTROL: u16 = xcb::MOD_MASK_CONTROL as u16;
+    /// Usually the Alt key.
+    pub const ALT: u16 = xcb::MOD_MASK_1 as u16;
+    /// Usually the Super (Windows) key.
+    pub const SUPER: u16 = xcb::MOD_MASK_4 as u16;
+
+    pub fn new(modifiers: u16, keysym: u32) -> Hotkey {
+        Hotkey { modifiers, keysym }
+    }
+}
+
+// The bar window an event is for, if it's for one in particular. Other
+// events (e.g. hotkeys and screen changes) are for every bar.
+fn event_window(event: &xcb::GenericEvent) -> Option<xcb::Window> {
+    unsafe {
+        match event.response_type() & !0x80 {
+            xcb::EXPOSE => Some(xcb::cast_event::<xcb::ExposeEvent>(event).window()),
+            xcb::BUTTON_PRESS => Some(xcb::cast_event::<xcb::ButtonPressEvent>(event).event()),
+            xcb::MOTION_NOTIFY => Some(xcb::cast_event::<xcb::MotionNotifyEvent>(event).event()),
+            xcb::ENTER_NOTIFY => Some(xcb::cast_event::<xcb::EnterNotifyEvent>(event).event()),
+            xcb::LEAVE_NOTIFY => Some(xcb::cast_event::<xcb::LeaveNotifyEvent>(event).event()),
+            _ => None,
+        }
+    }
+}
+
+// An event for `Cnx` to act on, returned by `Bar::process_event()`.
+pub enum BarEvent {
+    // A click on the widget with the given index.
+    Click(usize, ClickEvent),
+    // A press of the hotkey with the given index.
+    Hotkey(usize),
+}
+
+// Which part of the screen a bar spans.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    // The primary RandR output, or the whole screen if no output is primary.
+    Primary,
+    // The monitor at this index in `monitors()`.
+    Monitor(usize),
 }
 
 pub struct Bar {
     position: Position,
+    output: Output,
 
-    pub conn: ewmh::Connection,
-    screen_idx: usize,
+    pub conn: Rc<ewmh::Connection>,
+    pub screen_idx: usize,
     window_id: u32,
 
     surface: cairo::XCBSurface,
+    // Widgets are drawn here and then copied to `surface` in one operation,
+    // so that partially drawn updates are never visible.
+    buffer: cairo::ImageSurface,
     width: u16,
     height: u16,
+    // The size of the screen, kept up to date by RandR events.
+    screen_width: u16,
+    screen_height: u16,
+    // The part of the screen the bar is on, given by `output`.
+    area: Monitor,
+    margins: Margins,
+    corner_radius: u16,
+    stacking: Stacking,
+    background: Background,
+    hide_on_fullscreen: Option<HideOnFullscreen>,
+    // Whether the bar is unmapped, because of a fullscreen window or because
+    // it was hidden with a hotkey.
+    hidden: bool,
+    hidden_for_fullscreen: bool,
+    hidden_by_user: bool,
+    // The grabbed keycode and modifiers of each hotkey, with its index.
+    hotkeys: Vec<(xcb::Keycode, u16, usize)>,
+    // The response type of RandR's `ScreenChangeNotify`, if it is available.
+    screen_change_event: Option<u8>,
 
     contents: Vec<Vec<ComputedText>>,
+    // The zone of each widget in `contents`.
+    zones: Vec<Zone>,
+    // The gap between each widget's texts, before scaling.
+    segment_gaps: Vec<f64>,
+    // The name of each widget, if it was given one, for log messages.
+    names: Vec<Option<String>>,
+    // Whether each widget handles clicks, and so shows `hand_cursor`.
+    clickable: Vec<bool>,
+    hand_cursor: xcb::Cursor,
+    // Whether the pointer is currently over a clickable widget.
+    hand_shown: bool,
+    // Multiplies font sizes and padding, for HiDPI displays.
+    scale: f64,
+    // Whether blinking texts are currently shown.
+    blink_visible: bool,
+    // Updates waiting for `flush_updates()`, keyed by widget index.
+    pending: BTreeMap<usize, Vec<Text>>,
+    error_text: Option<Text>,
+    // Fills in the attributes that texts leave as defaults.
+    default_attr: Attributes,
+}
+
+// What needs redrawing after a widget's content changes.
+enum Redraw {
+    Nothing,
+    Content,
+    EntireBar,
+}
+
+// Shown in place of a widget's content when it errors, unless the user has
+// configured their own indicator.
+const DEFAULT_ERROR_GLYPH: &str = "⚠";
+
+// Connects to the X server, returning the connection and the index of the
+// default screen.
+fn connect() -> Result<(Rc<ewmh::Connection>, usize)> {
+    let (conn, screen_idx) =
+        xcb::Connection::connect(None).context("Failed to connect to X server")?;
+    let ewmh_conn = ewmh::Connection::connect(conn)
+        .map_err(|(e, _)| e)
+        .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
+    Ok((Rc::new(ewmh_conn), screen_idx as usize))
+}
+
+// Finds the part of the screen that `output` covers, or `None` if it isn't
+// there any more (e.g. its monitor was unplugged).
+fn output_area(
+    conn: &xcb::Connection,
+    root: xcb::Window,
+    output: Output,
+    screen_width: u16,
+    screen_height: u16,
+) -> Option<Monitor> {
+    match output {
+        Output::Primary => Some(primary_monitor(conn, root).unwrap_or(Monitor {
+            x: 0,
+            y: 0,
+            width: screen_width,
+            height: screen_height,
+        })),
+        Output::Monitor(idx) => monitors(conn, root).ok()?.get(idx).copied(),
+    }
 }
 
 impl Bar {
+    // Creates a bar on the primary output, or spanning the whole screen if
+    // no output is primary.
     pub fn new(position: Position) -> Result<Bar> {
-        let (conn, screen_idx) =
-            xcb::Connection::connect(None).context("Failed to connect to X server")?;
-        let screen_idx = screen_idx as usize;
+        let (conn, screen_idx) = connect()?;
+        Bar::on_output(conn, screen_idx, position, Output::Primary)
+    }
+
+    // Creates a bar on each monitor, all sharing one connection. If RandR
+    // doesn't report any monitors, a single bar is created, as by `new()`.
+    pub fn new_on_all_outputs(position: Position) -> Result<Vec<Bar>> {
+        let (conn, screen_idx) = connect()?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx)
+            .ok_or_else(|| anyhow!("Invalid screen"))?
+            .root();
+        let count = monitors(&conn, root).map_or(0, |monitors| monitors.len());
+        if count == 0 {
+            return Ok(vec![Bar::on_output(
+                conn,
+                screen_idx,
+                position,
+                Output::Primary,
+            )?]);
+        }
+        (0..count)
+            .map(|idx| {
+                let conn = Rc::clone(&conn);
+                Bar::on_output(conn, screen_idx, position.clone(), Output::Monitor(idx))
+            })
+            .collect()
+    }
+
+    fn on_output(
+        conn: Rc<ewmh::Connection>,
+        screen_idx: usize,
+        position: Position,
+        output: Output,
+    ) -> Result<Bar> {
         let window_id = conn.generate_id();
 
-        // We don't actually care about how tall our initial window is - we'll resize
-        // our window once we know how big it needs to be. However, it seems to need
-        // to be bigger than 0px, or either Xcb/Cairo (or maybe QTile?) gets upset.
-        let height = 1;
-        let (width, surface) = create_surface(&conn, screen_idx, window_id, height)?;
+        let screen = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx)
+            .ok_or_else(|| anyhow!("Invalid screen"))?;
+        let screen_width = screen.width_in_pixels();
+        let screen_height = screen.height_in_pixels();
+        let area = output_area(&conn, screen.root(), output, screen_width, screen_height)
+            .ok_or_else(|| anyhow!("Invalid output {:?}", output))?;
 
-        let ewmh_conn = ewmh::Connection::connect(conn)
-            .map_err(|(e, _)| e)
-            .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
+        // We don't actually care about how tall (or, for a vertical bar, how
+        // wide) our initial window is - we'll resize our window once we know
+        // how big it needs to be. However, it seems to need to be bigger than
+        // 0px, or either Xcb/Cairo (or maybe QTile?) gets upset.
+        let (width, height) = if position.is_vertical() {
+            (1, area.height)
+        } else {
+            (area.width, 1)
+        };
+        let surface = create_surface(&conn, screen_idx, window_id, width, height)?;
+        let buffer = create_buffer(width, height)?;
+        let screen_change_event = select_screen_change_events(&conn, screen.root());
+        let hand_cursor = create_hand_cursor(&conn);
 
         let bar = Bar {
-            conn: ewmh_conn,
+            conn,
             window_id,
             screen_idx,
             surface,
+            buffer,
             width,
             height,
+            screen_width,
+            screen_height,
+            area,
+            output,
+            margins: Margins::default(),
+            corner_radius: 0,
+            stacking: Stacking::default(),
+            background: Background::default(),
+            hide_on_fullscreen: None,
+            hidden: false,
+            hidden_for_fullscreen: false,
+            hidden_by_user: false,
+            hotkeys: Vec::new(),
+            screen_change_event,
             position,
             contents: Vec::new(),
+            zones: Vec::new(),
+            segment_gaps: Vec::new(),
+            names: Vec::new(),
+            clickable: Vec::new(),
+            hand_cursor,
+            hand_shown: false,
+            scale: 1.0,
+            blink_visible: true,
+            pending: BTreeMap::new(),
+            error_text: None,
+            default_attr: Attributes::initial(),
         };
         bar.set_ewmh_properties();
 
@@ -167,7 +596,16 @@ impl Bar {
     }
 
     fn map_window(&self) {
-        xcb::map_window(&self.conn, self.window_id);
+        if !self.hidden {
+            xcb::map_window(&self.conn, self.window_id);
+        }
+    }
+
+    // Removes the bar's window, when the bar stops running. Others may still
+    // hold a reference to the connection, so we can't rely on closing it.
+    pub fn destroy(&self) {
+        xcb::destroy_window(&self.conn, self.window_id);
+        self.flush();
     }
 
     fn set_ewmh_properties(&self) {
@@ -177,7 +615,24 @@ impl Bar {
             &[self.conn.WM_WINDOW_TYPE_DOCK()],
         );
 
-        // TODO: Update _WM_STRUT_PARTIAL if the height/position of the bar changes?
+        // The window isn't mapped until the bar starts running, so we can set
+        // the state directly rather than asking the window manager to.
+        let state = match self.stacking {
+            Stacking::Default => vec![],
+            Stacking::Above => vec![self.conn.WM_STATE_ABOVE()],
+            Stacking::Below => vec![self.conn.WM_STATE_BELOW()],
+        };
+        ewmh::set_wm_state(&self.conn, self.window_id, &state);
+
+        // Only reserve space for the part of the screen edge that the bar
+        // covers, so that windows can still use the margins either side.
+        // Struts are measured from the edges of the whole screen, so a bar
+        // on a monitor away from the edge reserves the space up to it, too.
+        let (x, y) = self.origin();
+        let (x, y) = (x.max(0) as u32, y.max(0) as u32);
+        let (width, height) = (u32::from(self.width), u32::from(self.height));
+        let (start_x, end_x) = (x, (x + width).saturating_sub(1));
+        let (start_y, end_y) = (y, (y + height).saturating_sub(1));
         let mut strut_partial = ewmh::StrutPartial {
             left: 0,
             right: 0,
@@ -192,144 +647,778 @@ impl Bar {
             bottom_start_x: 0,
             bottom_end_x: 0,
         };
+        // A hidden bar doesn't reserve any space.
         match self.position {
-            Position::Top => strut_partial.top = u32::from(self.height),
-            Position::Bottom => strut_partial.bottom = u32::from(self.height),
+            _ if self.hidden => (),
+            Position::Top => {
+                strut_partial.top = y + height;
+                strut_partial.top_start_x = start_x;
+                strut_partial.top_end_x = end_x;
+            }
+            Position::Bottom => {
+                strut_partial.bottom = u32::from(self.screen_height).saturating_sub(y);
+                strut_partial.bottom_start_x = start_x;
+                strut_partial.bottom_end_x = end_x;
+            }
+            Position::Left => {
+                strut_partial.left = x + width;
+                strut_partial.left_start_y = start_y;
+                strut_partial.left_end_y = end_y;
+            }
+            Position::Right => {
+                strut_partial.right = u32::from(self.screen_width).saturating_sub(x);
+                strut_partial.right_start_y = start_y;
+                strut_partial.right_end_y = end_y;
+            }
         }
         ewmh::set_wm_strut_partial(&self.conn, self.window_id, strut_partial);
     }
 
-    fn screen(&self) -> Result<xcb::Screen<'_>> {
+    // Sets the bar's height, or its width if it's vertical, to fit its
+    // tallest (or widest) text.
+    fn update_bar_thickness(&mut self, thickness: u16) -> Result<()> {
+        let current = if self.position.is_vertical() {
+            &mut self.width
+        } else {
+            &mut self.height
+        };
+        if *current != thickness {
+            *current = thickness;
+            self.update_geometry()?;
+        }
+
+        Ok(())
+    }
+
+    // Called when RandR tells us the screen has changed size, e.g. after a
+    // monitor is plugged in or unplugged. The bar follows its output; if the
+    // output has gone, it stays where it was.
+    fn update_screen_size(&mut self, width: u16, height: u16) -> Result<()> {
+        let area = self
+            .conn
+            .get_setup()
+            .roots()
+            .nth(self.screen_idx)
+            .and_then(|screen| output_area(&self.conn, screen.root(), self.output, width, height))
+            .unwrap_or(self.area);
+        if self.screen_width != width || self.screen_height != height || self.area != area {
+            self.screen_width = width;
+            self.screen_height = height;
+            self.area = area;
+            self.update_length();
+            self.update_geometry()?;
+            // Stretch texts need to be laid out for the new length.
+            self.redraw_entire_bar()?;
+        }
+
+        Ok(())
+    }
+
+    // Sets the gaps between the bar and the screen edges. This must be called
+    // before the bar starts running.
+    pub fn set_margins(&mut self, margins: Margins) {
+        self.margins = margins;
+        self.update_length();
+    }
+
+    // Rounds the corners of the bar. This must be called before the bar
+    // starts running.
+    pub fn set_corner_radius(&mut self, radius: u16) {
+        self.corner_radius = radius;
+    }
+
+    // Hides the bar while a fullscreen window is shown. This must be called
+    // before the bar starts running.
+    pub fn set_hide_on_fullscreen(&mut self, which: HideOnFullscreen) {
+        self.hide_on_fullscreen = Some(which);
+
+        // Watch the root window for the active window or client list
+        // changing. The windows themselves are watched in update_hidden().
+        if let Some(screen) = self.conn.get_setup().roots().nth(self.screen_idx) {
+            let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)];
+            xcb::change_window_attributes(&self.conn, screen.root(), &attributes);
+        }
+        if let Err(err) = self.update_hidden() {
+            println!("Error checking for fullscreen windows: {:#}", err);
+        }
+    }
+
+    // Hides or shows the bar, depending on whether there's a fullscreen
+    // window that should hide it.
+    fn update_hidden(&mut self) -> Result<()> {
+        let which = match self.hide_on_fullscreen {
+            Some(which) => which,
+            None => return Ok(()),
+        };
+        let screen_idx = self.screen_idx as i32;
+        let windows: Vec<xcb::Window> = match which {
+            HideOnFullscreen::Focused => ewmh::get_active_window(&self.conn, screen_idx)
+                .get_reply()
+                .ok()
+                .filter(|&window| window != xcb::NONE)
+                .into_iter()
+                .collect(),
+            HideOnFullscreen::Any => {
+                let current = ewmh::get_current_desktop(&self.conn, screen_idx)
+                    .get_reply()
+                    .ok();
+                ewmh::get_client_list(&self.conn, screen_idx)
+                    .get_reply()?
+                    .windows()
+                    .iter()
+                    .copied()
+                    .filter(
+                        |&window| match ewmh::get_wm_desktop(&self.conn, window).get_reply() {
+                            Ok(ALL_DESKTOPS) | Err(_) => true,
+                            Ok(desktop) => Some(desktop) == current,
+                        },
+                    )
+                    .collect()
+            }
+        };
+
+        // Watch each window for its state changing. Setting the event mask
+        // only affects our own connection's events, but would replace the
+        // mask of our own window, so skip that.
+        let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)];
+        for &window in windows.iter().filter(|&&window| window != self.window_id) {
+            xcb::change_window_attributes(&self.conn, window, &attributes);
+        }
+
+        // With a bar on each output, only the bars that a fullscreen window
+        // covers are hidden.
+        self.hidden_for_fullscreen = windows.iter().any(|&window| {
+            WindowState::of(&self.conn, window).fullscreen && self.is_on_output(window)
+        });
+        self.set_hidden();
+        Ok(())
+    }
+
+    // Whether any part of `window` is on the bar's output. If the window's
+    // geometry can't be found, it's assumed to be.
+    fn is_on_output(&self, window: xcb::Window) -> bool {
+        let root = match self.conn.get_setup().roots().nth(self.screen_idx) {
+            Some(screen) => screen.root(),
+            None => return true,
+        };
+        // The window's position is relative to its parent, which is usually
+        // a frame added by the WM, so is translated to the root window's.
+        let geometry = xcb::get_geometry(&self.conn, window);
+        let origin = xcb::translate_coordinates(&self.conn, window, root, 0, 0);
+        match (geometry.get_reply(), origin.get_reply()) {
+            (Ok(geometry), Ok(origin)) => self.area.overlaps(&Monitor {
+                x: origin.dst_x(),
+                y: origin.dst_y(),
+                width: geometry.width(),
+                height: geometry.height(),
+            }),
+            _ => true,
+        }
+    }
+
+    // Hides the bar, or shows it again, in response to a hotkey. A bar shown
+    // again is still hidden while there's a fullscreen window.
+    pub fn toggle_hidden(&mut self) {
+        self.hidden_by_user = !self.hidden_by_user;
+        self.set_hidden();
+    }
+
+    fn set_hidden(&mut self) {
+        let hidden = self.hidden_by_user || self.hidden_for_fullscreen;
+        if self.hidden == hidden {
+            return;
+        }
+        self.hidden = hidden;
+        if hidden {
+            xcb::unmap_window(&self.conn, self.window_id);
+        } else {
+            // The window is redrawn from the buffer when it's exposed.
+            self.map_window();
+        }
+        // Release or reclaim the space reserved for the bar.
+        self.set_ewmh_properties();
+        self.flush();
+    }
+
+    // Grabs `hotkey`, so that pressing it is reported by `process_event()`
+    // with the given index.
+    pub fn grab_hotkey(&mut self, idx: usize, hotkey: Hotkey) -> Result<()> {
         let screen = self
             .conn
             .get_setup()
             .roots()
             .nth(self.screen_idx)
             .ok_or_else(|| anyhow!("Invalid screen"))?;
-        Ok(screen)
-    }
-
-    fn update_bar_height(&mut self, height: u16) -> Result<()> {
-        if self.height != height {
-            self.height = height;
-
-            // If we're at the bottom of the screen, we'll need to update the
-            // position of the window.
-            let y = match self.position {
-                Position::Top => 0,
-                Position::Bottom => self.screen()?.height_in_pixels() - self.height,
-            };
-
-            // Update the height/position of the XCB window and the height of the Cairo surface.
-            let values = [
-                (xcb::CONFIG_WINDOW_Y as u16, u32::from(y)),
-                (xcb::CONFIG_WINDOW_HEIGHT as u16, u32::from(self.height)),
-                (xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE),
-            ];
-            xcb::configure_window(&self.conn, self.window_id, &values);
-            self.map_window();
-            self.surface
-                .set_size(i32::from(self.width), i32::from(self.height))
-                .unwrap();
+        let keycodes = grab_hotkey(&self.conn, screen.root(), hotkey.modifiers, hotkey.keysym)?;
+        self.hotkeys.extend(
+            keycodes
+                .into_iter()
+                .map(|keycode| (keycode, hotkey.modifiers, idx)),
+        );
+        Ok(())
+    }
+
+    // Sets the background shown behind texts without their own. This must be
+    // called before the bar starts running.
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    // Sets where the bar is stacked relative to other windows. This must be
+    // called before the bar starts running.
+    pub fn set_stacking(&mut self, stacking: Stacking) {
+        self.stacking = stacking;
+    }
+
+    // Clips the window to a rounded rectangle using the X SHAPE extension.
+    //
+    // The clip is made of one rectangle per row of pixels in the corners, plus
+    // one for the straight section in between, so it isn't anti-aliased.
+    fn update_shape(&self) {
+        let radius = self.corner_radius.min(self.height / 2).min(self.width / 2);
+        if radius == 0 {
+            return;
+        }
+
+        let (width, height) = (self.width, self.height);
+        let r = f64::from(radius);
+        let mut rectangles = Vec::new();
+        for row in 0..radius {
+            // How far in from the side the rounded corner is on this row.
+            let dy = r - f64::from(row) - 0.5;
+            let inset = (r - (r * r - dy * dy).sqrt()).round() as u16;
+            let row_width = width - 2 * inset;
+            let top = row as i16;
+            let bottom = (height - 1 - row) as i16;
+            rectangles.push(xcb::Rectangle::new(inset as i16, top, row_width, 1));
+            rectangles.push(xcb::Rectangle::new(inset as i16, bottom, row_width, 1));
+        }
+        rectangles.push(xcb::Rectangle::new(
+            0,
+            radius as i16,
+            width,
+            height - 2 * radius,
+        ));
+
+        xcb::shape::rectangles(
+            &self.conn,
+            xcb::shape::SO_SET as u8,
+            xcb::shape::SK_BOUNDING as u8,
+            xcb::CLIP_ORDERING_UNSORTED as u8,
+            self.window_id,
+            0,
+            0,
+            &rectangles,
+        );
+    }
 
-            // Update EWMH properties - we might need to reserve more or less space.
-            self.set_ewmh_properties();
+    // Sets the bar's length along the edge of the screen: its width, or its
+    // height if it's vertical.
+    fn update_length(&mut self) {
+        if self.position.is_vertical() {
+            self.height = self
+                .area
+                .height
+                .saturating_sub(self.margins.top)
+                .saturating_sub(self.margins.bottom)
+                .max(1);
+        } else {
+            self.width = self
+                .area
+                .width
+                .saturating_sub(self.margins.left)
+                .saturating_sub(self.margins.right)
+                .max(1);
         }
+    }
+
+    // The position of the window within the screen: at the bar's edge of
+    // its area, inset by the margins.
+    fn origin(&self) -> (i32, i32) {
+        let left = i32::from(self.area.x) + i32::from(self.margins.left);
+        let top = i32::from(self.area.y) + i32::from(self.margins.top);
+        // If we're at the bottom or the right of the screen, the position
+        // depends on the size of the window.
+        match self.position {
+            Position::Top | Position::Left => (left, top),
+            Position::Bottom => (
+                left,
+                i32::from(self.area.y) + i32::from(self.area.height)
+                    - i32::from(self.height)
+                    - i32::from(self.margins.bottom),
+            ),
+            Position::Right => (
+                i32::from(self.area.x) + i32::from(self.area.width)
+                    - i32::from(self.width)
+                    - i32::from(self.margins.right),
+                top,
+            ),
+        }
+    }
+
+    // Moves and resizes the window (and its surfaces) to match the bar's
+    // current width and height.
+    fn update_geometry(&mut self) -> Result<()> {
+        let (x, y) = self.origin();
+
+        let stack_mode = match self.stacking {
+            Stacking::Default | Stacking::Above => xcb::STACK_MODE_ABOVE,
+            Stacking::Below => xcb::STACK_MODE_BELOW,
+        };
+
+        // Update the geometry of the XCB window and the size of the Cairo surface.
+        let values = [
+            // Negative coordinates are sent as two's complement.
+            (xcb::CONFIG_WINDOW_X as u16, x as u32),
+            (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+            (xcb::CONFIG_WINDOW_WIDTH as u16, u32::from(self.width)),
+            (xcb::CONFIG_WINDOW_HEIGHT as u16, u32::from(self.height)),
+            (xcb::CONFIG_WINDOW_STACK_MODE as u16, stack_mode),
+        ];
+        xcb::configure_window(&self.conn, self.window_id, &values);
+        self.map_window();
+        self.surface
+            .set_size(i32::from(self.width), i32::from(self.height))
+            .unwrap();
+        self.buffer = create_buffer(self.width, self.height)?;
+        self.update_shape();
+
+        // Update EWMH properties - we might need to reserve more or less space.
+        self.set_ewmh_properties();
 
         Ok(())
     }
 
+    // Whether an event should be passed to this bar's `process_event()`, when
+    // several bars share a connection.
+    pub fn is_for(&self, event: &xcb::GenericEvent) -> bool {
+        event_window(event).map_or(true, |window| window == self.window_id)
+    }
+
     // Process an X event received from the `Bar::connection()`.
-    pub fn process_event(&mut self, event: xcb::GenericEvent) -> Result<()> {
-        let expose = event.response_type() & !0x80 == xcb::EXPOSE;
-        if expose {
-            println!("Redrawing entire bar - expose event.");
-            self.redraw_entire_bar()?;
+    //
+    // If the event was a click on a widget, returns the index of the widget
+    // along with the click, so that it can be passed to the widget's handler.
+    // If it was a hotkey, returns the index of the hotkey.
+    pub fn process_event(&mut self, event: &xcb::GenericEvent) -> Result<Option<BarEvent>> {
+        match event.response_type() & !0x80 {
+            xcb::EXPOSE => {
+                // The buffer still holds the last frame, so there's no need
+                // to redraw the widgets.
+                self.present();
+                self.flush();
+                Ok(None)
+            }
+            xcb::BUTTON_PRESS => {
+                let event: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(event) };
+                Ok(self
+                    .click_at(event.event_x(), event.event_y(), event.detail())
+                    .map(|(idx, click)| BarEvent::Click(idx, click)))
+            }
+            xcb::KEY_PRESS => {
+                let event: &xcb::KeyPressEvent = unsafe { xcb::cast_event(event) };
+                let modifiers = hotkey_modifiers(event.state());
+                Ok(self
+                    .hotkeys
+                    .iter()
+                    .find(|&&(keycode, mods, _)| keycode == event.detail() && mods == modifiers)
+                    .map(|&(_, _, idx)| BarEvent::Hotkey(idx)))
+            }
+            xcb::MOTION_NOTIFY => {
+                let event: &xcb::MotionNotifyEvent = unsafe { xcb::cast_event(event) };
+                self.update_cursor(Some((event.event_x(), event.event_y())));
+                Ok(None)
+            }
+            xcb::ENTER_NOTIFY => {
+                let event: &xcb::EnterNotifyEvent = unsafe { xcb::cast_event(event) };
+                self.update_cursor(Some((event.event_x(), event.event_y())));
+                Ok(None)
+            }
+            xcb::LEAVE_NOTIFY => {
+                self.update_cursor(None);
+                Ok(None)
+            }
+            xcb::PROPERTY_NOTIFY if self.hide_on_fullscreen.is_some() => {
+                let event: &xcb::PropertyNotifyEvent = unsafe { xcb::cast_event(event) };
+                let atom = event.atom();
+                if atom == self.conn.ACTIVE_WINDOW()
+                    || atom == self.conn.CLIENT_LIST()
+                    || atom == self.conn.CURRENT_DESKTOP()
+                    || atom == self.conn.WM_STATE()
+                {
+                    self.update_hidden()?;
+                }
+                Ok(None)
+            }
+            response_type if Some(response_type) == self.screen_change_event => {
+                let (width, height) = screen_change_size(event);
+                println!("Screen resized to {}x{}", width, height);
+                self.update_screen_size(width, height)?;
+                Ok(None)
+            }
+            _ => Ok(None),
         }
-        Ok(())
+    }
+
+    // Finds the widget (and the segment within it) under the coordinates of
+    // a click.
+    fn click_at(&self, x: i16, y: i16, detail: u8) -> Option<(usize, ClickEvent)> {
+        let button = MouseButton::from_detail(detail)?;
+        self.segment_at(x, y)
+            .map(|(idx, segment)| (idx, ClickEvent { button, segment }))
+    }
+
+    // Finds the widget and the segment within it at the coordinates, using
+    // the extents computed when the texts were laid out. Texts span the
+    // bar's thickness, so only the coordinate along the bar matters.
+    fn segment_at(&self, x: i16, y: i16) -> Option<(usize, usize)> {
+        let vertical = self.position.is_vertical();
+        self.contents.iter().enumerate().find_map(|(idx, texts)| {
+            texts
+                .iter()
+                .position(|text| {
+                    let (pos, start, length) = if vertical {
+                        (f64::from(y), text.y, text.height)
+                    } else {
+                        (f64::from(x), text.x, text.width)
+                    };
+                    pos >= start && pos < start + length
+                })
+                .map(|segment| (idx, segment))
+        })
     }
 
     // Add a new widget's content to the `Bar`.
     //
     // Returns the index of the widget within the bar, so that subsequent
     // updates can be made by calling `Bar::update_content()`.
-    pub fn add_content(&mut self, content: Vec<Text>) -> Result<usize> {
+    pub fn add_content(&mut self, zone: Zone, content: Vec<Text>) -> Result<usize> {
         let idx = self.contents.len();
         self.contents.push(Vec::new());
+        self.zones.push(zone);
+        self.segment_gaps.push(0.0);
+        self.names.push(None);
+        self.clickable.push(false);
         self.update_content(idx, content)?;
         Ok(idx)
     }
 
-    // Updates an existing widget's content in the `Bar`.
+    // Marks the widget at `idx` as handling clicks, so that the pointer
+    // becomes a hand over it.
+    pub fn set_clickable(&mut self, idx: usize, clickable: bool) {
+        self.clickable[idx] = clickable;
+    }
+
+    // Shows the hand cursor if the pointer is at `pos` over a clickable
+    // widget, or the default cursor otherwise (including when `pos` is
+    // `None`, as the pointer has left the bar).
+    fn update_cursor(&mut self, pos: Option<(i16, i16)>) {
+        let over_clickable = pos
+            .and_then(|(x, y)| self.segment_at(x, y))
+            .map_or(false, |(idx, _)| self.clickable[idx]);
+        if over_clickable == self.hand_shown {
+            return;
+        }
+        self.hand_shown = over_clickable;
+        let cursor = if over_clickable {
+            self.hand_cursor
+        } else {
+            xcb::NONE
+        };
+        xcb::change_window_attributes(&self.conn, self.window_id, &[(xcb::CW_CURSOR, cursor)]);
+        self.flush();
+    }
+
+    // Sets the gap between the texts of the widget at `idx`.
+    pub fn set_segment_gap(&mut self, idx: usize, gap: f64) {
+        self.segment_gaps[idx] = gap;
+    }
+
+    // The scaled gap after each of the widget's texts but the last.
+    fn segment_gap(&self, idx: usize) -> f64 {
+        self.segment_gaps[idx] * self.scale
+    }
+
+    // Sets the text that is shown in place of a widget that has errored.
+    pub fn set_error_text(&mut self, text: Text) {
+        self.error_text = Some(text);
+    }
+
+    // Replaces a widget's content with the error indicator. The indicator
+    // stays until the widget next updates successfully.
+    //
+    // Without a configured indicator, the widget's current attributes are
+    // reused with a red foreground. If the widget has never shown anything
+    // (e.g. its backend is missing at startup), the default attributes are
+    // used instead.
+    pub fn show_error(&mut self, idx: usize) {
+        let text = match (&self.error_text, self.contents[idx].first()) {
+            (Some(text), _) => text.clone(),
+            (None, Some(current)) => Text::new(
+                Attributes {
+                    fg_color: Color::red(),
+                    ..current.attr.clone()
+                },
+                DEFAULT_ERROR_GLYPH.to_owned(),
+            )
+            .stretch(current.stretch),
+            (None, None) => Text::new(
+                Attributes {
+                    fg_color: Color::red(),
+                    ..self.default_attr.clone()
+                },
+                DEFAULT_ERROR_GLYPH.to_owned(),
+            ),
+        };
+        self.queue_content(idx, vec![text]);
+    }
+
+    // Sets the attributes that texts use for those they leave as defaults.
+    // This only affects content added after it is called, so it must be
+    // called before the bar starts running.
+    pub fn set_default_attributes(&mut self, attr: Attributes) {
+        self.default_attr = attr.inherit(&Attributes::initial());
+    }
+
+    // Sets the factor that font sizes and padding are multiplied by. This
+    // only affects content added after it is called, so it must be called
+    // before the bar starts running.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    // Reads the DPI that the user has configured through the `Xft.dpi` X
+    // resource, as used by most toolkits.
+    pub fn xft_dpi(&self) -> Option<f64> {
+        let screen = self.conn.get_setup().roots().nth(self.screen_idx)?;
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            screen.root(),
+            xcb::ATOM_RESOURCE_MANAGER,
+            xcb::ATOM_STRING,
+            0,
+            u32::MAX,
+        )
+        .get_reply()
+        .ok()?;
+        let resources = String::from_utf8_lossy(reply.value::<u8>()).into_owned();
+        resources.lines().find_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some("Xft.dpi"), Some(value)) => value.trim().parse().ok(),
+                _ => None,
+            }
+        })
+    }
+
+    // The number of widgets that have been added to the `Bar`.
+    pub fn widget_count(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn set_name(&mut self, idx: usize, name: Option<String>) {
+        self.names[idx] = name;
+    }
+
+    // Describes the widget at `idx` for log messages: its name if it has
+    // one, otherwise its index.
+    pub fn widget_label(&self, idx: usize) -> String {
+        match &self.names[idx] {
+            Some(name) => format!("'{}'", name),
+            None => idx.to_string(),
+        }
+    }
+
+    // Updates an existing widget's content in the `Bar` immediately.
     pub fn update_content(&mut self, idx: usize, content: Vec<Text>) -> Result<()> {
+        self.queue_content(idx, content);
+        self.flush_updates()
+    }
+
+    // Queues an update to a widget's content, to be applied by the next call
+    // to `flush_updates()`. If the widget updates again in the meantime, only
+    // its latest content is used.
+    //
+    // The content's default attributes are filled in here, so that they're
+    // compared with the widget's current (filled in) content.
+    pub fn queue_content(&mut self, idx: usize, content: Vec<Text>) {
+        let content = content
+            .into_iter()
+            .map(|text| text.inherit(&self.default_attr))
+            .collect();
+        self.pending.insert(idx, content);
+    }
+
+    // Applies all queued updates, redrawing the bar only once.
+    //
+    // If any update fails, the rest are still applied and the first error is
+    // returned.
+    pub fn flush_updates(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        let mut redraw_entire_bar = false;
+        let mut changed = Vec::new();
+        for (idx, content) in mem::take(&mut self.pending) {
+            match self.set_content(idx, content) {
+                Ok(Redraw::Nothing) => (),
+                Ok(Redraw::Content) => changed.push(idx),
+                Ok(Redraw::EntireBar) => redraw_entire_bar = true,
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(err.context(format!("Failed to update widget {}", idx)));
+                    }
+                }
+            }
+        }
+
+        if redraw_entire_bar {
+            println!("Redrawing entire bar - widget update");
+            self.redraw_entire_bar()?;
+        } else if !changed.is_empty() {
+            for idx in changed {
+                self.redraw_content(idx)?;
+            }
+            self.present();
+            self.flush();
+        }
+
+        result
+    }
+
+    // Replaces a widget's content, without redrawing. Returns what needs to
+    // be redrawn.
+    fn set_content(&mut self, idx: usize, content: Vec<Text>) -> Result<Redraw> {
         // If the text is the same, don't bother re-computing the text or
-        // redrawing it. This is a spurious wake-up.
+        // redrawing it. This is a spurious wake-up, or a widget that ticks
+        // more often than its text changes (e.g. CPU usage).
         let old = &self.contents[idx];
         if &content == old {
-            return Ok(());
+            return Ok(Redraw::Nothing);
         }
 
+        // Reuse the widget's existing Pango layouts where we can.
+        let mut layouts = old.iter().map(|text| text.layout.clone());
         let mut new = content
             .into_iter()
-            .map(|text| text.compute(&self.surface))
+            .map(|text| text.compute(&self.surface, layouts.next(), self.scale))
             .collect::<Result<Vec<_>>>()?;
 
         let error_margin = f64::EPSILON; // Use an epsilon for comparison
+        let vertical = self.position.is_vertical();
 
         // If there are any new texts or any non-stretch texts changed size,
-        // we'll redraw all texts.
+        // we'll redraw all texts. Otherwise, the other widgets stay where
+        // they are and only this widget needs redrawing. On a vertical bar,
+        // a text that's grown wider than the bar needs it widening, too.
         let redraw_entire_bar = old.len() != new.len()
-            || old
-                .iter()
-                .zip(&new)
-                .any(|(old, new)| ((old.width - new.width).abs() < error_margin) && !new.stretch);
+            || old.iter().zip(&new).any(|(old, new)| {
+                let resized = if vertical {
+                    (old.height - new.height).abs() >= error_margin || new.width > old.width
+                } else {
+                    (old.width - new.width).abs() >= error_margin
+                };
+                old.stretch != new.stretch || (!new.stretch && resized)
+            });
 
         // Steal dimenions from old ComputedText. If we need new dimensions,
         // they'll be recomputed by redraw_entire_bar().
         for (new, old) in new.iter_mut().zip(old.iter()) {
             new.x = old.x;
             new.y = old.y;
-            new.height = old.height;
-            // Only use width for stretch widgets.
-            if new.stretch {
-                new.width = old.width;
+            if vertical {
+                new.width = new.width.max(old.width);
+                // Only use height for stretch widgets.
+                if new.stretch {
+                    new.height = old.height;
+                }
+            } else {
+                new.height = old.height;
+                // Only use width for stretch widgets.
+                if new.stretch {
+                    new.width = old.width;
+                }
             }
         }
 
         self.contents[idx] = new;
 
-        if !redraw_entire_bar {
-            println!("Redrawing one");
-            self.redraw_content(idx)?;
+        if redraw_entire_bar {
+            Ok(Redraw::EntireBar)
         } else {
-            println!("Redrawing entire bar - widget update");
-            self.redraw_entire_bar()?;
+            Ok(Redraw::Content)
         }
-
-        Ok(())
     }
 
+    // Draws a widget's content into the buffer. The caller is responsible for
+    // calling `present()` and flushing.
     fn redraw_content(&mut self, idx: usize) -> Result<()> {
         for text in &mut self.contents[idx] {
-            text.render(&self.surface)?;
+            text.render(&self.buffer, self.blink_visible, &self.background)?;
         }
 
-        self.flush();
+        Ok(())
+    }
 
+    // Whether any widget has blinking text, and so needs `toggle_blink()`
+    // calling periodically.
+    pub fn is_blinking(&self) -> bool {
+        self.contents.iter().flatten().any(|text| text.blink)
+    }
+
+    // Shows or hides all blinking texts. Using one clock for the whole bar
+    // keeps blinking widgets in sync with each other.
+    pub fn toggle_blink(&mut self) -> Result<()> {
+        self.blink_visible = !self.blink_visible;
+        let blinking: Vec<usize> = (0..self.contents.len())
+            .filter(|idx| self.contents[*idx].iter().any(|text| text.blink))
+            .collect();
+        for idx in blinking {
+            self.redraw_content(idx)?;
+        }
+        self.present();
+        self.flush();
         Ok(())
     }
 
+    // Copies the buffer to the window.
+    fn present(&self) {
+        let context = cairo::Context::new(&self.surface);
+        context.set_source_surface(&self.buffer, 0.0, 0.0);
+        context.set_operator(cairo::Operator::Source);
+        context.paint();
+        self.surface.flush();
+    }
+
     pub fn redraw_entire_bar(&mut self) -> Result<()> {
         self.recompute_dimensions()?;
 
+        // Clear the gaps between zones, which no text is drawn over.
+        // Translucent backgrounds replace the last frame rather than being
+        // drawn over it.
+        let context = cairo::Context::new(&self.buffer);
+        self.background
+            .apply_to_context(&context, f64::from(self.width), f64::from(self.height));
+        context.set_operator(cairo::Operator::Source);
+        context.paint();
+
         for idx in 0..self.contents.len() {
             self.redraw_content(idx)?;
         }
+        self.present();
+        self.flush();
         Ok(())
     }
 
     fn recompute_dimensions(&mut self) -> Result<()> {
-        // Set the height to the max height of any content.
+        // A vertical bar is laid out as if it were horizontal, by swapping
+        // each text's axes before and after.
+        let vertical = self.position.is_vertical();
+        if vertical {
+            transpose(&mut self.contents);
+        }
+
+        // Set the height (the width, for a vertical bar) to the max height
+        // of any content.
         let height = self
             .contents
             .iter()
@@ -340,43 +1429,236 @@ impl Bar {
         for text in self.contents.iter_mut().flatten() {
             text.height = height;
         }
-        self.update_bar_height(height as u16)?;
 
-        // Sum the width of all non-stretch texts. Subtract from the screen
-        // width to get width remaining for stretch texts.
-        let used: f64 = self
-            .contents
-            .iter()
-            .flatten()
-            .filter(|text| !text.stretch)
-            .map(|text| text.width)
-            .sum();
-        let remaining = f64::from(self.width) - used;
+        let bar_length = f64::from(if vertical { self.height } else { self.width });
+        let gaps: Vec<f64> = (0..self.contents.len())
+            .map(|idx| self.segment_gap(idx))
+            .collect();
+        lay_out(&mut self.contents, &self.zones, &gaps, bar_length);
 
-        // Distribute remaining width evenly between stretch texts.
-        let stretches_count = self
-            .contents
-            .iter()
-            .flatten()
-            .filter(|text| text.stretch)
-            .count();
-        let stretch_width = remaining / (stretches_count as f64);
-        let stretches = self
-            .contents
-            .iter_mut()
-            .flatten()
-            .filter(|text| text.stretch);
-        for text in stretches {
-            text.width = stretch_width;
+        if vertical {
+            transpose(&mut self.contents);
         }
+        self.update_bar_thickness(height as u16)?;
 
-        // Set x based on computed widths.
-        let mut x = 0.0;
-        for text in self.contents.iter_mut().flatten() {
-            text.x = x;
-            x += text.width;
+        Ok(())
+    }
+}
+
+// Swaps the x and y axes of every text, so that a vertical bar can be laid
+// out as a horizontal one.
+fn transpose(contents: &mut [Vec<ComputedText>]) {
+    for text in contents.iter_mut().flatten() {
+        std::mem::swap(&mut text.x, &mut text.y);
+        std::mem::swap(&mut text.width, &mut text.height);
+    }
+}
+
+// Positions each widget's texts within its zone of a bar `bar_width` wide,
+// leaving `gaps[idx]` between the texts of the widget at `idx`.
+fn lay_out(contents: &mut [Vec<ComputedText>], zones: &[Zone], gaps: &[f64], bar_width: f64) {
+    // Each zone's texts are laid out together. Stretch texts fill the
+    // space that is left over on their side(s) of the bar.
+    let mut fixed = [0.0; 3];
+    let mut stretches = [0usize; 3];
+    for ((texts, zone), gap) in contents.iter().zip(zones).zip(gaps) {
+        let i = *zone as usize;
+        fixed[i] += gap * texts.len().saturating_sub(1) as f64;
+        for text in texts {
+            if text.stretch {
+                stretches[i] += 1;
+            } else {
+                fixed[i] += text.width;
+            }
         }
+    }
+    let [left, center, right] = fixed;
+    let has_center = contents
+        .iter()
+        .zip(zones)
+        .any(|(texts, zone)| *zone == Zone::Center && !texts.is_empty());
 
-        Ok(())
+    // Center stretch texts widen the center zone as far as they can
+    // while keeping it centered.
+    let mut stretch_widths = [0.0; 3];
+    if stretches[1] > 0 {
+        let room = bar_width - center - 2.0 * left.max(right);
+        stretch_widths[1] = room.max(0.0) / stretches[1] as f64;
+    }
+    let center = center + stretch_widths[1] * stretches[1] as f64;
+    let center_x = ((bar_width - center) / 2.0)
+        .min(bar_width - right - center)
+        .max(left);
+
+    // Left and right stretch texts share the gaps either side of the
+    // center zone, or the single gap if there isn't one.
+    if has_center {
+        let gap_left = center_x - left;
+        let gap_right = bar_width - right - (center_x + center);
+        if stretches[0] > 0 {
+            stretch_widths[0] = gap_left.max(0.0) / stretches[0] as f64;
+        }
+        if stretches[2] > 0 {
+            stretch_widths[2] = gap_right.max(0.0) / stretches[2] as f64;
+        }
+    } else if stretches[0] + stretches[2] > 0 {
+        let gap = bar_width - left - right;
+        let width = gap.max(0.0) / (stretches[0] + stretches[2]) as f64;
+        stretch_widths[0] = width;
+        stretch_widths[2] = width;
+    }
+    let right = right + stretch_widths[2] * stretches[2] as f64;
+
+    // Set x based on computed widths.
+    let starts = [0.0, center_x, bar_width - right];
+    for zone in &[Zone::Left, Zone::Center, Zone::Right] {
+        let i = *zone as usize;
+        let mut x = starts[i];
+        let widgets = contents
+            .iter_mut()
+            .zip(zones)
+            .zip(gaps)
+            .filter(|((_, z), _)| *z == zone);
+        for ((texts, _), gap) in widgets {
+            for (n, text) in texts.iter_mut().enumerate() {
+                if n > 0 {
+                    x += gap;
+                }
+                if text.stretch {
+                    text.width = stretch_widths[i];
+                }
+                text.x = x;
+                x += text.width;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_buffer, lay_out, render_to_image, Zone};
+    use crate::text::{Attributes, ComputedText, Text};
+
+    // A text `width` pixels wide, or a stretch text if `width` is `None`.
+    fn text(width: Option<f64>) -> ComputedText {
+        let surface = create_buffer(1, 1).unwrap();
+        let mut text = Text::new(Attributes::default(), String::new())
+            .stretch(width.is_none())
+            .compute(&surface, None, 1.0)
+            .unwrap();
+        text.width = width.unwrap_or(0.0);
+        text
+    }
+
+    // The x and width of each widget's texts.
+    fn positions(contents: &[Vec<ComputedText>]) -> Vec<Vec<(f64, f64)>> {
+        contents
+            .iter()
+            .map(|texts| texts.iter().map(|text| (text.x, text.width)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn centers_the_center_zone() {
+        let mut contents = vec![
+            vec![text(Some(20.0))],
+            vec![text(Some(10.0)), text(Some(10.0))],
+            vec![text(Some(30.0))],
+        ];
+        let zones = [Zone::Left, Zone::Center, Zone::Right];
+        lay_out(&mut contents, &zones, &[0.0, 4.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![
+                vec![(0.0, 20.0)],
+                vec![(38.0, 10.0), (52.0, 10.0)],
+                vec![(70.0, 30.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn pushes_the_center_zone_out_of_the_way() {
+        let zones = [Zone::Left, Zone::Center];
+        let mut contents = vec![vec![text(Some(60.0))], vec![text(Some(20.0))]];
+        lay_out(&mut contents, &zones, &[0.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![vec![(0.0, 60.0)], vec![(60.0, 20.0)]]
+        );
+
+        let zones = [Zone::Center, Zone::Right];
+        let mut contents = vec![vec![text(Some(20.0))], vec![text(Some(70.0))]];
+        lay_out(&mut contents, &zones, &[0.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![vec![(10.0, 20.0)], vec![(30.0, 70.0)]]
+        );
+    }
+
+    #[test]
+    fn splits_the_leftover_width_between_stretch_texts() {
+        let mut contents = vec![
+            vec![text(Some(20.0)), text(None)],
+            vec![text(None), text(Some(10.0))],
+        ];
+        let zones = [Zone::Left, Zone::Right];
+        lay_out(&mut contents, &zones, &[0.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![
+                vec![(0.0, 20.0), (20.0, 35.0)],
+                vec![(55.0, 35.0), (90.0, 10.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn stretches_the_center_zone_while_keeping_it_centered() {
+        let mut contents = vec![
+            vec![text(Some(10.0))],
+            vec![text(None), text(None)],
+            vec![text(Some(30.0))],
+        ];
+        let zones = [Zone::Left, Zone::Center, Zone::Right];
+        lay_out(&mut contents, &zones, &[0.0, 0.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![
+                vec![(0.0, 10.0)],
+                vec![(30.0, 20.0), (50.0, 20.0)],
+                vec![(70.0, 30.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn stretches_side_texts_to_the_center_zone() {
+        let mut contents = vec![
+            vec![text(None)],
+            vec![text(Some(20.0))],
+            vec![text(Some(10.0)), text(None)],
+        ];
+        let zones = [Zone::Left, Zone::Center, Zone::Right];
+        lay_out(&mut contents, &zones, &[0.0, 0.0, 0.0], 100.0);
+        assert_eq!(
+            positions(&contents),
+            vec![
+                vec![(0.0, 40.0)],
+                vec![(40.0, 20.0)],
+                vec![(60.0, 10.0), (70.0, 30.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_texts_to_an_image_of_the_given_size() {
+        let text = Text::new(Attributes::default(), "Hello".to_owned());
+        let mut image = render_to_image(vec![text], 200, 24).unwrap();
+        assert_eq!((image.get_width(), image.get_height()), (200, 24));
+
+        // Anything drawn makes the image differ from the bare background.
+        let mut blank = render_to_image(Vec::new(), 200, 24).unwrap();
+        assert_ne!(&*image.get_data().unwrap(), &*blank.get_data().unwrap());
     }
 }
@@ -24,7 +24,9 @@
 //! use cnx::{Cnx, Position};
 //! use anyhow::Result;
 //!
-//! fn main() -> Result<()> {
+//! # fn main() {}
+//! // Run with e.g. `#[tokio::main]`.
+//! async fn run() -> Result<()> {
 //!     let attr = Attributes {
 //!         font: Font::new("Envy Code R 21"),
 //!         fg_color: Color::white(),
@@ -32,10 +34,11 @@
 //!         padding: Padding::new(8.0, 8.0, 0.0, 0.0),
 //!     };
 //!
-//!     let mut cnx = Cnx::new(Position::Top);
-//!     cnx.add_widget(ActiveWindowTitle::new(attr.clone()));
-//!     cnx.add_widget(Clock::new(attr.clone(), None));
-//!     cnx.run()?;
+//!     let cnx = Cnx::builder(Position::Top)
+//!         .widget(ActiveWindowTitle::new(attr.clone()))
+//!         .widget(Clock::new(attr.clone(), None))
+//!         .build()?;
+//!     cnx.run().await?;
 //!
 //!     Ok(())
 //! }
@@ -60,6 +63,14 @@
 //!   currently active. (Uses [`EWMH`]'s `_NET_DESKTOP_NAMES`,
 //!   `_NET_NUMBER_OF_DESKTOPS` and `_NET_CURRENT_DESKTOP`).
 //! - [`crate::widgets::Clock`] — Shows the time.
+//! - [`crate::widgets::Crawl`] — Scrolls long text within a fixed width, like
+//!   a news ticker.
+//! - [`crate::widgets::ActiveWindowState`] — Shows whether the focused window is
+//!   fullscreen, maximized or floating (Uses [`EWMH`]'s `_NET_WM_STATE`).
+//! - [`crate::widgets::KeyboardLayout`] — Shows the current keyboard layout
+//!   (Uses XKB).
+//! - [`crate::widgets::LockKeys`] — Shows whether Caps Lock, Num Lock and
+//!   Scroll Lock are on (Uses XKB).
 //!
 //! The cnx-contrib crate contains additional widgets:
 //!
@@ -67,11 +78,30 @@
 //!   sensors provided by the system.
 //! - **Volume** - Shows the current volume/mute status of the default output
 //!   device.
-//! - **Battery** - Shows the remaining battery and charge status.
+//! - **Battery** - Shows the remaining battery and charge status. On Linux,
+//!   it can also be read from UPower over D-Bus (the `upower` feature).
 //! - **Wireless** - Shows the wireless strength of your current network.
 //! - **CPU** - Shows the current CPU consumption
-//! - **Weather** - Shows the Weather information of your location
+//! - **Weather** - Shows the Weather information of your location, from NOAA
+//!   or OpenWeatherMap
 //! - **Disk Usage** - Show the current usage of your monted filesystem
+//! - **Memory** - Shows the current RAM usage
+//! - **Swap** - Shows the current swap usage
+//! - **NetThroughput** - Shows the download and upload rates of a network
+//!   interface
+//! - **LoadAverage** - Shows the 1, 5 and 15 minute load averages
+//! - **Backlight** - Shows the brightness of your screen's backlight (Linux
+//!   only)
+//! - **Mpd** - Shows the song MPD is playing
+//! - **Mpris** - Shows the track of an MPRIS media player (the `mpris`
+//!   feature)
+//! - **Uptime** - Shows how long the system has been up
+//! - **Temperature** - Shows the temperature of a thermal zone
+//! - **Command** - Shows the output of a program, run periodically
+//! - **FileWatch** - Shows the contents of a file, updating when it changes
+//!   (Linux only)
+//! - **RSS** - Cycles through the latest items of an RSS or Atom feed (the
+//!   `rss` feature)
 //!
 //! The Sensors, Volume and Battery widgets require platform
 //! support. They currently support Linux (see dependencies below) and OpenBSD.
@@ -91,6 +121,8 @@
 //! Some widgets have additional dependencies on Linux:
 //!
 //!  - **Volume** widget relies on `alsa-lib`
+//!  - **PipeWireVolume** widget (the `volume-pipewire` feature) relies on
+//!    `libpipewire`.
 //!  - **Sensors** widget relies on [`lm_sensors`] being installed.
 //!  - **Wireless** widget relies on `libiw-dev`.
 //!
@@ -118,19 +150,172 @@
 
 mod bar;
 pub mod text;
+pub mod theme;
+pub mod time_of_day;
 pub mod widgets;
 mod xcb;
 
-use anyhow::Result;
+use std::future::{self, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
 use futures::Stream;
 use tokio_stream::{StreamExt, Empty};
-use widgets::{WidgetStreamI, WidgetStream};
+use text::Text;
+use widgets::{BoxedWidget, ClickHandler, WidgetStreamI, WidgetStream};
 use tokio::pin;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::time;
+use xcb_util::ewmh;
 
-use crate::bar::Bar;
+use crate::bar::{Bar, BarEvent};
 use crate::xcb::BarEventStream;
 
-pub use bar::Position;
+pub use bar::{render_to_image, HideOnFullscreen, Hotkey, Margins, Position, Stacking, Zone};
+
+/// How long [`Cnx::check()`] waits for every widget to produce an update.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long to wait for other widgets to update before redrawing the bar.
+const COALESCE_UPDATES: Duration = Duration::from_millis(10);
+
+// How long blinking texts are shown for, and then hidden for.
+const BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The widgets of a [`Cnx`] whose type doesn't change as widgets are added.
+/// See [`Cnx::add_boxed_widget()`].
+///
+/// [`Cnx`]: struct.Cnx.html
+/// [`Cnx::add_boxed_widget()`]: struct.Cnx.html#method.add_boxed_widget
+pub type BoxedWidgets = Pin<Box<dyn Stream<Item = (usize, WidgetStreamI)>>>;
+
+/// What happens when a [`Hotkey`] is pressed. See [`Cnx::hotkey()`].
+///
+/// [`Hotkey`]: struct.Hotkey.html
+/// [`Cnx::hotkey()`]: struct.Cnx.html#method.hotkey
+#[derive(Debug)]
+pub enum HotkeyAction {
+    /// Hides the bar, or shows it again. The space reserved for the bar is
+    /// released while it's hidden.
+    ToggleVisibility,
+    /// Runs a shell command.
+    Command(String),
+    /// Sends `()` on the channel, so that a widget can react, e.g. by
+    /// cycling through formats. A press is dropped if the channel is full.
+    Notify(mpsc::Sender<()>),
+}
+
+/// Configuration applied to a [`Cnx`] by [`CnxBuilder::configure()`].
+type Configure = Box<dyn FnOnce(Cnx<Empty<(usize, WidgetStreamI)>>) -> Cnx<Empty<(usize, WidgetStreamI)>>>;
+
+/// Collects the configuration and widgets for a [`Cnx`], and then creates it
+/// with a single [`build()`](#method.build).
+///
+/// Widgets are boxed as they're added, so the builder's type doesn't change
+/// and widgets can be added conditionally:
+///
+/// ```no_run
+/// # use cnx::text::Attributes;
+/// # use cnx::widgets::{ActiveWindowTitle, Clock};
+/// # use cnx::{Cnx, Margins, Position};
+/// # async fn run(show_title: bool) -> anyhow::Result<()> {
+/// let mut builder = Cnx::builder(Position::Top)
+///     .configure(|cnx| cnx.margins(Margins::new(8, 8, 8, 0)));
+/// if show_title {
+///     builder = builder.widget(ActiveWindowTitle::new(Attributes::default()));
+/// }
+/// builder.widget(Clock::new(Attributes::default(), None)).build()?.run().await
+/// # }
+/// ```
+pub struct CnxBuilder {
+    position: Position,
+    all_outputs: bool,
+    configure: Vec<Configure>,
+    widgets: Vec<(Zone, BoxedWidget)>,
+}
+
+impl CnxBuilder {
+    /// Shows a bar on every monitor, as [`Cnx::new_on_all_outputs()`] does,
+    /// rather than only on the primary one.
+    ///
+    /// [`Cnx::new_on_all_outputs()`]: struct.Cnx.html#method.new_on_all_outputs
+    pub fn on_all_outputs(mut self) -> Self {
+        self.all_outputs = true;
+        self
+    }
+
+    /// Configures the [`Cnx`] once it's built, before any widgets are added,
+    /// e.g. with [`Cnx::margins()`] or [`Cnx::hotkey()`].
+    ///
+    /// [`Cnx::margins()`]: struct.Cnx.html#method.margins
+    /// [`Cnx::hotkey()`]: struct.Cnx.html#method.hotkey
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(Cnx<Empty<(usize, WidgetStreamI)>>) -> Cnx<Empty<(usize, WidgetStreamI)>> + 'static,
+    {
+        self.configure.push(Box::new(configure));
+        self
+    }
+
+    /// Sets the attributes that widgets use by default, as
+    /// [`Cnx::default_attributes()`] does.
+    ///
+    /// [`Cnx::default_attributes()`]: struct.Cnx.html#method.default_attributes
+    pub fn default_attributes(self, attr: text::Attributes) -> Self {
+        self.configure(move |cnx| cnx.default_attributes(attr))
+    }
+
+    /// Uses the theme's normal attributes as the default for widgets, as
+    /// [`default_attributes()`] does.
+    ///
+    /// [`default_attributes()`]: #method.default_attributes
+    pub fn theme(self, theme: &theme::Theme) -> Self {
+        self.default_attributes(theme.attr())
+    }
+
+    /// Adds a widget to the left [`Zone`], to the right of any widgets
+    /// already added there.
+    ///
+    /// [`Zone`]: enum.Zone.html
+    pub fn widget<T: 'static, S: Stream<Item = WidgetStreamI> + 'static>(self, widget: WidgetStream<T, S>) -> Self {
+        self.widget_in_zone(Zone::Left, widget)
+    }
+
+    /// Adds a widget to the given [`Zone`], to the right of any widgets
+    /// already added there.
+    ///
+    /// [`Zone`]: enum.Zone.html
+    pub fn widget_in_zone<T: 'static, S: Stream<Item = WidgetStreamI> + 'static>(mut self, zone: Zone, widget: WidgetStream<T, S>) -> Self {
+        self.widgets.push((zone, widget.boxed()));
+        self
+    }
+
+    /// Creates the [`Cnx`], ready to [`run()`].
+    ///
+    /// This fails if the bar can't be created, e.g. if the X server can't be
+    /// connected to.
+    ///
+    /// [`run()`]: struct.Cnx.html#method.run
+    pub fn build(self) -> Result<Cnx<BoxedWidgets>> {
+        let cnx = if self.all_outputs {
+            Cnx::new_on_all_outputs(self.position)?
+        } else {
+            Cnx::new(self.position)?
+        };
+        let cnx = self
+            .configure
+            .into_iter()
+            .fold(cnx, |cnx, configure| configure(cnx));
+        self.widgets
+            .into_iter()
+            .try_fold(cnx.boxed(), |cnx, (zone, widget)| {
+                cnx.add_boxed_widget_in_zone(zone, widget)
+            })
+    }
+}
 
 /// The main object, used to instantiate an instance of Cnx.
 ///
@@ -141,8 +326,12 @@ pub use bar::Position;
 /// [`add_widget()`]: #method.add_widget
 /// [`run()`]: #method.run
 pub struct Cnx<FullStream: Stream<Item = (usize, WidgetStreamI)> + 'static> {
-    bar: Bar,
+    // One bar per output, all sharing a connection. There's always at least
+    // one, and the first is the one hotkeys are grabbed by.
+    bars: Vec<Bar>,
     stream: FullStream,
+    click_handlers: Vec<Option<ClickHandler>>,
+    hotkey_actions: Vec<HotkeyAction>,
 }
 
 impl Cnx<Empty<(usize, WidgetStreamI)>> {
@@ -151,63 +340,547 @@ impl Cnx<Empty<(usize, WidgetStreamI)>> {
     /// This creates a new `Cnx` instance at either the top or bottom of the
     /// screen, depending on the value of the [`Position`] enum.
     ///
+    /// The bar is shown on the primary monitor (as set by e.g. `xrandr
+    /// --primary`), or spans the whole screen if no monitor is primary. Use
+    /// [`new_on_all_outputs()`] to show a bar on every monitor.
+    ///
     /// [`Position`]: enum.Position.html
+    /// [`new_on_all_outputs()`]: #method.new_on_all_outputs
     pub fn new(position: Position) -> Result<Self> {
         Ok(Self {
-            bar: Bar::new(position)?,
+            bars: vec![Bar::new(position)?],
+            stream: tokio_stream::empty(),
+            click_handlers: Vec::new(),
+            hotkey_actions: Vec::new(),
+        })
+    }
+
+    /// Creates a new `Cnx` instance with a bar on each monitor (RandR
+    /// output), all at the given [`Position`].
+    ///
+    /// Every bar shows the same widgets, which are only run once, and is
+    /// configured the same way. Clicks are handled by the widget under the
+    /// pointer on whichever bar was clicked.
+    ///
+    /// The bars are created for the monitors connected when this is called.
+    /// A bar follows its monitor if it moves or changes resolution, but
+    /// bars aren't added or removed as monitors are plugged in or unplugged.
+    ///
+    /// [`Position`]: enum.Position.html
+    pub fn new_on_all_outputs(position: Position) -> Result<Self> {
+        Ok(Self {
+            bars: Bar::new_on_all_outputs(position)?,
             stream: tokio_stream::empty(),
+            click_handlers: Vec::new(),
+            hotkey_actions: Vec::new(),
         })
     }
+
+    /// Returns a [`CnxBuilder`] for a bar at the given [`Position`], as an
+    /// alternative to adding widgets to a `Cnx` one at a time.
+    ///
+    /// [`CnxBuilder`]: struct.CnxBuilder.html
+    /// [`Position`]: enum.Position.html
+    pub fn builder(position: Position) -> CnxBuilder {
+        CnxBuilder {
+            position,
+            all_outputs: false,
+            configure: Vec::new(),
+            widgets: Vec::new(),
+        }
+    }
+
+    /// Insets the bar from the edges of the screen, e.g. for a "floating"
+    /// bar. Only the part of the screen edge covered by the bar is reserved,
+    /// so maximized windows can still extend into the margins either side.
+    ///
+    /// This can only be called before any widgets are added.
+    pub fn margins(self, margins: Margins) -> Self {
+        self.for_each_bar(|bar| bar.set_margins(margins.clone()))
+    }
+
+    /// Rounds the corners of the bar by `radius` pixels. This looks best
+    /// with [`margins()`], so that the bar floats away from the screen edges.
+    ///
+    /// The window is clipped with the X SHAPE extension, so this works without
+    /// a compositor, but the corners aren't anti-aliased.
+    ///
+    /// This can only be called before any widgets are added.
+    ///
+    /// [`margins()`]: #method.margins
+    pub fn corner_radius(self, radius: u16) -> Self {
+        self.for_each_bar(|bar| bar.set_corner_radius(radius))
+    }
+
+    /// Sets the bar's background, which is shown between widgets and behind
+    /// texts without a `bg_color`, e.g. a gradient:
+    ///
+    /// ```no_run
+    /// # use cnx::{Cnx, Position};
+    /// # use cnx::text::{Background, Color, Gradient, GradientDirection};
+    /// # fn run() -> anyhow::Result<()> {
+    /// let cnx = Cnx::new(Position::Top)?.background(Background::Gradient(Gradient {
+    ///     from: Color::from_rgb(0x28, 0x28, 0x28),
+    ///     to: Color::from_rgb(0x45, 0x85, 0x88),
+    ///     direction: GradientDirection::Horizontal,
+    /// }));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The default is black. Translucent colors (see [`Color::with_alpha()`])
+    /// let windows behind the bar show through, if a compositor is running.
+    /// This can only be called before any widgets are added.
+    ///
+    /// [`Color::with_alpha()`]: text/struct.Color.html#method.with_alpha
+    pub fn background(self, background: text::Background) -> Self {
+        self.for_each_bar(|bar| bar.set_background(background.clone()))
+    }
+
+    /// Stacks the bar above or below other windows, e.g. to keep it above or
+    /// below another panel. By default the bar is raised whenever it is
+    /// resized, but the window manager is free to restack it.
+    ///
+    /// This can only be called before any widgets are added.
+    pub fn stacking(self, stacking: Stacking) -> Self {
+        self.for_each_bar(|bar| bar.set_stacking(stacking))
+    }
+
+    /// Hides the bar while a fullscreen window is shown, either the focused
+    /// window or any window on the current desktop. The space reserved for the
+    /// bar is released while it's hidden, so other windows can use it.
+    ///
+    /// Only a bar on the same monitor as the fullscreen window is hidden, so
+    /// with [`new_on_all_outputs()`] the bars on other monitors stay shown.
+    ///
+    /// This can only be called before any widgets are added.
+    ///
+    /// [`new_on_all_outputs()`]: #method.new_on_all_outputs
+    pub fn hide_on_fullscreen(self, which: HideOnFullscreen) -> Self {
+        self.for_each_bar(|bar| bar.set_hide_on_fullscreen(which))
+    }
+
+    /// Multiplies font sizes and padding by `scale`, e.g. `2.0` on a HiDPI
+    /// display. The bar's height follows the size of its text, so scales too.
+    ///
+    /// The default is `1.0`. This can only be called before any widgets
+    /// are added.
+    pub fn scale_factor(self, scale: f64) -> Self {
+        self.for_each_bar(|bar| bar.set_scale(scale))
+    }
+
+    /// Sets the scale factor from the `Xft.dpi` X resource (as set by e.g.
+    /// `xrdb` or a desktop environment), relative to the usual 96 DPI. If the
+    /// resource isn't set, the scale factor is left unchanged.
+    ///
+    /// This can only be called before any widgets are added.
+    pub fn scale_to_xft_dpi(self) -> Self {
+        match self.bars[0].xft_dpi() {
+            Some(dpi) => self.scale_factor(dpi / 96.0),
+            None => self,
+        }
+    }
 }
 
 impl<FullStream: Stream<Item = (usize, WidgetStreamI)> + 'static> Cnx<FullStream> {
     /// Adds a widget to the `Cnx` instance.
     ///
-    /// Takes ownership of the [`Widget`] and adds it to the Cnx instance to
-    /// the right of any existing widgets.
+    /// Takes ownership of the [`WidgetStream`] and adds it to the Cnx instance
+    /// to the right of any existing widgets in the left [`Zone`].
+    ///
+    /// If the widget's stream can't be created (e.g. a missing backend), the
+    /// error is reported as the widget's first item rather than returned from
+    /// here, so that one broken widget doesn't take down the whole bar.
     ///
-    /// [`Widget`]: widgets/trait.Widget.html
-    pub fn add_widget<T: 'static, S: Stream<Item = WidgetStreamI> + 'static>(mut self, stream: WidgetStream<T, S>) -> Result<Cnx<impl Stream<Item = (usize, WidgetStreamI)> + 'static>> {
-        let idx = self.bar.add_content(Vec::new())?;
+    /// [`WidgetStream`]: widgets/struct.WidgetStream.html
+    /// [`Zone`]: enum.Zone.html
+    pub fn add_widget<T: 'static, S: Stream<Item = WidgetStreamI> + 'static>(self, stream: WidgetStream<T, S>) -> Result<Cnx<impl Stream<Item = (usize, WidgetStreamI)> + 'static>> {
+        self.add_widget_in_zone(Zone::Left, stream)
+    }
+
+    /// Adds a widget to the given [`Zone`] of the bar, to the right of any
+    /// existing widgets in that zone.
+    ///
+    /// [`Zone`]: enum.Zone.html
+    pub fn add_widget_in_zone<T: 'static, S: Stream<Item = WidgetStreamI> + 'static>(mut self, zone: Zone, mut stream: WidgetStream<T, S>) -> Result<Cnx<impl Stream<Item = (usize, WidgetStreamI)> + 'static>> {
+        let idx = self.click_handlers.len();
+        let click_handler = stream.take_click_handler();
+        for bar in &mut self.bars {
+            bar.add_content(zone, Vec::new())?;
+            bar.set_segment_gap(idx, stream.gap());
+            bar.set_name(idx, stream.name());
+            bar.set_clickable(idx, click_handler.is_some());
+        }
+        self.click_handlers.push(click_handler);
         Ok(Cnx {
-            bar: self.bar,
-            stream: self.stream.merge(stream.into_stream()?.map(move |v| (idx, v))),
+            bars: self.bars,
+            stream: self.stream.merge(stream.into_stream().map(move |v| (idx, v))),
+            click_handlers: self.click_handlers,
+            hotkey_actions: self.hotkey_actions,
         })
     }
 
+    /// Adds a widget whose type has been erased with
+    /// [`WidgetStream::boxed()`] to the left [`Zone`], as [`add_widget()`]
+    /// does.
+    ///
+    /// Unlike [`add_widget()`], this returns a `Cnx` of the same type each
+    /// time, so widgets can be added in a loop, e.g. from a list assembled
+    /// from configuration:
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::{ActiveWindowTitle, BoxedWidget, Clock};
+    /// # use cnx::{Cnx, Position};
+    /// # async fn run(show_title: bool) -> anyhow::Result<()> {
+    /// let mut widgets: Vec<BoxedWidget> = Vec::new();
+    /// if show_title {
+    ///     widgets.push(ActiveWindowTitle::new(Attributes::default()).boxed());
+    /// }
+    /// widgets.push(Clock::new(Attributes::default(), None).boxed());
+    ///
+    /// let mut cnx = Cnx::new(Position::Top)?.boxed();
+    /// for widget in widgets {
+    ///     cnx = cnx.add_boxed_widget(widget)?;
+    /// }
+    /// cnx.run().await
+    /// # }
+    /// ```
+    ///
+    /// [`WidgetStream::boxed()`]: widgets/struct.WidgetStream.html#method.boxed
+    /// [`Zone`]: enum.Zone.html
+    /// [`add_widget()`]: #method.add_widget
+    pub fn add_boxed_widget(self, widget: BoxedWidget) -> Result<Cnx<BoxedWidgets>> {
+        self.add_boxed_widget_in_zone(Zone::Left, widget)
+    }
+
+    /// Adds a widget whose type has been erased with
+    /// [`WidgetStream::boxed()`] to the given [`Zone`], as
+    /// [`add_widget_in_zone()`] does.
+    ///
+    /// [`WidgetStream::boxed()`]: widgets/struct.WidgetStream.html#method.boxed
+    /// [`Zone`]: enum.Zone.html
+    /// [`add_widget_in_zone()`]: #method.add_widget_in_zone
+    pub fn add_boxed_widget_in_zone(self, zone: Zone, widget: BoxedWidget) -> Result<Cnx<BoxedWidgets>> {
+        Ok(self.add_widget_in_zone(zone, widget)?.boxed())
+    }
+
+    /// Boxes the widgets added so far, so that the `Cnx`'s type doesn't
+    /// change as more are added with [`add_boxed_widget()`].
+    ///
+    /// [`add_boxed_widget()`]: #method.add_boxed_widget
+    pub fn boxed(self) -> Cnx<BoxedWidgets> {
+        Cnx {
+            bars: self.bars,
+            stream: Box::pin(self.stream),
+            click_handlers: self.click_handlers,
+            hotkey_actions: self.hotkey_actions,
+        }
+    }
+
+    /// Returns the bar's connection to the X server.
+    ///
+    /// This lets widgets that query or change EWMH state (desktops, windows)
+    /// share the bar's connection rather than opening their own. The
+    /// connection is the same one the bar uses to draw and receive events, so:
+    ///
+    /// - It is not `Send`: it may only be used from the thread running the
+    ///   bar, which in practice means from within widget streams.
+    /// - It may be used for requests/replies and for sending client messages
+    ///   (remember to `flush()` afterwards), but never to wait for or poll
+    ///   events, as that would steal the bar's own events.
+    /// - It stays open until the [`Cnx`] instance and every clone of the
+    ///   returned handle have been dropped.
+    pub fn connection(&self) -> Rc<ewmh::Connection> {
+        Rc::clone(&self.bars[0].conn)
+    }
+
+    /// Returns the index of the screen the bar is displayed on, as expected by
+    /// the `ewmh` functions alongside [`connection()`].
+    ///
+    /// [`connection()`]: #method.connection
+    pub fn screen_idx(&self) -> i32 {
+        self.bars[0].screen_idx as i32
+    }
+
+    /// Sets the text shown in place of a widget when it reports an error.
+    ///
+    /// The indicator is cleared as soon as the widget next updates
+    /// successfully. By default, a `⚠` glyph is shown in red using the
+    /// widget's own attributes, or the default attributes if the widget
+    /// hasn't shown anything yet.
+    pub fn error_indicator(self, text: Text) -> Self {
+        self.for_each_bar(|bar| bar.set_error_text(text.clone()))
+    }
+
+    /// Sets the attributes that widgets use by default.
+    ///
+    /// Widgets given [`Attributes::default()`] use these attributes, whether
+    /// they were created before or after this is called. Widgets that are
+    /// given their own attributes are unaffected, and a widget can override
+    /// just some of the defaults:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// let warning = Attributes {
+    ///     fg_color: Color::red(),
+    ///     ..Attributes::default()
+    /// };
+    /// ```
+    ///
+    /// [`Attributes::default()`]: text/struct.Attributes.html#impl-Default
+    pub fn default_attributes(self, attr: text::Attributes) -> Self {
+        self.for_each_bar(|bar| bar.set_default_attributes(attr.clone()))
+    }
+
+    /// Uses the theme's normal attributes as the default for widgets, as
+    /// [`default_attributes()`] does.
+    ///
+    /// [`default_attributes()`]: #method.default_attributes
+    pub fn theme(self, theme: &theme::Theme) -> Self {
+        self.default_attributes(theme.attr())
+    }
+
+    /// Grabs `hotkey` globally, so that pressing it triggers `action`
+    /// wherever the keyboard focus is, e.g. to toggle the bar with
+    /// Super+B:
+    ///
+    /// ```no_run
+    /// # use cnx::{Cnx, Hotkey, HotkeyAction, Position};
+    /// # fn run() -> anyhow::Result<()> {
+    /// let cnx = Cnx::new(Position::Top)?
+    ///     .hotkey(Hotkey::new(Hotkey::SUPER, 0x62), HotkeyAction::ToggleVisibility);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// If the key can't be grabbed (e.g. another program already has), an
+    /// error is logged and the hotkey does nothing.
+    pub fn hotkey(mut self, hotkey: Hotkey, action: HotkeyAction) -> Self {
+        let idx = self.hotkey_actions.len();
+        // The key is grabbed on the root window, so one bar grabbing it is
+        // enough, even with a bar on each monitor.
+        if let Err(err) = self.bars[0].grab_hotkey(idx, hotkey) {
+            println!("Error grabbing hotkey {:?}: {:#}", hotkey, err);
+        }
+        self.hotkey_actions.push(action);
+        self
+    }
+
+    // Applies a setting to each of the bars.
+    fn for_each_bar(mut self, f: impl FnMut(&mut Bar)) -> Self {
+        self.bars.iter_mut().for_each(f);
+        self
+    }
+
+    /// Checks that every widget is able to produce its first update.
+    ///
+    /// Each widget's stream is created and polled until it yields once, or
+    /// until [`CHECK_TIMEOUT`] elapses. A per-widget OK/FAIL report is printed
+    /// and an error is returned if any widget failed. This is intended to be
+    /// used instead of [`run()`] when debugging a configuration.
+    ///
+    /// [`run()`]: #method.run
+    pub async fn check(self) -> Result<()> {
+        let bar = &self.bars[0];
+        let count = bar.widget_count();
+        let mut results: Vec<Option<Result<()>>> = (0..count).map(|_| None).collect();
+
+        let stream = self.stream;
+        let deadline = time::sleep(CHECK_TIMEOUT);
+        pin!(stream);
+        pin!(deadline);
+        while results.iter().any(Option::is_none) {
+            tokio::select! {
+                _ = &mut deadline => break,
+                item = stream.next() => match item {
+                    Some((idx, result)) => {
+                        if results[idx].is_none() {
+                            results[idx] = Some(result.map(|_| ()));
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let mut failed = 0;
+        for (idx, result) in results.into_iter().enumerate() {
+            match result {
+                Some(Ok(())) => println!("Widget {}: OK", bar.widget_label(idx)),
+                Some(Err(err)) => {
+                    failed += 1;
+                    println!("Widget {}: FAIL: {:#}", bar.widget_label(idx), err);
+                }
+                None => {
+                    failed += 1;
+                    println!(
+                        "Widget {}: FAIL: no update within {:?}",
+                        bar.widget_label(idx),
+                        CHECK_TIMEOUT
+                    );
+                }
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow!("{} of {} widgets failed", failed, count));
+        }
+        Ok(())
+    }
+
 
     /// Runs the Cnx instance.
     ///
     /// This method takes ownership of the Cnx instance and runs it until either
     /// the process is terminated, or an internal error is returned.
     pub async fn run(self) -> Result<()> {
-        let bar = self.bar;
+        self.run_until(future::pending::<()>()).await
+    }
+
+    /// Runs the Cnx instance until `shutdown` completes, then removes the
+    /// bar's window and returns `Ok(())`.
+    ///
+    /// This is for embedding Cnx in a larger application (or testing it),
+    /// where the bar needs to be stopped without terminating the process,
+    /// e.g. with a channel:
+    ///
+    /// ```no_run
+    /// # use cnx::{Cnx, Position};
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    /// let cnx = Cnx::new(Position::Top)?;
+    /// # drop(stop);
+    /// cnx.run_until(async { stopped.await.ok(); }).await
+    /// # }
+    /// ```
+    ///
+    /// Any future will do, so a `tokio_util::sync::CancellationToken`'s
+    /// `cancelled()` or `tokio::signal::ctrl_c()` work too.
+    pub async fn run_until<F: Future>(self, shutdown: F) -> Result<()> {
+        let bars = self.bars;
         let stream = self.stream;
+        let mut click_handlers = self.click_handlers;
+        let hotkey_actions = self.hotkey_actions;
 
-        let mut event_stream = BarEventStream::new(bar)?;
+        let mut event_stream = BarEventStream::new(bars)?;
+        let flush_updates = time::sleep(Duration::from_secs(0));
+        let mut updates_pending = false;
+        let blink = time::sleep(Duration::from_secs(0));
         pin!(stream);
+        pin!(flush_updates);
+        pin!(blink);
+        pin!(shutdown);
         loop {
+            let blinking = event_stream.bars_mut().iter().any(Bar::is_blinking);
             tokio::select! {
-                // Pass each XCB event to the Bar.
+                // Pass each XCB event to the Bar it's for, or to every Bar if
+                // it isn't for a particular one. Clicks on a widget are
+                // passed on to its handler, if it has one, and hotkeys to
+                // their action.
                 Some(event) = event_stream.next() => {
-                    if let Err(err) = event_stream.bar_mut().process_event(event) {
-                        println!("Error processing XCB event: {}", err);
+                    let bars = event_stream.bars_mut();
+                    let results: Vec<_> = bars
+                        .iter_mut()
+                        .filter(|bar| bar.is_for(&event))
+                        .map(|bar| bar.process_event(&event))
+                        .collect();
+                    for result in results {
+                        match result {
+                            Ok(Some(BarEvent::Click(idx, click))) => {
+                                if let Some(handler) = click_handlers[idx].as_mut() {
+                                    let conn: &ewmh::Connection = &bars[0].conn;
+                                    if let Err(err) = handler(conn, click) {
+                                        let label = bars[0].widget_label(idx);
+                                        println!("Error handling click on widget {}: {}", label, err);
+                                    }
+                                }
+                            }
+                            Ok(Some(BarEvent::Hotkey(idx))) => {
+                                if let Err(err) = run_hotkey_action(bars, &hotkey_actions[idx]) {
+                                    println!("Error handling hotkey {}: {:#}", idx, err);
+                                }
+                            }
+                            Ok(None) => (),
+                            Err(err) => println!("Error processing XCB event: {}", err),
+                        }
                     }
                 },
 
-                // Each time a widget yields new values, pass to the bar.
-                // Ignore (but log) any errors from widgets.
+                // Each time a widget yields new values, pass to the bars.
+                // Errors from widgets are logged and replace the widget's
+                // content with the error indicator.
+                //
+                // Updates are queued and applied together shortly afterwards,
+                // so that widgets updating at the same time cause only one
+                // redraw.
                 Some((idx, result)) = stream.next() => {
+                    let bars = event_stream.bars_mut();
                     match result {
-                        Err(err) => println!("Error from widget {}: {}", idx, err),
+                        Err(err) => {
+                            let label = bars[0].widget_label(idx);
+                            println!("Error from widget {}: {}", label, err);
+                            for bar in bars {
+                                bar.show_error(idx);
+                            }
+                        }
                         Ok(texts) => {
-                            if let Err(err) = event_stream.bar_mut().update_content(idx, texts) {
-                                println!("Error updating widget {}: {}", idx, err);
+                            for bar in bars {
+                                bar.queue_content(idx, texts.clone());
                             }
                         }
                     }
+                    if !updates_pending {
+                        updates_pending = true;
+                        flush_updates.as_mut().reset(time::Instant::now() + COALESCE_UPDATES);
+                    }
                 }
+
+                _ = &mut flush_updates, if updates_pending => {
+                    updates_pending = false;
+                    for bar in event_stream.bars_mut() {
+                        if let Err(err) = bar.flush_updates() {
+                            println!("Error updating widgets: {:#}", err);
+                        }
+                    }
+                }
+
+                // Only wake up to blink while something is blinking.
+                _ = &mut blink, if blinking => {
+                    for bar in event_stream.bars_mut() {
+                        if let Err(err) = bar.toggle_blink() {
+                            println!("Error blinking widgets: {:#}", err);
+                        }
+                    }
+                    blink.as_mut().reset(time::Instant::now() + BLINK_INTERVAL);
+                }
+
+                _ = &mut shutdown => {
+                    for bar in event_stream.bars_mut() {
+                        bar.destroy();
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn run_hotkey_action(bars: &mut [Bar], action: &HotkeyAction) -> Result<()> {
+    match action {
+        HotkeyAction::ToggleVisibility => bars.iter_mut().for_each(Bar::toggle_hidden),
+        HotkeyAction::Command(command) => {
+            Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .spawn()
+                .with_context(|| format!("Failed to run hotkey command {:?}", command))?;
+        }
+        HotkeyAction::Notify(sender) => {
+            if let Err(mpsc::error::TrySendError::Closed(())) = sender.try_send(()) {
+                return Err(anyhow!("The hotkey's receiver was dropped"));
             }
         }
     }
+    Ok(())
 }
@@ -4,15 +4,25 @@
 //! implementations for inspiration.
 
 use anyhow::{anyhow, Result};
-use cairo::{Context, Surface};
-use pango::{EllipsizeMode, FontDescription};
+use cairo::{Context, ImageSurface, LinearGradient, Operator, Surface};
+use pango::{EllipsizeMode, FontDescription, Style, Weight};
 use std::fmt;
+use std::rc::Rc;
 
+/// A color, with an alpha channel.
+///
+/// Colors are opaque unless given an alpha with [`Color::from_rgba()`] or
+/// [`Color::with_alpha()`]. Translucent bar backgrounds need a compositor;
+/// without one, they're blended with black.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Color {
     red: f64,
     green: f64,
     blue: f64,
+    alpha: f64,
+    // Whether this stands in for the bar's default color, see
+    // `Attributes::default()`.
+    inherit: bool,
 }
 
 macro_rules! color {
@@ -23,6 +33,8 @@ macro_rules! color {
                 red: $r,
                 green: $g,
                 blue: $b,
+                alpha: 1.0,
+                inherit: false,
             }
         }
     };
@@ -37,34 +49,102 @@ impl Color {
     color!(yellow, (1.0, 1.0, 0.0));
 
     pub fn apply_to_context(&self, cr: &Context) {
-        cr.set_source_rgb(self.red, self.green, self.blue);
+        cr.set_source_rgba(self.red, self.green, self.blue, self.alpha);
+    }
+
+    /// Like [`apply_to_context()`](#method.apply_to_context), but with the
+    /// color's alpha multiplied by `alpha`.
+    pub fn apply_to_context_with_alpha(&self, cr: &Context, alpha: f64) {
+        cr.set_source_rgba(self.red, self.green, self.blue, self.alpha * alpha);
     }
 
     pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgba(r, g, b, 255)
+    }
+
+    /// Creates a color from its components, where an alpha of 0 is fully
+    /// transparent and 255 is opaque.
+    pub fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self {
             red: r as f64 / 255.0,
             green: g as f64 / 255.0,
             blue: b as f64 / 255.0,
+            alpha: a as f64 / 255.0,
+            inherit: false,
         }
     }
 
-    pub fn to_hex(&self) -> String {
-        let r = if self.red >= 1.0 {
-            255
-        } else {
-            (self.red * 255.0) as i32
-        };
-        let g = if self.green >= 1.0 {
-            255
-        } else {
-            (self.green * 255.0) as i32
+    /// Returns the color with its alpha set to `alpha`, from 0.0 (fully
+    /// transparent) to 1.0 (opaque), e.g. `Color::black().with_alpha(0.7)`
+    /// for a translucent background.
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha.max(0.0).min(1.0);
+        self
+    }
+
+    /// The color's alpha, from 0.0 (fully transparent) to 1.0 (opaque).
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Parses a color from a hex string: `#RRGGBB`, `#RRGGBBAA`, or the
+    /// short forms `#RGB` and `#RGBA`, where each digit is doubled (so
+    /// `#F80` is `#FF8800`). The `#` is optional and case doesn't matter.
+    ///
+    /// ```
+    /// # use cnx::text::Color;
+    /// let accent = Color::from_hex("#88C0D0").unwrap();
+    /// assert_eq!(accent, Color::from_rgb(0x88, 0xc0, 0xd0));
+    /// ```
+    pub fn from_hex(hex: &str) -> Result<Color> {
+        let invalid = || anyhow!("Invalid hex color {:?}", hex);
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let digits: Vec<u8> = digits
+            .chars()
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let components: Vec<u8> = match digits.len() {
+            3 | 4 => digits.iter().map(|digit| digit * 0x11).collect(),
+            6 | 8 => digits
+                .chunks(2)
+                .map(|pair| pair[0] * 0x10 + pair[1])
+                .collect(),
+            _ => return Err(invalid()),
         };
-        let b = if self.blue >= 1.0 {
-            255
+        let alpha = components.get(3).copied().unwrap_or(0xff);
+        Ok(Color::from_rgba(
+            components[0],
+            components[1],
+            components[2],
+            alpha,
+        ))
+    }
+
+    fn inherited() -> Color {
+        Color {
+            inherit: true,
+            ..Color::white()
+        }
+    }
+
+    /// Formats the color as `#RRGGBB`, or `#RRGGBBAA` if it isn't opaque,
+    /// e.g. for use in Pango markup.
+    pub fn to_hex(&self) -> String {
+        let byte = |component: f64| (component.max(0.0).min(1.0) * 255.0).round() as u8;
+        let rgb = format!(
+            "#{:02X}{:02X}{:02X}",
+            byte(self.red),
+            byte(self.green),
+            byte(self.blue)
+        );
+        // Opaque colors leave out the alpha, for Pango versions that don't
+        // understand it.
+        if self.alpha >= 1.0 {
+            rgb
         } else {
-            (self.blue * 255.0) as i32
-        };
-        format!("#{:0width$X}{:0width$X}{:0width$X}", r, g, b, width = 2)
+            format!("{}{:02X}", rgb, byte(self.alpha))
+        }
     }
 }
 
@@ -74,6 +154,9 @@ pub struct Padding {
     right: f64,
     top: f64,
     bottom: f64,
+    // Whether this stands in for the bar's default padding, see
+    // `Attributes::default()`.
+    inherit: bool,
 }
 
 impl Padding {
@@ -83,17 +166,70 @@ impl Padding {
             right,
             top,
             bottom,
+            inherit: false,
+        }
+    }
+
+    fn inherited() -> Padding {
+        Padding {
+            inherit: true,
+            ..Padding::new(0.0, 0.0, 0.0, 0.0)
         }
     }
+
+    fn scaled(&self, scale: f64) -> Padding {
+        Padding::new(
+            self.left * scale,
+            self.right * scale,
+            self.top * scale,
+            self.bottom * scale,
+        )
+    }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub struct Font(FontDescription);
 
 impl Font {
+    /// Creates a font from a Pango font description string, e.g.
+    /// `"Ubuntu Mono Bold 14"`.
     pub fn new(name: &str) -> Font {
         Font(FontDescription::from_string(name))
     }
+
+    /// Creates a font from a family name and a size in points.
+    ///
+    /// The result can be styled further with the other builder methods, e.g.
+    /// `Font::from_family("Ubuntu Mono", 14.0).bold().italic()`.
+    pub fn from_family(family: &str, size: f64) -> Font {
+        let mut description = FontDescription::new();
+        description.set_family(family);
+        Font(description).size(size)
+    }
+
+    /// Sets the size of the font, in points.
+    pub fn size(mut self, size: f64) -> Font {
+        self.0.set_size((size * f64::from(pango::SCALE)) as i32);
+        self
+    }
+
+    /// Uses the bold weight of the font.
+    pub fn bold(mut self) -> Font {
+        self.0.set_weight(Weight::Bold);
+        self
+    }
+
+    /// Uses the light weight of the font.
+    pub fn light(mut self) -> Font {
+        self.0.set_weight(Weight::Light);
+        self
+    }
+
+    /// Uses the italic style of the font.
+    pub fn italic(mut self) -> Font {
+        self.0.set_style(Style::Italic);
+        self
+    }
 }
 
 impl fmt::Debug for Font {
@@ -102,6 +238,15 @@ impl fmt::Debug for Font {
     }
 }
 
+// Fonts are compared by their description string, e.g. `"Sans Bold 12"`, so
+// that fonts built in different ways compare equal if Pango would pick the
+// same font for them.
+impl PartialEq for Font {
+    fn eq(&self, other: &Font) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Attributes {
     pub font: Font,
@@ -110,42 +255,457 @@ pub struct Attributes {
     pub padding: Padding,
 }
 
+impl Attributes {
+    /// Starts building `Attributes` from the defaults (see
+    /// [`Attributes::default()`]), e.g.
+    /// `Attributes::builder().font(Font::new("Ubuntu Mono 14")).fg(Color::red()).build()`.
+    ///
+    /// [`Attributes::default()`]: #impl-Default
+    pub fn builder() -> AttributesBuilder {
+        AttributesBuilder {
+            attr: Attributes::default(),
+        }
+    }
+
+    // The bar's default attributes until `Cnx::default_attributes()` is
+    // called: a white `Sans 12` font with no background or padding.
+    pub(crate) fn initial() -> Attributes {
+        Attributes {
+            font: Font::new("Sans 12"),
+            fg_color: Color::white(),
+            bg_color: None,
+            padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    // Fills in the attributes left as defaults (see `Attributes::default()`)
+    // from `default`. Fonts are merged, so that e.g. a font of just `"Bold"`
+    // takes its family and size from the default font.
+    pub(crate) fn inherit(&self, default: &Attributes) -> Attributes {
+        let mut font = self.font.0.clone();
+        font.merge(Some(&default.font.0), false);
+        let fg_color = if self.fg_color.inherit {
+            default.fg_color.clone()
+        } else {
+            self.fg_color.clone()
+        };
+        let bg_color = match &self.bg_color {
+            Some(color) if color.inherit => default.bg_color.clone(),
+            bg_color => bg_color.clone(),
+        };
+        let padding = if self.padding.inherit {
+            default.padding.clone()
+        } else {
+            self.padding.clone()
+        };
+        Attributes {
+            font: Font(font),
+            fg_color,
+            bg_color,
+            padding,
+        }
+    }
+}
+
+/// A builder for [`Attributes`], created by [`Attributes::builder()`].
+///
+/// [`Attributes`]: struct.Attributes.html
+/// [`Attributes::builder()`]: struct.Attributes.html#method.builder
+#[derive(Clone, Debug)]
+pub struct AttributesBuilder {
+    attr: Attributes,
+}
+
+impl AttributesBuilder {
+    /// Sets the font.
+    pub fn font(mut self, font: Font) -> Self {
+        self.attr.font = font;
+        self
+    }
+
+    /// Sets the foreground (text) color.
+    pub fn fg(mut self, color: Color) -> Self {
+        self.attr.fg_color = color;
+        self
+    }
+
+    /// Sets the background color.
+    pub fn bg(mut self, color: Color) -> Self {
+        self.attr.bg_color = Some(color);
+        self
+    }
+
+    /// Sets the padding around the text.
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.attr.padding = padding;
+        self
+    }
+
+    pub fn build(self) -> Attributes {
+        self.attr
+    }
+}
+
+impl Default for Attributes {
+    /// Returns attributes that use the bar's defaults, as set by
+    /// [`Cnx::default_attributes()`]. Until that is called, they're a white
+    /// `Sans 12` font with no background or padding.
+    ///
+    /// The defaults are filled in when the widget's text reaches the bar, so
+    /// it doesn't matter whether the widget is created before or after
+    /// [`Cnx::default_attributes()`] is called. Fields that are set replace
+    /// the defaults, e.g. to use the bar's font in red:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// let warning = Attributes {
+    ///     fg_color: Color::red(),
+    ///     ..Attributes::default()
+    /// };
+    /// ```
+    ///
+    /// [`Cnx::default_attributes()`]: ../struct.Cnx.html#method.default_attributes
+    fn default() -> Self {
+        Attributes {
+            font: Font(FontDescription::new()),
+            fg_color: Color::inherited(),
+            bg_color: Some(Color::inherited()),
+            padding: Padding::inherited(),
+        }
+    }
+}
+
 fn create_pango_layout(cairo_context: &cairo::Context) -> Result<pango::Layout> {
     let layout = pangocairo::functions::create_layout(cairo_context)
         .ok_or_else(|| anyhow!("Failed to create Pango layout"))?;
     Ok(layout)
 }
 
+// Shortens `text` until it fits within `width` pixels when shown in `font`
+// (before the bar's scale factor is applied), replacing what was removed with
+// an ellipsis.
+pub(crate) fn truncate_to_width(text: &str, font: &Font, width: f64) -> Result<String> {
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 1, 1)
+        .map_err(|status| anyhow!("ImageSurface::create: {}", status))?;
+    let context = Context::new(&surface);
+    let layout = create_pango_layout(&context)?;
+    layout.set_font_description(Some(&font.0));
+    let fits = |text: &str| {
+        layout.set_text(text);
+        f64::from(layout.get_pixel_size().0) <= width
+    };
+    if fits(text) {
+        return Ok(text.to_owned());
+    }
+
+    // Find the longest prefix that fits alongside the ellipsis.
+    let chars: Vec<char> = text.chars().collect();
+    let truncated = |len: usize| chars[..len].iter().chain(&['…']).collect::<String>();
+    let (mut fitting, mut too_long) = (0, chars.len());
+    while too_long - fitting > 1 {
+        let len = (fitting + too_long) / 2;
+        if fits(&truncated(len)) {
+            fitting = len;
+        } else {
+            too_long = len;
+        }
+    }
+    Ok(truncated(fitting))
+}
+
 fn show_pango_layout(cairo_context: &cairo::Context, layout: &pango::Layout) {
     pangocairo::functions::show_layout(cairo_context, layout);
 }
 
+// The resolution Pango assumes when converting font sizes from points to
+// pixels. The bar's scale factor is applied by changing this.
+const BASE_DPI: f64 = 96.0;
+
+/// The direction of a [`Gradient`].
+///
+/// [`Gradient`]: struct.Gradient.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientDirection {
+    /// From the left edge of the bar to the right edge.
+    Horizontal,
+    /// From the top edge of the bar to the bottom edge.
+    Vertical,
+}
+
+/// A linear gradient between two colors, spanning the whole bar.
 #[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub from: Color,
+    pub to: Color,
+    pub direction: GradientDirection,
+}
+
+/// The background of the bar, shown behind texts without a `bg_color` and
+/// between widgets. Passed to [`Cnx::background()`].
+///
+/// The default is a flat black background.
+///
+/// [`Cnx::background()`]: ../struct.Cnx.html#method.background
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    Color(Color),
+    Gradient(Gradient),
+}
+
+impl Background {
+    // Sets the background as the source of `cr`, for a bar `width` by
+    // `height` pixels in `cr`'s current user space.
+    pub(crate) fn apply_to_context(&self, cr: &Context, width: f64, height: f64) {
+        match self {
+            Background::Color(color) => color.apply_to_context(cr),
+            Background::Gradient(gradient) => {
+                let (x1, y1) = match gradient.direction {
+                    GradientDirection::Horizontal => (width, 0.0),
+                    GradientDirection::Vertical => (0.0, height),
+                };
+                let pattern = LinearGradient::new(0.0, 0.0, x1, y1);
+                let from = &gradient.from;
+                let to = &gradient.to;
+                pattern.add_color_stop_rgba(0.0, from.red, from.green, from.blue, from.alpha);
+                pattern.add_color_stop_rgba(1.0, to.red, to.green, to.blue, to.alpha);
+                cr.set_source(&pattern);
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::Color(Color::black())
+    }
+}
+
+// The opacity of the foreground of a dimmed `Text`.
+const DIM_ALPHA: f64 = 0.5;
+
+/// A horizontal bar, filled in proportion to a value, that is drawn in place
+/// of a [`Text`]'s text, e.g. to show battery charge or volume.
+///
+/// The filled part uses `fg` (or the text's foreground color) and the rest
+/// uses `bg` (or is left unfilled). The bar is vertically centered within
+/// the text's padding.
+///
+/// [`Text`]: struct.Text.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressBar {
+    /// How full the bar is, from 0 to 100.
+    pub value: f64,
+    /// The width of the bar, in pixels.
+    pub width: f64,
+    /// The height of the bar, in pixels. Defaults to the height of a line
+    /// of text.
+    pub height: Option<f64>,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl ProgressBar {
+    /// Creates a bar `width` pixels wide, filled to `value` percent.
+    pub fn new(value: f64, width: f64) -> ProgressBar {
+        ProgressBar {
+            value,
+            width,
+            height: None,
+            fg: None,
+            bg: None,
+        }
+    }
+
+    fn render(&self, cr: &Context, attr: &Attributes, width: f64, height: f64, scale: f64) {
+        let bar_height = self.height.map_or(height, |h| (h * scale).min(height));
+        let y = (height - bar_height) / 2.0;
+        if let Some(bg) = &self.bg {
+            bg.apply_to_context(cr);
+            cr.rectangle(0.0, y, width, bar_height);
+            cr.fill();
+        }
+        let fraction = self.value.max(0.0).min(100.0) / 100.0;
+        self.fg.as_ref().unwrap_or(&attr.fg_color).apply_to_context(cr);
+        cr.rectangle(0.0, y, width * fraction, bar_height);
+        cr.fill();
+    }
+}
+
+/// A custom drawing, made directly with Cairo, to draw in place of a
+/// [`Text`]'s text, e.g. a sparkline or an icon.
+///
+/// The closure is passed a context whose origin is the top-left corner
+/// inside the text's padding and which is clipped to that area, along with
+/// the area's width and height (in device pixels, so already multiplied by
+/// the bar's scale).
+///
+/// Two drawables are only equal if they share the same closure, so a widget
+/// that creates a new one on each update is always redrawn.
+///
+/// [`Text`]: struct.Text.html
+#[derive(Clone)]
+pub struct Drawable {
+    /// The width of the drawing, in pixels.
+    pub width: f64,
+    draw: Rc<dyn Fn(&Context, f64, f64)>,
+}
+
+impl Drawable {
+    /// Creates a drawing `width` pixels wide, drawn by `draw`.
+    pub fn new<F>(width: f64, draw: F) -> Drawable
+    where
+        F: Fn(&Context, f64, f64) + 'static,
+    {
+        Drawable {
+            width,
+            draw: Rc::new(draw),
+        }
+    }
+}
+
+impl fmt::Debug for Drawable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Drawable")
+            .field("width", &self.width)
+            .finish()
+    }
+}
+
+impl PartialEq for Drawable {
+    fn eq(&self, other: &Drawable) -> bool {
+        self.width == other.width
+            && Rc::as_ptr(&self.draw) as *const () == Rc::as_ptr(&other.draw) as *const ()
+    }
+}
+
+/// Some of a widget's content: a piece of text, and how to draw it.
+///
+/// Create one with [`Text::new()`] and change how it's drawn with the
+/// chainable setters, e.g. `Text::new(attr, text).markup(true)`, rather
+/// than with a struct literal, so that widgets keep building when fields are
+/// added.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Text {
     pub attr: Attributes,
     pub text: String,
     pub stretch: bool,
     pub markup: bool,
+    /// Draw the text greyed-out, e.g. to show that it is stale because its
+    /// data source is temporarily unavailable.
+    pub dim: bool,
+    /// Draw a progress bar instead of the text. The text is still used to
+    /// size the bar's height, so it can usually be left empty.
+    pub progress: Option<ProgressBar>,
+    /// Draw with a closure instead of drawing the text, e.g. for a sparkline.
+    /// As with `progress`, the text is still used to size the height.
+    pub draw: Option<Drawable>,
+    /// Blink the text on and off to draw attention to it, e.g. for a
+    /// critically low battery. All blinking texts blink together.
+    pub blink: bool,
 }
 
 impl Text {
-    pub(crate) fn compute(self, surface: &Surface) -> Result<ComputedText> {
-        let (width, height) = {
-            let context = Context::new(&surface);
-            let layout = create_pango_layout(&context)?;
-            if self.markup {
-                layout.set_markup(&self.text);
-            } else {
-                layout.set_text(&self.text);
+    /// Creates a text showing `text` with `attr`. It is plain text (not
+    /// Pango markup), doesn't stretch, dim or blink, and has no progress bar
+    /// or drawing.
+    pub fn new(attr: Attributes, text: String) -> Text {
+        Text {
+            attr,
+            text,
+            ..Text::default()
+        }
+    }
+
+    /// Sets whether the text stretches to fill the space left in the bar.
+    pub fn stretch(mut self, stretch: bool) -> Self {
+        self.stretch = stretch;
+        self
+    }
+
+    /// Sets whether the text is Pango markup.
+    pub fn markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Sets whether the text is drawn greyed-out, see [`Text::dim`].
+    ///
+    /// [`Text::dim`]: #structfield.dim
+    pub fn dim(mut self, dim: bool) -> Self {
+        self.dim = dim;
+        self
+    }
+
+    /// Draws `progress` instead of the text.
+    pub fn progress(mut self, progress: ProgressBar) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Draws `draw` instead of the text.
+    pub fn draw(mut self, draw: Drawable) -> Self {
+        self.draw = Some(draw);
+        self
+    }
+
+    /// Sets whether the text blinks, see [`Text::blink`].
+    ///
+    /// [`Text::blink`]: #structfield.blink
+    pub fn blink(mut self, blink: bool) -> Self {
+        self.blink = blink;
+        self
+    }
+
+    // Fills in the text's attributes that were left as defaults from
+    // `default`, see `Attributes::inherit()`.
+    pub(crate) fn inherit(mut self, default: &Attributes) -> Self {
+        self.attr = self.attr.inherit(default);
+        self
+    }
+
+    // Lays out the text, ready to be rendered.
+    //
+    // If `layout` is given (e.g. the layout previously used for this widget's
+    // content), it is reused rather than allocating a new one.
+    //
+    // Font sizes and padding are multiplied by `scale`, for HiDPI displays.
+    pub(crate) fn compute(
+        self,
+        surface: &Surface,
+        layout: Option<pango::Layout>,
+        scale: f64,
+    ) -> Result<ComputedText> {
+        let context = Context::new(&surface);
+        let layout = match layout {
+            Some(layout) => {
+                pangocairo::functions::update_layout(&context, &layout);
+                // Undo the size constraints set when it was last rendered.
+                layout.set_width(-1);
+                layout.set_height(-1);
+                layout
             }
-            layout.set_font_description(Some(&self.attr.font.0));
+            None => create_pango_layout(&context)?,
+        };
+        if let Some(pango_context) = layout.get_context() {
+            pangocairo::functions::context_set_resolution(&pango_context, BASE_DPI * scale);
+            layout.context_changed();
+        }
+        if self.markup {
+            layout.set_markup(&self.text);
+        } else {
+            layout.set_text(&self.text);
+        }
+        layout.set_font_description(Some(&self.attr.font.0));
 
-            let padding = &self.attr.padding;
-            let (text_width, text_height) = layout.get_pixel_size();
-            let width = f64::from(text_width) + padding.left + padding.right;
-            let height = f64::from(text_height) + padding.top + padding.bottom;
-            (width, height)
+        let padding = self.attr.padding.scaled(scale);
+        let (text_width, text_height) = layout.get_pixel_size();
+        let text_width = match (&self.progress, &self.draw) {
+            (Some(progress), _) => progress.width * scale,
+            (None, Some(draw)) => draw.width * scale,
+            (None, None) => f64::from(text_width),
         };
+        let width = text_width + padding.left + padding.right;
+        let height = f64::from(text_height) + padding.top + padding.bottom;
 
         Ok(ComputedText {
             attr: self.attr,
@@ -156,6 +716,12 @@ impl Text {
             width,
             height,
             markup: self.markup,
+            dim: self.dim,
+            progress: self.progress,
+            draw: self.draw,
+            blink: self.blink,
+            scale,
+            layout,
         })
     }
 }
@@ -164,7 +730,14 @@ impl Text {
 // having to call the (relatively) expensive .compute().
 impl PartialEq<ComputedText> for Text {
     fn eq(&self, other: &ComputedText) -> bool {
-        self.attr == other.attr && self.text == other.text && self.stretch == other.stretch
+        self.attr == other.attr
+            && self.text == other.text
+            && self.stretch == other.stretch
+            && self.markup == other.markup
+            && self.dim == other.dim
+            && self.progress == other.progress
+            && self.draw == other.draw
+            && self.blink == other.blink
     }
 }
 
@@ -179,41 +752,84 @@ pub(crate) struct ComputedText {
     pub width: f64,
     pub height: f64,
     pub markup: bool,
+    pub dim: bool,
+    pub progress: Option<ProgressBar>,
+    pub draw: Option<Drawable>,
+    pub blink: bool,
+    pub scale: f64,
+    // The layout from `Text::compute()`, kept so that it needn't be
+    // recreated on every render.
+    pub layout: pango::Layout,
 }
 
 impl ComputedText {
-    pub fn render(&self, surface: &Surface) -> Result<()> {
+    // Draws the text. If it blinks, `blink_visible` says whether it's in the
+    // visible half of the blink, otherwise only its background is drawn.
+    pub fn render(
+        &self,
+        surface: &ImageSurface,
+        blink_visible: bool,
+        background: &Background,
+    ) -> Result<()> {
         let context = Context::new(&surface);
-        let layout = create_pango_layout(&context)?;
-        if self.markup {
-            layout.set_markup(&self.text);
-        } else {
-            layout.set_text(&self.text);
+        let layout = &self.layout;
+        pangocairo::functions::update_layout(&context, layout);
+
+        // Texts without their own background show the bar's, which is set
+        // up before translating, as gradients span the whole bar.
+        match &self.attr.bg_color {
+            Some(bg_color) => bg_color.apply_to_context(&context),
+            None => background.apply_to_context(
+                &context,
+                f64::from(surface.get_width()),
+                f64::from(surface.get_height()),
+            ),
         }
-        layout.set_font_description(Some(&self.attr.font.0));
+        // The background replaces what was drawn before, rather than being
+        // drawn over it, so that translucent backgrounds don't build up.
+        context.set_operator(Operator::Source);
+        context.rectangle(self.x, self.y, self.width, self.height);
+        context.fill();
+        context.set_operator(Operator::Over);
 
         context.translate(self.x, self.y);
 
         // Set the width/height on the Pango layout so that it word-wraps/ellipises.
-        let padding = &self.attr.padding;
+        let padding = self.attr.padding.scaled(self.scale);
         let text_width = self.width - padding.left - padding.right;
         let text_height = self.height - padding.top - padding.bottom;
         layout.set_ellipsize(EllipsizeMode::End);
         layout.set_width(text_width as i32 * pango::SCALE);
         layout.set_height(text_height as i32 * pango::SCALE);
 
-        let bg_color = &self.attr.bg_color.clone().unwrap_or_else(Color::black);
-        bg_color.apply_to_context(&context);
-        // FIXME: The use of `height` isnt' right here: we want to do the
-        // full height of the bar, not the full height of the text. It
-        // would be useful if we could do Surface.get_height(), but that
-        // doesn't seem to be available in cairo-rs for some reason?
-        context.rectangle(0.0, 0.0, self.width, self.height);
-        context.fill();
+        if self.blink && !blink_visible {
+            return Ok(());
+        }
 
-        self.attr.fg_color.apply_to_context(&context);
+        if let Some(progress) = &self.progress {
+            context.translate(padding.left, padding.top);
+            progress.render(&context, &self.attr, text_width, text_height, self.scale);
+            return Ok(());
+        }
+
+        if let Some(draw) = &self.draw {
+            context.translate(padding.left, padding.top);
+            context.rectangle(0.0, 0.0, text_width, text_height);
+            context.clip();
+            self.attr.fg_color.apply_to_context(&context);
+            (draw.draw)(&context, text_width, text_height);
+            return Ok(());
+        }
+
+        if self.dim {
+            self.attr
+                .fg_color
+                .apply_to_context_with_alpha(&context, DIM_ALPHA);
+        } else {
+            self.attr.fg_color.apply_to_context(&context);
+        }
         context.translate(padding.left, padding.top);
-        show_pango_layout(&context, &layout);
+        show_pango_layout(&context, layout);
 
         Ok(())
     }
@@ -223,6 +839,8 @@ impl ComputedText {
 pub struct ThresholdValue {
     pub threshold: u8,
     pub color: Color,
+    /// Blink the widget while its value is within this threshold.
+    pub blink: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -238,15 +856,130 @@ impl Default for Threshold {
             low: ThresholdValue {
                 threshold: 40,
                 color: Color::red(),
+                blink: false,
             },
             normal: ThresholdValue {
                 threshold: 60,
                 color: Color::yellow(),
+                blink: false,
             },
             high: ThresholdValue {
                 threshold: 100,
                 color: Color::green(),
+                blink: false,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Attributes, Color, Font, Padding};
+
+    #[test]
+    fn parses_long_forms() {
+        assert_eq!(
+            Color::from_hex("#FF8000").unwrap(),
+            Color::from_rgb(0xff, 0x80, 0x00)
+        );
+        assert_eq!(
+            Color::from_hex("2e3440").unwrap(),
+            Color::from_rgb(0x2e, 0x34, 0x40)
+        );
+        assert_eq!(
+            Color::from_hex("#ff800080").unwrap(),
+            Color::from_rgba(0xff, 0x80, 0x00, 0x80)
+        );
+    }
+
+    #[test]
+    fn parses_short_forms() {
+        assert_eq!(
+            Color::from_hex("#F80").unwrap(),
+            Color::from_rgb(0xff, 0x88, 0x00)
+        );
+        assert_eq!(
+            Color::from_hex("#f808").unwrap(),
+            Color::from_rgba(0xff, 0x88, 0x00, 0x88)
+        );
+        assert_eq!(Color::from_hex("#fff").unwrap(), Color::white());
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for hex in &[
+            "",
+            "#",
+            "#12",
+            "#12345",
+            "#1234567",
+            "#123456789",
+            "#GG0000",
+            "#+12345",
+            "#ééé",
+            " #123",
+        ] {
+            assert!(
+                Color::from_hex(hex).is_err(),
+                "{:?} should be rejected",
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_through_to_hex() {
+        for hex in &[
+            "#000000",
+            "#FFFFFF",
+            "#2E3440",
+            "#88C0D0",
+            "#010203",
+            "#FF800080",
+            "#12345600",
+        ] {
+            assert_eq!(Color::from_hex(hex).unwrap().to_hex(), *hex);
+        }
+        for r in 0..=255 {
+            let color = Color::from_rgba(r, 255 - r, r / 2, r);
+            assert_eq!(Color::from_hex(&color.to_hex()).unwrap(), color);
+        }
+    }
+
+    #[test]
+    fn formats_named_colors() {
+        assert_eq!(Color::red().to_hex(), "#FF0000");
+        assert_eq!(Color::black().with_alpha(0.5).to_hex(), "#00000080");
+    }
+
+    #[test]
+    fn inherits_the_attributes_left_as_defaults() {
+        let default = Attributes {
+            font: Font::new("Sans 12"),
+            fg_color: Color::white(),
+            bg_color: Some(Color::blue()),
+            padding: Padding::new(4.0, 4.0, 0.0, 0.0),
+        };
+        assert_eq!(Attributes::default().inherit(&default), default);
+
+        let warning = Attributes {
+            fg_color: Color::red(),
+            bg_color: None,
+            ..Attributes::default()
+        };
+        assert_eq!(
+            warning.inherit(&default),
+            Attributes {
+                fg_color: Color::red(),
+                bg_color: None,
+                ..default.clone()
+            }
+        );
+
+        let bold = Attributes {
+            font: Font::new("Bold"),
+            ..Attributes::default()
+        };
+        assert_eq!(bold.inherit(&default).font, Font::new("Sans Bold 12"));
+    }
+}
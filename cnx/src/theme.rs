@@ -0,0 +1,114 @@
+//! Color schemes shared between widgets.
+
+use crate::text::{Attributes, Color, Font, Padding};
+
+/// A set of colors and a font for the whole bar.
+///
+/// Rather than configuring each widget's colors individually, create a
+/// `Theme` and take widgets' [`Attributes`] from it:
+///
+/// ```no_run
+/// # use cnx::theme::Theme;
+/// # use cnx::widgets::Clock;
+/// let theme = Theme::nord();
+/// let clock = Clock::new(theme.accent_attr(), None);
+/// ```
+///
+/// [`Cnx::theme()`] also makes [`Theme::attr()`] the default for widgets
+/// created with `Attributes::default()`.
+///
+/// [`Attributes`]: ../text/struct.Attributes.html
+/// [`Cnx::theme()`]: ../struct.Cnx.html#method.theme
+/// [`Theme::attr()`]: #method.attr
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub fg: Color,
+    pub bg: Option<Color>,
+    /// Used for text that needs attention, e.g. a low battery.
+    pub warning: Color,
+    /// Used for text that needs urgent attention, e.g. a critical battery.
+    pub critical: Color,
+    /// Used to highlight text, e.g. the active desktop in the pager.
+    pub accent: Color,
+    pub font: Font,
+    pub padding: Padding,
+}
+
+impl Theme {
+    /// The [Nord](https://www.nordtheme.com/) color scheme.
+    pub fn nord() -> Theme {
+        Theme {
+            fg: Color::from_rgb(0xd8, 0xde, 0xe9),
+            bg: Some(Color::from_rgb(0x2e, 0x34, 0x40)),
+            warning: Color::from_rgb(0xeb, 0xcb, 0x8b),
+            critical: Color::from_rgb(0xbf, 0x61, 0x6a),
+            accent: Color::from_rgb(0x88, 0xc0, 0xd0),
+            font: Font::new("Sans 12"),
+            padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The [Gruvbox](https://github.com/morhetz/gruvbox) (dark) color scheme.
+    pub fn gruvbox() -> Theme {
+        Theme {
+            fg: Color::from_rgb(0xeb, 0xdb, 0xb2),
+            bg: Some(Color::from_rgb(0x28, 0x28, 0x28)),
+            warning: Color::from_rgb(0xfa, 0xbd, 0x2f),
+            critical: Color::from_rgb(0xfb, 0x49, 0x34),
+            accent: Color::from_rgb(0x83, 0xa5, 0x98),
+            font: Font::new("Sans 12"),
+            padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Uses `font` rather than the theme's default font.
+    pub fn font(mut self, font: Font) -> Theme {
+        self.font = font;
+        self
+    }
+
+    /// Uses `padding` rather than the theme's default padding.
+    pub fn padding(mut self, padding: Padding) -> Theme {
+        self.padding = padding;
+        self
+    }
+
+    fn attr_with_fg(&self, fg: &Color) -> Attributes {
+        Attributes {
+            font: self.font.clone(),
+            fg_color: fg.clone(),
+            bg_color: self.bg.clone(),
+            padding: self.padding.clone(),
+        }
+    }
+
+    /// Attributes for normal text.
+    pub fn attr(&self) -> Attributes {
+        self.attr_with_fg(&self.fg)
+    }
+
+    /// Attributes for text that needs attention.
+    pub fn warning_attr(&self) -> Attributes {
+        self.attr_with_fg(&self.warning)
+    }
+
+    /// Attributes for text that needs urgent attention.
+    pub fn critical_attr(&self) -> Attributes {
+        self.attr_with_fg(&self.critical)
+    }
+
+    /// Attributes for highlighted text.
+    pub fn accent_attr(&self) -> Attributes {
+        self.attr_with_fg(&self.accent)
+    }
+
+    /// Attributes for highlighted text drawn on the accent color, e.g. the
+    /// active desktop in the pager.
+    pub fn accent_bg_attr(&self) -> Attributes {
+        Attributes {
+            bg_color: Some(self.accent.clone()),
+            fg_color: self.bg.clone().unwrap_or_else(Color::black),
+            ..self.attr()
+        }
+    }
+}
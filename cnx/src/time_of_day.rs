@@ -0,0 +1,250 @@
+//! Whether it is currently day or night at a location.
+//!
+//! This is useful for switching colors at sunrise and sunset. Widgets'
+//! attributes are fixed when they're created, so a widget that wants to
+//! change its colors can merge [`time_of_day_stream()`] into its own stream
+//! and pick its attributes from the latest value, e.g.:
+//!
+//! ```no_run
+//! # use cnx::text::{Attributes, Color};
+//! # use cnx::time_of_day::{time_of_day_stream, TimeOfDay};
+//! # use tokio_stream::StreamExt;
+//! let day = Attributes::builder().fg(Color::black()).bg(Color::white()).build();
+//! let night = Attributes::builder().fg(Color::white()).build();
+//! let attrs = time_of_day_stream(51.5, -0.13).map(move |time_of_day| match time_of_day {
+//!     TimeOfDay::Day => day.clone(),
+//!     TimeOfDay::Night => night.clone(),
+//! });
+//! ```
+//!
+//! Sunrise and sunset are calculated with the [sunrise equation], which is
+//! accurate to within a minute or two away from the poles.
+//!
+//! [sunrise equation]: https://en.wikipedia.org/wiki/Sunrise_equation
+
+use async_stream::stream;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, TimeZone, Utc};
+use futures::Stream;
+use std::f64::consts::PI;
+use std::time::Duration;
+use tokio::time;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeOfDay {
+    Day,
+    Night,
+}
+
+/// The times of sunrise and sunset on a day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SunTimes {
+    /// The sun rises and sets at these times.
+    RiseAndSet(DateTime<Utc>, DateTime<Utc>),
+    /// The sun doesn't set (polar day).
+    AlwaysUp,
+    /// The sun doesn't rise (polar night).
+    AlwaysDown,
+}
+
+// Julian day of 2000-01-01 12:00 UTC.
+const J2000: f64 = 2_451_545.0;
+// Julian day of the Unix epoch.
+const UNIX_EPOCH_JULIAN: f64 = 2_440_587.5;
+
+fn sin_deg(degrees: f64) -> f64 {
+    (degrees * PI / 180.0).sin()
+}
+
+fn julian_to_utc(julian: f64) -> DateTime<Utc> {
+    let millis = ((julian - UNIX_EPOCH_JULIAN) * 86_400_000.0).round() as i64;
+    Utc.timestamp_millis(millis)
+}
+
+/// Calculates sunrise and sunset on `date` at the given location.
+///
+/// `latitude` is in degrees north and `longitude` in degrees east.
+pub fn sun_times(date: NaiveDate, latitude: f64, longitude: f64) -> SunTimes {
+    let days = (date - NaiveDate::from_ymd(2000, 1, 1)).num_days() as f64 + 0.0008;
+    let mean_noon = days - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_noon).rem_euclid(360.0);
+    let center = 1.9148 * sin_deg(anomaly)
+        + 0.0200 * sin_deg(2.0 * anomaly)
+        + 0.0003 * sin_deg(3.0 * anomaly);
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let transit =
+        J2000 + mean_noon + 0.0053 * sin_deg(anomaly) - 0.0069 * sin_deg(2.0 * ecliptic_longitude);
+
+    let sin_declination = sin_deg(ecliptic_longitude) * sin_deg(23.44);
+    let cos_declination = (1.0 - sin_declination * sin_declination).sqrt();
+    let latitude = latitude * PI / 180.0;
+    // -0.83° accounts for refraction and the size of the sun's disc.
+    let cos_hour_angle = (sin_deg(-0.83) - latitude.sin() * sin_declination)
+        / (latitude.cos() * cos_declination);
+
+    if cos_hour_angle < -1.0 {
+        SunTimes::AlwaysUp
+    } else if cos_hour_angle > 1.0 {
+        SunTimes::AlwaysDown
+    } else {
+        let hour_angle = cos_hour_angle.acos() * 180.0 / PI;
+        SunTimes::RiseAndSet(
+            julian_to_utc(transit - hour_angle / 360.0),
+            julian_to_utc(transit + hour_angle / 360.0),
+        )
+    }
+}
+
+// The sun times for the days around `now`. A day's times are calculated
+// around solar noon, which can fall on the previous or next UTC day far from
+// the prime meridian, so we check either side.
+fn nearby_sun_times(now: DateTime<Utc>, latitude: f64, longitude: f64) -> Vec<SunTimes> {
+    let today = now.date().naive_utc();
+    (-1..=2)
+        .map(|offset| sun_times(today + ChronoDuration::days(offset), latitude, longitude))
+        .collect()
+}
+
+/// Whether it is day or night at `now` at the given location.
+pub fn time_of_day(now: DateTime<Utc>, latitude: f64, longitude: f64) -> TimeOfDay {
+    let today = now.date().naive_utc();
+    let is_day = nearby_sun_times(now, latitude, longitude)
+        .into_iter()
+        .any(|times| match times {
+            SunTimes::RiseAndSet(rise, set) => rise <= now && now < set,
+            _ => false,
+        });
+    if is_day {
+        return TimeOfDay::Day;
+    }
+
+    match sun_times(today, latitude, longitude) {
+        SunTimes::AlwaysUp => TimeOfDay::Day,
+        _ => TimeOfDay::Night,
+    }
+}
+
+// The next sunrise or sunset after `now`, if there is one in the next couple
+// of days.
+fn next_transition(now: DateTime<Utc>, latitude: f64, longitude: f64) -> Option<DateTime<Utc>> {
+    nearby_sun_times(now, latitude, longitude)
+        .into_iter()
+        .flat_map(|times| match times {
+            SunTimes::RiseAndSet(rise, set) => vec![rise, set],
+            _ => vec![],
+        })
+        .filter(|time| *time > now)
+        .min()
+}
+
+// The longest we'll sleep before re-checking. This keeps the stream correct
+// if the system clock changes or the machine is suspended.
+const MAX_SLEEP: Duration = Duration::from_secs(60 * 60);
+
+/// Returns a stream that yields whether it is day or night at the given
+/// location immediately, and then again at each sunrise and sunset.
+pub fn time_of_day_stream(latitude: f64, longitude: f64) -> impl Stream<Item = TimeOfDay> {
+    stream! {
+        let mut last = None;
+        loop {
+            let now = Utc::now();
+            let current = time_of_day(now, latitude, longitude);
+            if last != Some(current) {
+                last = Some(current);
+                yield current;
+            }
+
+            let sleep = next_transition(now, latitude, longitude)
+                .and_then(|next| (next - now).to_std().ok())
+                .map_or(MAX_SLEEP, |until| until.min(MAX_SLEEP));
+            // Wake just after the transition, so that it has definitely passed.
+            time::sleep(sleep + Duration::from_secs(1)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{next_transition, sun_times, time_of_day, SunTimes, TimeOfDay};
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+    // Tromsø is north of the Arctic Circle, so has a polar day and night.
+    const TROMSO: (f64, f64) = (69.6492, 18.9553);
+
+    fn at(day: u32, month: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.ymd(2021, month, day).and_hms(hour, minute, 0)
+    }
+
+    // The sunrise equation is only accurate to a minute or two.
+    fn assert_near(time: DateTime<Utc>, expected: DateTime<Utc>) {
+        let minutes = (time - expected).num_minutes().abs();
+        assert!(minutes <= 3, "{} is not near {}", time, expected);
+    }
+
+    #[test]
+    fn rises_and_sets_in_london_on_the_equinox() {
+        let (latitude, longitude) = LONDON;
+        match sun_times(NaiveDate::from_ymd(2021, 3, 20), latitude, longitude) {
+            SunTimes::RiseAndSet(rise, set) => {
+                assert_near(rise, at(20, 3, 6, 3));
+                assert_near(set, at(20, 3, 18, 15));
+            }
+            times => panic!("Expected a sunrise and sunset, got {:?}", times),
+        }
+    }
+
+    #[test]
+    fn is_day_between_sunrise_and_sunset() {
+        let (latitude, longitude) = LONDON;
+        let times = [
+            (0, 0, TimeOfDay::Night),
+            (5, 55, TimeOfDay::Night),
+            (6, 15, TimeOfDay::Day),
+            (12, 0, TimeOfDay::Day),
+            (18, 5, TimeOfDay::Day),
+            (18, 25, TimeOfDay::Night),
+        ];
+        for &(hour, minute, expected) in &times {
+            let now = at(20, 3, hour, minute);
+            assert_eq!(
+                time_of_day(now, latitude, longitude),
+                expected,
+                "at {}",
+                now
+            );
+        }
+    }
+
+    #[test]
+    fn next_transition_is_the_next_sunrise_or_sunset() {
+        let (latitude, longitude) = LONDON;
+        let sunset = next_transition(at(20, 3, 12, 0), latitude, longitude).unwrap();
+        assert_near(sunset, at(20, 3, 18, 15));
+        let sunrise = next_transition(at(20, 3, 20, 0), latitude, longitude).unwrap();
+        assert_near(sunrise, at(21, 3, 6, 1));
+    }
+
+    #[test]
+    fn is_always_day_in_a_polar_day() {
+        let (latitude, longitude) = TROMSO;
+        assert_eq!(
+            sun_times(NaiveDate::from_ymd(2021, 6, 21), latitude, longitude),
+            SunTimes::AlwaysUp
+        );
+        let midnight = at(21, 6, 0, 0);
+        assert_eq!(time_of_day(midnight, latitude, longitude), TimeOfDay::Day);
+        assert_eq!(next_transition(midnight, latitude, longitude), None);
+    }
+
+    #[test]
+    fn is_always_night_in_a_polar_night() {
+        let (latitude, longitude) = TROMSO;
+        assert_eq!(
+            sun_times(NaiveDate::from_ymd(2021, 12, 21), latitude, longitude),
+            SunTimes::AlwaysDown
+        );
+        let noon = at(21, 12, 12, 0);
+        assert_eq!(time_of_day(noon, latitude, longitude), TimeOfDay::Night);
+        assert_eq!(next_transition(noon, latitude, longitude), None);
+    }
+}
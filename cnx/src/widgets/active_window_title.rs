@@ -1,13 +1,13 @@
 use anyhow::{Context, Result};
-use futures::Stream;
-use futures::stream::StreamExt;
+use futures::{Stream, StreamExt};
 use xcb_util::ewmh;
 
-use crate::text::{Attributes, Text};
-use crate::xcb::xcb_properties_stream;
+use crate::text::{truncate_to_width, Attributes, Text};
 
+use super::crawl::{scroll_step, CrawlState};
+use super::ewmh_widget::on_property_change;
+use super::{interval_and_events, CrawlOptions, Trigger, WindowState};
 use super::{WidgetStreamI, WidgetStream};
-use async_stream::stream;
 
 /// Shows the title of the currently focused window.
 ///
@@ -15,43 +15,176 @@ use async_stream::stream;
 /// currently focused window. It uses the `_NET_ACTIVE_WINDOW` [`EWMH`] property
 /// of the root window to determine which window is currently focused.
 ///
+/// With WMs that don't support these properties, a warning is logged at
+/// startup and the widget falls back to the [`ICCCM`] `WM_NAME` of the window
+/// with the input focus. As focus changes can't be watched for this way, the
+/// title is then only updated when the focused window's title changes.
+///
 /// The widgets content stretches to fill all available space. If the title is
-/// too large for the available space, it will be truncated.
+/// too large for the available space, it will be truncated. It can also be
+/// truncated to a fixed length, or scroll within a fixed length, see
+/// [`TitleOptions`].
 ///
 /// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+/// [`ICCCM`]: https://tronche.com/gui/x/icccm/
+#[derive(Clone)]
 pub struct ActiveWindowTitle {
     attr: Attributes,
+    sticky_marker: Option<String>,
+    truncation: Truncation,
+    // The number of characters to scroll long titles within, if they scroll.
+    scroll: Option<usize>,
+}
+
+/// Options controlling how an [`ActiveWindowTitle`] shows titles.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TitleOptions {
+    /// How long titles are shortened, by default only when there isn't
+    /// room for them in the bar.
+    pub truncation: Truncation,
+    /// Scroll titles longer than [`Truncation::Chars`] horizontally, like a
+    /// [`Crawl`] with the default [`CrawlOptions`], rather than truncating
+    /// them with "…". This has no effect with other truncations.
+    ///
+    /// [`Crawl`]: super::Crawl
+    pub scroll: bool,
+    /// A marker to prefix the title with (e.g. `"📌 "`) while the window is
+    /// shown on all desktops.
+    pub sticky_marker: Option<String>,
+}
+
+/// How an [`ActiveWindowTitle`] shortens long titles. Shortened titles end
+/// with "…".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Truncation {
+    /// Titles are only truncated when there isn't room for them in the bar.
+    None,
+    /// Titles are truncated to at most this many characters.
+    Chars(usize),
+    /// Titles are truncated to at most this many pixels wide, measured with
+    /// the widget's font. This is more precise than [`Truncation::Chars`]
+    /// with proportional fonts. The width excludes padding and scales with
+    /// [`Cnx::scale_factor()`].
+    ///
+    /// Titles are truncated before the bar fills in default attributes, so
+    /// the widget should be given its own font's family and size (rather
+    /// than those of [`Attributes::default()`]) to measure them accurately.
+    ///
+    /// [`Cnx::scale_factor()`]: crate::Cnx::scale_factor
+    /// [`Attributes::default()`]: crate::text::Attributes#impl-Default
+    Pixels(f64),
+}
+
+impl Default for Truncation {
+    fn default() -> Self {
+        Truncation::None
+    }
+}
+
+impl Truncation {
+    fn apply(self, title: String, attr: &Attributes) -> Result<String> {
+        match self {
+            Truncation::Chars(max) if title.chars().count() > max => {
+                let kept = title.chars().take(max.saturating_sub(1));
+                Ok(kept.chain(Some('…')).collect())
+            }
+            Truncation::Pixels(width) => truncate_to_width(&title, &attr.font, width),
+            _ => Ok(title),
+        }
+    }
 }
 
 impl ActiveWindowTitle {
     /// Creates a new Active Window Title widget.
     pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_options(attr, TitleOptions::default())
+    }
+
+    /// Creates a new Active Window Title widget that shows titles as
+    /// described by `options`, e.g. scrolling titles longer than 40
+    /// characters:
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::{ActiveWindowTitle, TitleOptions, Truncation};
+    /// let title = ActiveWindowTitle::new_with_options(
+    ///     Attributes::default(),
+    ///     TitleOptions {
+    ///         truncation: Truncation::Chars(40),
+    ///         scroll: true,
+    ///         ..TitleOptions::default()
+    ///     },
+    /// );
+    /// ```
+    pub fn new_with_options(
+        attr: Attributes,
+        options: TitleOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        let (truncation, scroll) = match options.truncation {
+            Truncation::Chars(max) if options.scroll => (Truncation::None, Some(max)),
+            truncation => (truncation, None),
+        };
         WidgetStream::new(
             Self {
-                attr
+                attr,
+                sticky_marker: options.sticky_marker,
+                truncation,
+                scroll,
             },
             Self::into_stream
         )
+        .restart_on_end()
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        let properties = &["_NET_ACTIVE_WINDOW", "_NET_WM_NAME"];
+        let mut properties = vec!["_NET_ACTIVE_WINDOW", "_NET_WM_NAME", "WM_NAME"];
+        if self.sticky_marker.is_some() {
+            properties.extend(&["_NET_WM_STATE", "_NET_WM_DESKTOP"]);
+        }
         let screen_idx = 0; // XXX assume
-        let mut stream =
-            xcb_properties_stream(properties).context("Initialising ActiveWindowtitle")?;
-        let mapped = stream! {
-            while let Some(v) = stream.next().await {
-                yield Ok(self.on_change(stream.conn(), screen_idx));
+        let attr = self.attr.clone();
+        let scroll = self.scroll;
+        let titles = on_property_change("ActiveWindowTitle", &properties, move |conn| {
+            self.on_change(conn, screen_idx)
+        })
+            .context("Initialising ActiveWindowtitle")?;
+
+        let width = match scroll {
+            Some(width) => width,
+            None => {
+                let stream = titles.map(move |title| Ok(vec![title_text(&attr, title?)]));
+                return Ok(stream.left_stream());
             }
         };
-
-        Ok(mapped)
+        // Long titles are scrolled on a timer, starting again from the
+        // beginning whenever the title changes.
+        let options = CrawlOptions::default();
+        let step = scroll_step("ActiveWindowTitle", options.speed);
+        let mut state = CrawlState::default();
+        let stream = interval_and_events(step, titles).map(move |trigger| {
+            let title = match trigger {
+                Trigger::Tick => {
+                    let shown = state.window(width);
+                    state.advance(width);
+                    shown
+                }
+                Trigger::Event(title) => {
+                    state.set_text(title?, &options.separator);
+                    state.window(width)
+                }
+            };
+            Ok(vec![title_text(&attr, title)])
+        });
+        Ok(stream.right_stream())
     }
 
-    fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Vec<Text> {
+    fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Result<String> {
         let title = ewmh::get_active_window(conn, screen_idx)
             .get_reply()
-            .and_then(|active_window| {
+            .ok()
+            .filter(|&window| window != xcb::NONE)
+            .or_else(|| focused_window(conn))
+            .map(|active_window| {
                 // xcb_properties_stream() will only register for notifications on the
                 // root window, so will only receive notifications when the active window
                 // changes. So, for each active window we see, register for property
@@ -62,16 +195,108 @@ impl ActiveWindowTitle {
                 xcb::change_window_attributes(conn, active_window, &attributes);
                 conn.flush();
 
-                ewmh::get_wm_name(conn, active_window).get_reply()
+                match &self.sticky_marker {
+                    Some(marker) if WindowState::of(conn, active_window).sticky => {
+                        format!("{}{}", marker, window_title(conn, active_window))
+                    }
+                    _ => window_title(conn, active_window),
+                }
             })
-            .map(|reply| reply.string().to_owned())
-            .unwrap_or_else(|_| "".to_owned());
-
-        vec![Text {
-            attr: self.attr.clone(),
-            text: title,
-            stretch: true,
-            markup: false,
-        }]
+            .unwrap_or_default();
+        self.truncation.apply(title, &self.attr)
+    }
+}
+
+fn title_text(attr: &Attributes, title: String) -> Text {
+    Text::new(attr.clone(), title).stretch(true)
+}
+
+// The window with the input focus, for WMs that don't set
+// `_NET_ACTIVE_WINDOW`.
+fn focused_window(conn: &ewmh::Connection) -> Option<xcb::Window> {
+    let focus = xcb::get_input_focus(conn).get_reply().ok()?.focus();
+    if focus == xcb::NONE || focus == xcb::INPUT_FOCUS_POINTER_ROOT {
+        None
+    } else {
+        Some(focus)
+    }
+}
+
+// The window's `_NET_WM_NAME`, falling back to the ICCCM `WM_NAME`.
+//
+// Titles come from other clients, which may set anything, so they're read as
+// raw bytes and sanitized rather than trusted to be valid UTF-8.
+fn window_title(conn: &ewmh::Connection, window: xcb::Window) -> String {
+    text_property(conn, window, conn.WM_NAME())
+        .or_else(|| text_property(conn, window, xcb::ATOM_WM_NAME))
+        .unwrap_or_default()
+}
+
+// Reads a text property of `window`, or `None` if it is unset or empty.
+fn text_property(
+    conn: &ewmh::Connection,
+    window: xcb::Window,
+    property: xcb::Atom,
+) -> Option<String> {
+    let reply = xcb::get_property(conn, false, window, property, xcb::ATOM_ANY, 0, u32::MAX)
+        .get_reply()
+        .ok()?;
+    let bytes: &[u8] = reply.value();
+    if bytes.is_empty() {
+        return None;
+    }
+    Some(decode_title(bytes, reply.type_() == xcb::ATOM_STRING))
+}
+
+// Decodes a title, as Latin-1 if it has the ICCCM `STRING` type and as UTF-8
+// otherwise, replacing invalid sequences. Line breaks and tabs become spaces
+// and other control characters (e.g. NULs or escape sequences) are dropped,
+// as Pango would otherwise draw them as boxes or break the line.
+fn decode_title(bytes: &[u8], latin1: bool) -> String {
+    let title = if latin1 {
+        bytes.iter().map(|&byte| char::from(byte)).collect()
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    let sanitized: String = title
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+    sanitized.trim().to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode_title;
+
+    #[test]
+    fn passes_through_ordinary_titles() {
+        assert_eq!(decode_title("Inbox — Mail".as_bytes(), false), "Inbox — Mail");
+        assert_eq!(decode_title("日本語のタイトル".as_bytes(), false), "日本語のタイトル");
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        assert_eq!(decode_title(b"caf\xe9 au lait", false), "caf\u{fffd} au lait");
+        assert_eq!(decode_title(b"\xff\xfeTitle", false), "\u{fffd}\u{fffd}Title");
+        assert_eq!(decode_title(b"truncated \xe2\x82", false), "truncated \u{fffd}");
+    }
+
+    #[test]
+    fn decodes_latin1_strings() {
+        assert_eq!(decode_title(b"caf\xe9 au lait", true), "café au lait");
+    }
+
+    #[test]
+    fn removes_control_characters() {
+        assert_eq!(decode_title(b"vim\0 - file.rs\0", false), "vim - file.rs");
+        assert_eq!(decode_title(b"line one\nline two\r\n", false), "line one line two");
+        assert_eq!(decode_title(b"a\tb", false), "a b");
+        assert_eq!(decode_title(b"\x1b[31mred\x1b[0m", false), "[31mred[0m");
+        assert_eq!(decode_title(b"\x07\x08\x7f", false), "");
     }
 }
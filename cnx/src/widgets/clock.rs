@@ -1,9 +1,11 @@
 use anyhow::Result;
+use async_stream::stream;
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
 use futures::Stream;
 use std::time::Duration;
 use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
 
 use crate::text::{Attributes, Text};
 
@@ -13,45 +15,198 @@ use super::{WidgetStreamI, WidgetStream};
 ///
 /// This widget shows the current time and date, in the form `%Y-%m-%d %a %I:%M
 /// %p`, e.g. `2017-09-01 Fri 12:51 PM`.
+///
+/// The time can also be split into several segments, each with its own format
+/// and [`Attributes`], e.g. to show the date dimmer than the time. See
+/// [`Clock::new_with_segments()`].
+///
+/// The clock updates just after each minute starts, or each second if any
+/// format shows seconds (e.g. with `%S` or `%T`).
+///
+/// The time is local by default, but can be shown in any timezone, e.g. to
+/// show the time in another city alongside a local clock. See
+/// [`Clock::new_with_tz()`].
 pub struct Clock {
-    attr: Attributes,
-    format_str: Option<String>,
+    // The format and attributes of each segment.
+    segments: Vec<(String, Attributes)>,
+    // The timezone to show the time in, or `None` for local time.
+    tz: Option<Tz>,
 }
 
+const DEFAULT_FORMAT: &str = "%Y-%m-%d %a %I:%M %p";
+
 impl Clock {
     // Creates a new Clock widget.
     pub fn new(attr: Attributes, format_str: Option<String>) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
-        WidgetStream::new(
-            Self {
-                attr, format_str
-            },
-            Self::into_stream
-        )
+        let format_str = format_str.unwrap_or_else(|| DEFAULT_FORMAT.to_owned());
+        Self::new_with_segments(vec![(format_str, attr)], None)
+    }
+
+    /// Creates a new Clock widget that shows the time in `tz`, or in local
+    /// time if it's `None`, e.g. UTC and the time in Tokyo:
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::Clock;
+    /// let utc = Clock::new_with_tz(
+    ///     Attributes::default(),
+    ///     Some("UTC %H:%M".to_owned()),
+    ///     Some(chrono_tz::UTC),
+    /// );
+    /// let tokyo = Clock::new_with_tz(
+    ///     Attributes::default(),
+    ///     Some("Tokyo %H:%M".to_owned()),
+    ///     Some(chrono_tz::Asia::Tokyo),
+    /// );
+    /// ```
+    ///
+    /// Timezones come from the IANA database bundled with [`chrono_tz`], so
+    /// daylight saving time is followed, rather than using a fixed offset.
+    /// `%Z` formats as the zone's abbreviation, e.g. `BST`.
+    pub fn new_with_tz(
+        attr: Attributes,
+        format_str: Option<String>,
+        tz: Option<Tz>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        let format_str = format_str.unwrap_or_else(|| DEFAULT_FORMAT.to_owned());
+        Self::new_with_segments(vec![(format_str, attr)], tz)
+    }
+
+    /// Creates a new Clock widget that shows a [`Text`] for each
+    /// `(format, Attributes)` pair, in order, with the time in `tz` as for
+    /// [`Clock::new_with_tz()`], e.g. the local date in grey followed by the
+    /// local time in white:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Color};
+    /// # use cnx::widgets::Clock;
+    /// let date = Attributes {
+    ///     fg_color: Color::from_rgb(0x80, 0x80, 0x80),
+    ///     ..Attributes::default()
+    /// };
+    /// let time = Attributes {
+    ///     fg_color: Color::white(),
+    ///     ..Attributes::default()
+    /// };
+    /// let clock = Clock::new_with_segments(
+    ///     vec![("%a %d %b".to_owned(), date), ("%H:%M".to_owned(), time)],
+    ///     None,
+    /// );
+    /// ```
+    ///
+    /// Formats are as for [`chrono::format::strftime`].
+    pub fn new_with_segments(
+        segments: Vec<(String, Attributes)>,
+        tz: Option<Tz>,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(Self { segments, tz }, Self::into_stream)
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
-        // As we're not showing seconds, we can sleep for however long
-        // it takes until the minutes changes between updates.
-        let one_minute = Duration::from_secs(60);
-        let interval = time::interval(one_minute);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+        // Unless we're showing seconds, we can sleep until the minute
+        // changes between updates. The sleep is worked out from the wall
+        // clock each time, rather than using a fixed interval, so that
+        // updates don't drift away from when the time changes.
+        let seconds = self
+            .segments
+            .iter()
+            .any(|(format, _)| shows_seconds(format));
+        let period = if seconds { 1 } else { 60 };
+        let stream = stream! {
+            loop {
+                yield Ok(self.tick());
+                time::sleep(until_next(Utc::now(), period)).await;
+            }
+        };
 
-        return Ok(stream)
+        Ok(stream)
     }
 
     fn tick(&self) -> Vec<Text> {
-        let now = chrono::Local::now();
-        let format_time: String = self
-            .format_str
-            .clone()
-            .map_or("%Y-%m-%d %a %I:%M %p".to_string(), |item| item);
-        let text = now.format(&format_time).to_string();
-        let texts = vec![Text {
-            attr: self.attr.clone(),
-            text,
-            stretch: false,
-            markup: true,
-        }];
-        texts
+        let now = Utc::now();
+        self.segments
+            .iter()
+            .map(|(format_time, attr)| {
+                let text = match self.tz {
+                    Some(tz) => now.with_timezone(&tz).format(format_time).to_string(),
+                    None => now.with_timezone(&Local).format(format_time).to_string(),
+                };
+                Text::new(attr.clone(), text).markup(true)
+            })
+            .collect()
+    }
+}
+
+// Whether `format` shows seconds, including through specifiers that expand to
+// them, such as `%T` or `%+`.
+fn shows_seconds(format: &str) -> bool {
+    StrftimeItems::new(format).any(|item| {
+        matches!(
+            item,
+            Item::Numeric(Numeric::Second, _)
+                | Item::Numeric(Numeric::Timestamp, _)
+                | Item::Fixed(Fixed::RFC2822)
+                | Item::Fixed(Fixed::RFC3339)
+        )
+    })
+}
+
+// How long it is from `now` until the wall clock next reaches a whole
+// multiple of `period` seconds, e.g. the start of the next minute.
+fn until_next(now: DateTime<Utc>, period: i64) -> Duration {
+    let secs = now.timestamp().rem_euclid(period) as u64;
+    // Leap seconds are represented with over a second of nanoseconds.
+    let nanos = now.timestamp_subsec_nanos().min(999_999_999);
+    Duration::from_secs(period as u64) - Duration::new(secs, nanos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{shows_seconds, until_next, DEFAULT_FORMAT};
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn detects_formats_showing_seconds() {
+        assert!(shows_seconds("%H:%M:%S"));
+        assert!(shows_seconds("%T"));
+        assert!(shows_seconds("%+"));
+        assert!(shows_seconds("%c"));
+        assert!(shows_seconds("%s"));
+    }
+
+    #[test]
+    fn ignores_formats_without_seconds() {
+        assert!(!shows_seconds("%H:%M"));
+        assert!(!shows_seconds("%R"));
+        assert!(!shows_seconds(DEFAULT_FORMAT));
+        assert!(!shows_seconds("100%% %M"));
+    }
+
+    #[test]
+    fn sleeps_until_the_next_minute() {
+        let now = Utc.ymd(2024, 3, 1).and_hms_milli(12, 30, 15, 250);
+        assert_eq!(until_next(now, 60), Duration::from_millis(44_750));
+    }
+
+    #[test]
+    fn sleeps_until_the_next_second() {
+        let now = Utc.ymd(2024, 3, 1).and_hms_milli(12, 30, 15, 250);
+        assert_eq!(until_next(now, 1), Duration::from_millis(750));
+    }
+
+    #[test]
+    fn sleeps_a_whole_period_on_the_boundary() {
+        let now = Utc.ymd(2024, 3, 1).and_hms(12, 30, 0);
+        assert_eq!(until_next(now, 60), Duration::from_secs(60));
+        assert_eq!(until_next(now, 1), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn wakes_just_after_a_leap_second() {
+        let now = Utc
+            .ymd(2016, 12, 31)
+            .and_hms_nano(23, 59, 59, 1_500_000_000);
+        assert_eq!(until_next(now, 60), Duration::from_nanos(1));
     }
 }
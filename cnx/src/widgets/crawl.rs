@@ -0,0 +1,160 @@
+use anyhow::Result;
+use futures::stream::{self, Stream, StreamExt};
+use std::iter;
+use std::time::Duration;
+
+use crate::text::{Attributes, Text};
+
+use super::{clamp_interval, interval_and_events, Trigger, WidgetStream, WidgetStreamI};
+
+/// Options controlling how a [`Crawl`] scrolls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrawlOptions {
+    /// How many characters are shown at once.
+    pub width: usize,
+    /// How many characters to scroll by each second.
+    pub speed: f64,
+    /// Shown between the end of the text and its start as it wraps around.
+    pub separator: String,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            width: 40,
+            speed: 4.0,
+            separator: "   ·   ".to_owned(),
+        }
+    }
+}
+
+// The slowest and fastest a crawl can scroll, in characters per second. The
+// bar redraws on each step, so this keeps a typo from hogging the CPU.
+const MIN_SPEED: f64 = 0.1;
+const MIN_STEP: Duration = Duration::from_millis(50);
+
+// How long to show each position of text scrolling at `speed` characters
+// per second.
+pub(super) fn scroll_step(widget: &str, speed: f64) -> Duration {
+    let step = Duration::from_secs_f64(1.0 / speed.max(MIN_SPEED));
+    clamp_interval(widget, step, MIN_STEP)
+}
+
+// Which part of the text is shown. The text is scrolled one character at a
+// time, wrapping around through the separator back to its start.
+#[derive(Debug, Default)]
+pub(super) struct CrawlState {
+    text: String,
+    // The text followed by the separator, which is what scrolls past.
+    looped: Vec<char>,
+    offset: usize,
+}
+
+impl CrawlState {
+    // Replaces the text, starting again from its beginning if it changed.
+    pub(super) fn set_text(&mut self, text: String, separator: &str) {
+        if text == self.text {
+            return;
+        }
+        self.looped = text.chars().chain(separator.chars()).collect();
+        self.text = text;
+        self.offset = 0;
+    }
+
+    fn scrolls(&self, width: usize) -> bool {
+        self.text.chars().count() > width
+    }
+
+    // The `width` characters currently shown, or the whole text if it fits.
+    pub(super) fn window(&self, width: usize) -> String {
+        if !self.scrolls(width) {
+            return self.text.clone();
+        }
+        self.looped
+            .iter()
+            .cycle()
+            .skip(self.offset)
+            .take(width)
+            .collect()
+    }
+
+    pub(super) fn advance(&mut self, width: usize) {
+        if self.scrolls(width) {
+            self.offset = (self.offset + 1) % self.looped.len();
+        }
+    }
+}
+
+/// Continuously scrolls text horizontally within a fixed width, like a news
+/// ticker, wrapping around from the end of the text to its start.
+///
+/// This is for long text that shouldn't be truncated, such as headlines or a
+/// long status message. Text that fits within [`CrawlOptions::width`] is
+/// shown as it is, without scrolling. The width is counted in characters, so
+/// the widget keeps a steady size with a monospace font.
+///
+/// The text is shown without Pango markup, as it is cut at arbitrary
+/// characters.
+pub struct Crawl<S> {
+    attr: Attributes,
+    options: CrawlOptions,
+    texts: S,
+}
+
+impl Crawl<stream::Iter<iter::Once<String>>> {
+    /// Creates a new `Crawl` widget that scrolls `text`.
+    pub fn new(
+        attr: Attributes,
+        text: String,
+        options: CrawlOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::from_stream(attr, stream::iter(iter::once(text)), options)
+    }
+}
+
+impl<S: Stream<Item = String> + 'static> Crawl<S> {
+    /// Creates a new `Crawl` widget that scrolls the latest text yielded by
+    /// `texts`, e.g. a status that changes occasionally. The crawl starts
+    /// again from the beginning each time the text changes.
+    pub fn from_stream(
+        attr: Attributes,
+        texts: S,
+        options: CrawlOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self {
+                attr,
+                options,
+                texts,
+            },
+            Self::into_stream,
+        )
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let step = scroll_step("Crawl", self.options.speed);
+        let Crawl {
+            attr,
+            options,
+            texts,
+        } = self;
+
+        let mut state = CrawlState::default();
+        let stream = interval_and_events(step, texts).map(move |trigger| {
+            let text = match trigger {
+                Trigger::Tick => {
+                    let shown = state.window(options.width);
+                    state.advance(options.width);
+                    shown
+                }
+                Trigger::Event(text) => {
+                    state.set_text(text, &options.separator);
+                    state.window(options.width)
+                }
+            };
+            Ok(vec![Text::new(attr.clone(), text)])
+        });
+
+        Ok(stream)
+    }
+}
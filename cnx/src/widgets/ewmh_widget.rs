@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use futures::stream::StreamExt;
+use futures::Stream;
+use std::rc::Rc;
+use xcb_util::ewmh;
+
+use crate::text::Text;
+use crate::xcb::xcb_properties_stream;
+
+use super::{WidgetStream, WidgetStreamI};
+use async_stream::stream;
+
+/// A widget that re-renders whenever any of a list of root window properties
+/// changes.
+///
+/// Many [`EWMH`] widgets differ only in which properties they watch and how
+/// they render them. This does the watching, so that such a widget is just a
+/// closure. It is rendered once when the bar starts and then after each
+/// change, e.g. to show the number of desktops:
+///
+/// ```no_run
+/// # use cnx::text::{Attributes, Text};
+/// # use cnx::widgets::EwmhWidget;
+/// # use xcb_util::ewmh;
+/// let desktops = EwmhWidget::new(&["_NET_NUMBER_OF_DESKTOPS"], |conn: &ewmh::Connection| {
+///     let number = ewmh::get_number_of_desktops(conn, 0).get_reply().unwrap_or(0);
+///     vec![Text::new(Attributes::default(), format!("{} desktops", number))]
+/// });
+/// ```
+///
+/// A warning is logged at startup if the WM doesn't list any of
+/// `properties` in `_NET_SUPPORTED`.
+///
+/// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+pub struct EwmhWidget<F> {
+    properties: Vec<String>,
+    render: Rc<F>,
+}
+
+// The widget is restarted if its X connection fails, so it must be `Clone`
+// however `render` is.
+impl<F> Clone for EwmhWidget<F> {
+    fn clone(&self) -> Self {
+        EwmhWidget {
+            properties: self.properties.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+impl<F: Fn(&ewmh::Connection) -> Vec<Text> + 'static> EwmhWidget<F> {
+    /// Creates a new widget that calls `render` whenever one of `properties`
+    /// changes on the root window.
+    pub fn new(
+        properties: &[&str],
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self {
+                properties: properties.iter().map(|p| (*p).to_owned()).collect(),
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let properties: Vec<&str> = self.properties.iter().map(String::as_str).collect();
+        let render = self.render;
+        on_property_change("EwmhWidget", &properties, move |conn| Ok(render(conn)))
+            .context("Initialising EwmhWidget")
+    }
+}
+
+// Calls `render` with the connection that the changes were seen on once
+// initially and then whenever one of the root window `properties` changes,
+// yielding what it returns. Errors from `render` are yielded, so that they're
+// logged by the bar.
+//
+// Minimal WMs may not support EWMH (or not all of it), in which case the
+// widget would silently show nothing, so `widget` is named in a warning if
+// any of `properties` are unsupported.
+pub(crate) fn on_property_change<F, T>(
+    widget: &str,
+    properties: &[&str],
+    render: F,
+) -> Result<impl Stream<Item = T>>
+where
+    F: Fn(&ewmh::Connection) -> T + 'static,
+{
+    let mut stream = xcb_properties_stream(properties)?;
+    let unsupported = unsupported_properties(stream.conn(), 0, properties);
+    if !unsupported.is_empty() {
+        println!(
+            "{}: the window manager doesn't support {} (missing from _NET_SUPPORTED)",
+            widget,
+            unsupported.join(", ")
+        );
+    }
+    let mapped = stream! {
+        while let Some(()) = stream.next().await {
+            yield render(stream.conn());
+        }
+    };
+    Ok(mapped)
+}
+
+// Returns those of the EWMH `properties` that the WM doesn't list in
+// `_NET_SUPPORTED`. If the WM doesn't set `_NET_SUPPORTED` at all, it doesn't
+// support EWMH. Other properties (e.g. ICCCM's) aren't listed there, so are
+// assumed to be supported.
+fn unsupported_properties<'a>(
+    conn: &ewmh::Connection,
+    screen_idx: i32,
+    properties: &[&'a str],
+) -> Vec<&'a str> {
+    let ewmh_properties = properties
+        .iter()
+        .copied()
+        .filter(|property| property.starts_with("_NET_"));
+    let supported = match ewmh::get_supported(conn, screen_idx).get_reply() {
+        Ok(reply) => reply.atoms().to_vec(),
+        Err(_) => return ewmh_properties.collect(),
+    };
+    ewmh_properties
+        .filter(|property| {
+            xcb::intern_atom(conn, true, property)
+                .get_reply()
+                .map_or(true, |reply| !supported.contains(&reply.atom()))
+        })
+        .collect()
+}
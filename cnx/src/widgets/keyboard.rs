@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use async_stream::stream;
+use futures::stream::{Stream, StreamExt};
+use std::rc::Rc;
+use xcb::xkb;
+
+use crate::text::{Attributes, Text};
+use crate::xcb::xkb_event_stream;
+
+use super::{WidgetStream, WidgetStreamI};
+
+/// The keyboard layout passed to a [`KeyboardLayout`] widget's render
+/// closure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutInfo {
+    /// The index of the current XKB group, from 0.
+    pub group_index: u8,
+    /// The name of the current layout, as given to `setxkbmap`, e.g. `us` or
+    /// `de`. If the layout names aren't known, this is the group's number
+    /// counting from 1.
+    pub name: String,
+}
+
+// Picks the name of layout `group` out of the `_XKB_RULES_NAMES` property,
+// which holds the rules, model, layouts, variants and options passed to
+// `setxkbmap`, separated by NULs. The layouts are separated by commas, e.g.
+// `us,de`.
+fn layout_name(rules_names: &[u8], group: u8) -> Option<String> {
+    let layouts = rules_names.split(|&byte| byte == 0).nth(2)?;
+    let layout = String::from_utf8_lossy(layouts)
+        .split(',')
+        .nth(usize::from(group))?
+        .trim()
+        .to_owned();
+    Some(layout).filter(|layout| !layout.is_empty())
+}
+
+/// Shows the current keyboard layout, e.g. `us`.
+///
+/// The widget listens for XKB state changes, so updates as soon as the
+/// layout is switched.
+pub struct KeyboardLayout<F> {
+    attr: Attributes,
+    render: Rc<F>,
+}
+
+// The widget is restarted if its X connection fails, so it must be `Clone`
+// however `render` is.
+impl<F> Clone for KeyboardLayout<F> {
+    fn clone(&self) -> Self {
+        KeyboardLayout {
+            attr: self.attr.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+impl KeyboardLayout<fn(LayoutInfo) -> String> {
+    /// Creates a new Keyboard Layout widget, which shows the layout's name.
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_render(attr, |info| info.name)
+    }
+}
+
+impl<F: Fn(LayoutInfo) -> String + 'static> KeyboardLayout<F> {
+    /// Creates a new Keyboard Layout widget, which shows the output of
+    /// `render`. The output is Pango markup.
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::{KeyboardLayout, LayoutInfo};
+    /// let layout = KeyboardLayout::new_with_render(Attributes::default(), |info: LayoutInfo| {
+    ///     format!("⌨ {}", info.name.to_uppercase())
+    /// });
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self {
+                attr,
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let mut stream = xkb_event_stream(
+            xkb::EVENT_TYPE_STATE_NOTIFY as u16,
+            xkb::STATE_PART_GROUP_STATE as u16,
+        )
+        .context("Initialising KeyboardLayout")?;
+        let mapped = stream! {
+            while let Some(()) = stream.next().await {
+                yield self.on_change(stream.conn(), stream.root());
+            }
+        };
+        Ok(mapped)
+    }
+
+    fn on_change(&self, conn: &xcb::Connection, root: xcb::Window) -> Result<Vec<Text>> {
+        let group = xkb::get_state(conn, xkb::ID_USE_CORE_KBD as u16)
+            .get_reply()
+            .context("Failed to get XKB state")?
+            .group();
+
+        // The layouts are re-read each time, as `setxkbmap` may have
+        // changed them.
+        let atom = xcb::intern_atom(conn, true, "_XKB_RULES_NAMES")
+            .get_reply()?
+            .atom();
+        let name = xcb::get_property(conn, false, root, atom, xcb::ATOM_STRING, 0, 1024)
+            .get_reply()
+            .ok()
+            .and_then(|reply| layout_name(reply.value(), group))
+            .unwrap_or_else(|| (u16::from(group) + 1).to_string());
+
+        let text = (self.render)(LayoutInfo {
+            group_index: group,
+            name,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use async_stream::stream;
+use futures::stream::{Stream, StreamExt};
+use std::rc::Rc;
+use xcb::xkb;
+
+use crate::text::{Attributes, Text};
+use crate::xcb::xkb_event_stream;
+
+use super::{WidgetStream, WidgetStreamI};
+
+// The names XKB gives the lock indicators (the LEDs on a keyboard that has
+// them).
+const INDICATORS: [&str; 3] = ["Caps Lock", "Num Lock", "Scroll Lock"];
+
+/// The state of the lock keys passed to a [`LockKeys`] widget's render
+/// closure.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LockState {
+    pub caps: bool,
+    pub num: bool,
+    pub scroll: bool,
+}
+
+/// Shows which of Caps Lock, Num Lock and Scroll Lock are on, for keyboards
+/// without lock LEDs.
+///
+/// The widget listens for changes to XKB's indicators, so updates as soon as
+/// a lock key is pressed.
+pub struct LockKeys<F> {
+    attr: Attributes,
+    render: Rc<F>,
+}
+
+// The widget is restarted if its X connection fails, so it must be `Clone`
+// however `render` is.
+impl<F> Clone for LockKeys<F> {
+    fn clone(&self) -> Self {
+        LockKeys {
+            attr: self.attr.clone(),
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+fn default_render(state: LockState) -> String {
+    if state.caps {
+        "CAPS".to_owned()
+    } else {
+        String::new()
+    }
+}
+
+impl LockKeys<fn(LockState) -> String> {
+    /// Creates a new Lock Keys widget, which shows `CAPS` while Caps Lock is
+    /// on and nothing otherwise.
+    pub fn new(attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_render(attr, default_render)
+    }
+}
+
+impl<F: Fn(LockState) -> String + 'static> LockKeys<F> {
+    /// Creates a new Lock Keys widget, which shows the output of `render`.
+    /// The output is Pango markup.
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::{LockKeys, LockState};
+    /// let locks = LockKeys::new_with_render(Attributes::default(), |state: LockState| {
+    ///     let mut locks = Vec::new();
+    ///     if state.caps {
+    ///         locks.push("A");
+    ///     }
+    ///     if state.num {
+    ///         locks.push("1");
+    ///     }
+    ///     locks.join(" ")
+    /// });
+    /// ```
+    pub fn new_with_render(
+        attr: Attributes,
+        render: F,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self {
+                attr,
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let mut stream = xkb_event_stream(xkb::EVENT_TYPE_INDICATOR_STATE_NOTIFY as u16, 0)
+            .context("Initialising LockKeys")?;
+        let mut atoms = [xcb::ATOM_NONE; 3];
+        for (atom, name) in atoms.iter_mut().zip(INDICATORS.iter()) {
+            *atom = xcb::intern_atom(stream.conn(), false, name)
+                .get_reply()
+                .context("Failed to intern indicator atoms")?
+                .atom();
+        }
+
+        // The stream yields once before any events, so the state at startup
+        // is shown.
+        let mapped = stream! {
+            while let Some(()) = stream.next().await {
+                yield self.on_change(stream.conn(), &atoms);
+            }
+        };
+        Ok(mapped)
+    }
+
+    fn on_change(&self, conn: &xcb::Connection, atoms: &[xcb::Atom; 3]) -> Result<Vec<Text>> {
+        // An indicator that the keymap doesn't have is reported as off.
+        let on = |atom: xcb::Atom| -> Result<bool> {
+            let reply = xkb::get_named_indicator(
+                conn,
+                xkb::ID_USE_CORE_KBD as u16,
+                xkb::LED_CLASS_DFLT_XI_CLASS as u16,
+                xkb::ID_DFLT_XI_ID as u16,
+                atom,
+            )
+            .get_reply()
+            .context("Failed to get XKB indicator state")?;
+            Ok(reply.on())
+        };
+        let text = (self.render)(LockState {
+            caps: on(atoms[0])?,
+            num: on(atoms[1])?,
+            scroll: on(atoms[2])?,
+        });
+        Ok(vec![Text::new(self.attr.clone(), text).markup(true)])
+    }
+}
@@ -3,30 +3,395 @@
 mod active_window_title;
 
 mod clock;
+mod crawl;
+mod ewmh_widget;
+mod keyboard;
+mod locks;
 mod pager;
-pub use self::active_window_title::ActiveWindowTitle;
+mod shared;
+mod window_state;
+pub use self::active_window_title::{ActiveWindowTitle, TitleOptions, Truncation};
 pub use self::clock::Clock;
-pub use self::pager::Pager;
+pub use self::crawl::{Crawl, CrawlOptions};
+pub use self::ewmh_widget::EwmhWidget;
+pub use self::keyboard::{KeyboardLayout, LayoutInfo};
+pub use self::locks::{LockKeys, LockState};
+pub use self::pager::{Pager, PagerOptions};
+pub use self::shared::{SharedSource, SharedWidget, Subscription};
+pub use self::window_state::{ActiveWindowState, WindowState};
 use crate::text::Text;
-use anyhow::Result;
-use futures::stream::Stream;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use futures::stream::{self, Stream, StreamExt};
+use std::cmp;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::{pin, time};
+use tokio_stream::wrappers::{IntervalStream, ReceiverStream};
+use xcb_util::ewmh;
 
 pub type WidgetStreamI = Result<Vec<Text>>;
 
+/// A mouse button (or scroll wheel direction) that a widget was clicked with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    ScrollUp,
+    ScrollDown,
+}
+
+impl MouseButton {
+    // Maps the `detail` of an X `ButtonPressEvent` to a `MouseButton`.
+    pub(crate) fn from_detail(detail: u8) -> Option<Self> {
+        match detail {
+            1 => Some(MouseButton::Left),
+            2 => Some(MouseButton::Middle),
+            3 => Some(MouseButton::Right),
+            4 => Some(MouseButton::ScrollUp),
+            5 => Some(MouseButton::ScrollDown),
+            _ => None,
+        }
+    }
+}
+
+/// The direction the scroll wheel was turned in over a widget, passed to its
+/// [`on_scroll()`](WidgetStream::on_scroll) handler.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+}
+
+/// A click on one of a widget's segments.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClickEvent {
+    /// The button that was pressed.
+    pub button: MouseButton,
+    /// The index of the clicked [`Text`] within the widget's current content.
+    pub segment: usize,
+}
+
+/// What woke up a widget built on [`interval_and_events()`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trigger<E> {
+    /// The interval elapsed.
+    Tick,
+    /// The event source yielded an item.
+    Event(E),
+}
+
+/// Merges a periodic timer with an event source.
+///
+/// This is for widgets that want to both poll periodically and react to
+/// events, e.g. polling a battery's charge while also listening for uevents.
+/// The timer ticks immediately and then every `period`. The widget's
+/// `into_stream()` can map each [`Trigger`] to its texts:
+///
+/// ```ignore
+/// fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+///     let events = self.events()?;
+///     let stream = interval_and_events(self.update_interval, events)
+///         .map(move |trigger| self.tick(trigger));
+///     Ok(stream)
+/// }
+/// ```
+pub fn interval_and_events<E, S>(period: Duration, events: S) -> impl Stream<Item = Trigger<E>>
+where
+    S: Stream<Item = E>,
+{
+    let ticks = IntervalStream::new(time::interval(period)).map(|_| Trigger::Tick);
+    stream::select(ticks, events.map(Trigger::Event))
+}
+
+/// Raises a user-configured update `interval` to at least `minimum`.
+///
+/// Widgets that poll should pass user-configured intervals through this, so
+/// that a typo like `Duration::from_millis(10)` doesn't have the bar spend all
+/// its time updating. A message naming `widget` is logged when the interval
+/// is raised.
+pub fn clamp_interval(widget: &str, interval: Duration, minimum: Duration) -> Duration {
+    if interval < minimum {
+        println!(
+            "{}: update interval {:?} is too short, using {:?}",
+            widget, interval, minimum
+        );
+        minimum
+    } else {
+        interval
+    }
+}
+
+/// Called with the bar's connection each time a widget is clicked.
+pub type ClickHandler = Box<dyn FnMut(&ewmh::Connection, ClickEvent) -> Result<()>>;
+
+/// The initial delay before re-creating a widget stream that has ended.
+pub const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+/// The maximum delay before re-creating a widget stream that has ended.
+pub const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// A widget whose concrete type has been erased with
+/// [`WidgetStream::boxed()`], so that widgets of different types can be
+/// kept together, e.g. in a `Vec`.
+pub type BoxedWidget = WidgetStream<BoxedWidgetStream, BoxedWidgetStream>;
+
+type BoxedWidgetStream = Pin<Box<dyn Stream<Item = WidgetStreamI>>>;
+
 pub struct WidgetStream<T, S: Stream<Item = WidgetStreamI>> {
     widget: T,
-    stream_gen: fn(T) -> Result<S>
+    stream_gen: fn(T) -> Result<S>,
+    respawn: Option<Box<dyn Fn() -> T>>,
+    click_handler: Option<ClickHandler>,
+    segment_gap: f64,
+    name: Option<String>,
 }
 
 impl<T: 'static, S: Stream<Item= WidgetStreamI> + 'static> WidgetStream<T, S> {
     pub fn new(widget: T, stream_gen: fn(T) -> Result<S>) -> Self {
         return Self {
             widget,
-            stream_gen
+            stream_gen,
+            respawn: None,
+            click_handler: None,
+            segment_gap: 0.0,
+            name: None,
+        }
+    }
+
+    /// Sets a handler to be called whenever the widget is clicked.
+    ///
+    /// The handler is told which of the widget's [`Text`] segments was
+    /// clicked, and is lent the bar's connection (with the same constraints
+    /// as [`Cnx::connection()`](crate::Cnx::connection)) so that it can act
+    /// on EWMH state. Errors are logged by the bar.
+    ///
+    /// The pointer becomes a hand while it's over a widget with a handler.
+    pub fn on_click<H>(mut self, handler: H) -> Self
+    where
+        H: FnMut(&ewmh::Connection, ClickEvent) -> Result<()> + 'static,
+    {
+        self.click_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Runs a shell command whenever the widget is clicked with `button`, like
+    /// polybar's `click-left` and friends:
+    ///
+    /// ```no_run
+    /// # use cnx::text::Attributes;
+    /// # use cnx::widgets::{Clock, MouseButton};
+    /// let clock = Clock::new(Attributes::default(), None)
+    ///     .on_click_command(MouseButton::Left, "gsimplecal")
+    ///     .on_click_command(MouseButton::Right, "notify-send \"$(date)\"");
+    /// ```
+    ///
+    /// `command` is run with `sh -c` and isn't waited for. This can be
+    /// called once per button. A handler set by [`on_click()`] beforehand is
+    /// still called, but calling `on_click()` afterwards replaces the commands.
+    ///
+    /// [`on_click()`]: #method.on_click
+    pub fn on_click_command(mut self, button: MouseButton, command: &str) -> Self {
+        let command = command.to_owned();
+        let mut previous = self.click_handler.take();
+        self.on_click(move |conn, click| {
+            if click.button == button {
+                Command::new("sh")
+                    .arg("-c")
+                    .arg(&command)
+                    .spawn()
+                    .with_context(|| format!("Failed to run click command {:?}", command))?;
+            }
+            match previous.as_mut() {
+                Some(previous) => previous(conn, click),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Sets a handler to be called whenever the scroll wheel is turned over
+    /// the widget, e.g. to change the volume.
+    ///
+    /// Presses of other buttons are passed on to a handler set by
+    /// [`on_click()`] beforehand, if there is one, but scrolling isn't. As
+    /// with [`on_click_command()`], calling `on_click()` afterwards replaces
+    /// this handler.
+    ///
+    /// [`on_click()`]: #method.on_click
+    /// [`on_click_command()`]: #method.on_click_command
+    pub fn on_scroll<H>(mut self, mut handler: H) -> Self
+    where
+        H: FnMut(&ewmh::Connection, ScrollDirection) -> Result<()> + 'static,
+    {
+        let mut previous = self.click_handler.take();
+        self.on_click(move |conn, click| {
+            let direction = match click.button {
+                MouseButton::ScrollUp => ScrollDirection::Up,
+                MouseButton::ScrollDown => ScrollDirection::Down,
+                _ => {
+                    return match previous.as_mut() {
+                        Some(previous) => previous(conn, click),
+                        None => Ok(()),
+                    }
+                }
+            };
+            handler(conn, direction)
+        })
+    }
+
+    pub(crate) fn take_click_handler(&mut self) -> Option<ClickHandler> {
+        self.click_handler.take()
+    }
+
+    /// Leaves a gap of `gap` pixels between each of the widget's [`Text`]
+    /// segments, e.g. between the desktops of a [`Pager`]. The gap shows the
+    /// bar's background, and scales with [`Cnx::scale_factor()`].
+    ///
+    /// The default is no gap, so segments are only spaced by their padding.
+    ///
+    /// [`Cnx::scale_factor()`]: crate::Cnx::scale_factor
+    pub fn segment_gap(mut self, gap: f64) -> Self {
+        self.segment_gap = gap;
+        self
+    }
+
+    pub(crate) fn gap(&self) -> f64 {
+        self.segment_gap
+    }
+
+    /// Names the widget, e.g. `"weather"`, so that log messages about it
+    /// (such as its errors) say which widget they're about rather than
+    /// giving its index in the bar.
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    pub(crate) fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    /// Re-creates the widget's stream whenever it ends, or fails to be
+    /// created.
+    ///
+    /// Widgets backed by an external service (e.g. a media player that may
+    /// quit) can end their stream, after which their slot in the bar would
+    /// never update again. With this set, the stream is re-created from a
+    /// fresh copy of the widget, backing off exponentially between attempts
+    /// from [`RESTART_BACKOFF_MIN`] up to [`RESTART_BACKOFF_MAX`].
+    ///
+    /// All of the event-driven widgets here restart, and so should custom
+    /// widgets whose streams can end. Widgets that poll on an interval
+    /// generally report errors from each update instead, so don't need to.
+    pub fn restart_on_end(mut self) -> Self
+    where
+        T: Clone,
+    {
+        let widget = self.widget.clone();
+        self.respawn = Some(Box::new(move || widget.clone()));
+        self
+    }
+
+    /// Erases the widget's type, so that it can be added with
+    /// [`Cnx::add_boxed_widget()`] alongside widgets of other types. Its click
+    /// handler, segment gap and restart behaviour are kept.
+    ///
+    /// [`Cnx::add_boxed_widget()`]: crate::Cnx::add_boxed_widget
+    pub fn boxed(mut self) -> BoxedWidget {
+        let click_handler = self.click_handler.take();
+        let segment_gap = self.segment_gap;
+        let name = self.name.clone();
+        let stream: BoxedWidgetStream = Box::pin(self.into_stream());
+        WidgetStream {
+            widget: stream,
+            stream_gen: Ok,
+            respawn: None,
+            click_handler,
+            segment_gap,
+            name,
+        }
+    }
+
+    // Errors creating the stream are yielded as the stream's first item, so
+    // that they're reported like any other widget error. They're only retried
+    // if the widget restarts (see `restart_on_end()`); otherwise the widget
+    // shows its error indicator from then on.
+    pub(crate) fn into_stream(self: Self) -> impl Stream<Item = WidgetStreamI> {
+        let WidgetStream {
+            widget,
+            stream_gen,
+            respawn,
+            name,
+            ..
+        } = self;
+        let label = name.map_or_else(|| "Widget".to_owned(), |name| format!("Widget '{}'", name));
+
+        stream! {
+            let mut next = Some(widget);
+            let mut backoff = RESTART_BACKOFF_MIN;
+            while let Some(widget) = next.take() {
+                match stream_gen(widget) {
+                    Ok(stream) => {
+                        pin!(stream);
+                        while let Some(item) = stream.next().await {
+                            if item.is_ok() {
+                                backoff = RESTART_BACKOFF_MIN;
+                            }
+                            yield item;
+                        }
+                    }
+                    Err(err) => yield Err(err),
+                }
+
+                if let Some(respawn) = &respawn {
+                    println!("{} stream ended, restarting in {:?}", label, backoff);
+                    time::sleep(backoff).await;
+                    backoff = cmp::min(backoff * 2, RESTART_BACKOFF_MAX);
+                    next = Some(respawn());
+                }
+            }
         }
     }
+}
+
+/// A widget that shows messages received from a channel. Created by
+/// [`WidgetStream::from_channel()`].
+pub struct ChannelWidget<M, F> {
+    rx: mpsc::Receiver<M>,
+    render: F,
+}
+
+impl<M: 'static, F: Fn(M) -> Vec<Text> + 'static> ChannelWidget<M, F> {
+    fn into_stream(self) -> Result<Pin<Box<dyn Stream<Item = WidgetStreamI>>>> {
+        let render = self.render;
+        let stream = ReceiverStream::new(self.rx).map(move |message| Ok(render(message)));
+        Ok(Box::pin(stream))
+    }
+}
 
-    pub(crate) fn into_stream(self: Self) -> Result<S> {
-        return (self.stream_gen)(self.widget);
+impl<M: 'static, F: Fn(M) -> Vec<Text> + 'static>
+    WidgetStream<ChannelWidget<M, F>, Pin<Box<dyn Stream<Item = WidgetStreamI>>>>
+{
+    /// Creates a widget that shows each message sent on a channel, rendered
+    /// to texts by `render`.
+    ///
+    /// This is the simplest way to feed the bar from an existing async
+    /// source without implementing a `Stream`:
+    ///
+    /// ```no_run
+    /// # use cnx::text::{Attributes, Text};
+    /// # use cnx::widgets::WidgetStream;
+    /// let (tx, rx) = tokio::sync::mpsc::channel(8);
+    /// let widget = WidgetStream::from_channel(rx, |message: String| {
+    ///     vec![Text::new(Attributes::default(), message)]
+    /// });
+    /// # let _ = tx;
+    /// ```
+    ///
+    /// The widget keeps its last content once every sender has been dropped.
+    pub fn from_channel(rx: mpsc::Receiver<M>, render: F) -> Self {
+        WidgetStream::new(ChannelWidget { rx, render }, ChannelWidget::into_stream)
     }
 }
@@ -1,14 +1,44 @@
 use anyhow::{Context, Result};
 use futures::Stream;
-use futures::stream::StreamExt;
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use xcb_util::ewmh;
 
 use crate::text::{Attributes, Text};
-use crate::xcb::xcb_properties_stream;
-use async_stream::stream;
+use crate::xcb::{monitors, Monitor, ALL_DESKTOPS};
 
-use super::{WidgetStreamI, WidgetStream};
+use super::ewmh_widget::on_property_change;
+use super::{ClickEvent, MouseButton, ScrollDirection, WidgetStreamI, WidgetStream};
+
+/// Options controlling how the [`Pager`] behaves.
+#[derive(Clone, Debug, Default)]
+pub struct PagerOptions {
+    /// Skip desktops without any windows when cycling through desktops with
+    /// the scroll wheel.
+    pub skip_empty: bool,
+    /// Labels to show instead of the desktops' names, keyed by name, e.g.
+    /// `"web"` → `"🌐"`. Desktops without an entry show their name. As the
+    /// Pager's text is rendered as Pango markup, labels may contain markup.
+    pub icons: HashMap<String, String>,
+    /// Only show the desktops on this monitor (an index into the monitors
+    /// reported by RandR), for WMs that give each monitor its own desktops.
+    ///
+    /// A desktop is on a monitor if its `_NET_DESKTOP_VIEWPORT` lies within
+    /// the monitor. If the WM doesn't report viewports, all desktops are
+    /// shown.
+    pub monitor: Option<usize>,
+    /// Attributes for desktops that are showing on another monitor, but
+    /// aren't the current desktop. If this isn't set, they're shown with the
+    /// inactive attributes.
+    ///
+    /// EWMH only has a single `_NET_CURRENT_DESKTOP`. If the WM also sets
+    /// the (non-standard) `_NET_VISIBLE_DESKTOPS`, listing the desktop on
+    /// each monitor, that is used. Otherwise, a desktop is considered showing
+    /// if it has a mapped window on it. This works for WMs that unmap the
+    /// windows of hidden desktops (most do), but an empty desktop showing on
+    /// another monitor can't be told apart from a hidden one.
+    pub visible_attr: Option<Attributes>,
+}
 
 /// Shows the WM's workspaces/groups, highlighting whichever is currently
 /// active.
@@ -16,48 +46,125 @@ use super::{WidgetStreamI, WidgetStream};
 /// This widget shows the WM's workspaces/groups, as determined by the [`EWMH`]
 /// `_NET_NUMBER_OF_DESKTOPS` and `_NET_DESKTOP_NAMES` and
 /// `_NET_CURRENT_DESKTOP` properties. The active workspace is highlighted.
+/// On multi-monitor setups, desktops showing on other monitors can also be
+/// highlighted, see [`PagerOptions::visible_attr`].
+///
+/// Clicking a desktop switches to it, and scrolling over the widget switches
+/// to the previous/next desktop, wrapping around at either end.
+///
+/// ICCCM has no notion of desktops, so with a WM that doesn't support these
+/// properties the widget is empty, and a warning is logged at startup.
 ///
 /// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+#[derive(Clone)]
 pub struct Pager {
     active_attr: Attributes,
-    inactive_attr : Attributes
+    inactive_attr : Attributes,
+    visible_attr: Option<Attributes>,
+    icons: HashMap<String, String>,
+    monitor: Option<usize>,
 }
 
 impl Pager {
     pub fn new(active_attr: Attributes, inactive_attr: Attributes) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        Self::new_with_options(active_attr, inactive_attr, PagerOptions::default())
+    }
+
+    /// Creates a new Pager widget, with the given [`PagerOptions`].
+    pub fn new_with_options(
+        active_attr: Attributes,
+        inactive_attr: Attributes,
+        options: PagerOptions,
+    ) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        let screen_idx = 0;
+        let monitor = options.monitor;
         WidgetStream::new(
             Self {
                 active_attr,
-                inactive_attr
+                inactive_attr,
+                visible_attr: options.visible_attr.clone(),
+                icons: options.icons.clone(),
+                monitor: options.monitor,
             },
             Self::into_stream
         )
+        .restart_on_end()
+        .on_click(move |conn, click| Self::on_click(conn, screen_idx, monitor, click))
+        .on_scroll(move |conn, direction| Self::on_scroll(conn, screen_idx, &options, direction))
+    }
+
+    fn on_click(
+        conn: &ewmh::Connection,
+        screen_idx: i32,
+        monitor: Option<usize>,
+        click: ClickEvent,
+    ) -> Result<()> {
+        if click.button != MouseButton::Left {
+            return Ok(());
+        }
+
+        // Segments are the desktops that are shown, in order.
+        let number = ewmh::get_number_of_desktops(conn, screen_idx).get_reply()?;
+        let shown = desktops_on_monitor(conn, screen_idx, monitor, number as usize);
+        let desktop = (0..number).filter(|&i| shown[i as usize]).nth(click.segment);
+        if let Some(desktop) = desktop {
+            ewmh::request_change_current_desktop(conn, screen_idx, desktop, xcb::CURRENT_TIME);
+            conn.flush();
+        }
+        Ok(())
+    }
+
+    fn on_scroll(
+        conn: &ewmh::Connection,
+        screen_idx: i32,
+        options: &PagerOptions,
+        direction: ScrollDirection,
+    ) -> Result<()> {
+        let step = match direction {
+            ScrollDirection::Up => -1,
+            ScrollDirection::Down => 1,
+        };
+
+        let number = ewmh::get_number_of_desktops(conn, screen_idx).get_reply()?;
+        let current = ewmh::get_current_desktop(conn, screen_idx).get_reply()?;
+        let occupied = if options.skip_empty {
+            Some(occupied_desktops(conn, screen_idx)?)
+        } else {
+            None
+        };
+
+        if let Some(desktop) = next_desktop(current, number, step, occupied.as_ref()) {
+            ewmh::request_change_current_desktop(conn, screen_idx, desktop, xcb::CURRENT_TIME);
+            conn.flush();
+        }
+        Ok(())
     }
 
     fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI> + 'static> {
-        let properties = &[
+        let mut properties = vec![
             "_NET_NUMBER_OF_DESKTOPS",
             "_NET_CURRENT_DESKTOP",
             "_NET_DESKTOP_NAMES",
         ];
+        if self.visible_attr.is_some() {
+            // Desktops become visible as windows on them are mapped.
+            properties.push("_NET_CLIENT_LIST");
+        }
         let screen_idx = 0;
-        let mut stream = xcb_properties_stream(properties).context("Initialising Pager")?;
-        let mapped = stream! {
-            while let Some(()) = stream.next().await {
-                yield Ok(self.on_change(&stream.conn(), screen_idx));
-            }
-        };
-
-        return Ok(mapped);
+        on_property_change("Pager", &properties, move |conn| self.on_change(conn, screen_idx))
+            .context("Initialising Pager")
     }
 
-    fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Vec<Text> {
+    // Errors getting the number of desktops or the current desktop are
+    // returned rather than showing nothing, so that a misbehaving WM is
+    // noticed. Desktop names are optional in EWMH, so may be missing.
+    fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Result<Vec<Text>> {
         let number = ewmh::get_number_of_desktops(conn, screen_idx)
             .get_reply()
-            .unwrap_or(0) as usize;
+            .context("Failed to get _NET_NUMBER_OF_DESKTOPS")? as usize;
         let current = ewmh::get_current_desktop(conn, screen_idx)
             .get_reply()
-            .unwrap_or(0) as usize;
+            .context("Failed to get _NET_CURRENT_DESKTOP")? as usize;
         let names_reply = ewmh::get_desktop_names(conn, screen_idx).get_reply();
         let mut names = match names_reply {
             Ok(ref r) => r.strings(),
@@ -75,22 +182,191 @@ impl Pager {
             }
         }
 
-        names
+        let shown = desktops_on_monitor(conn, screen_idx, self.monitor, number);
+        let visible = if self.visible_attr.is_some() {
+            visible_desktops(conn, screen_idx).unwrap_or_default()
+        } else {
+            HashSet::new()
+        };
+
+        let texts: Vec<Text> = names
             .into_iter()
             .enumerate()
+            .filter(|(i, _)| shown[*i])
             .map(|(i, name)| {
-                let attr = if i == current {
-                    self.active_attr.clone()
-                } else {
-                    self.inactive_attr.clone()
+                let attr = match self.visible_attr {
+                    _ if i == current => self.active_attr.clone(),
+                    Some(ref visible_attr) if visible.contains(&(i as u32)) => {
+                        visible_attr.clone()
+                    }
+                    _ => self.inactive_attr.clone(),
                 };
-                Text {
-                    attr,
-                    text: name.to_owned(),
-                    stretch: false,
-                    markup: true,
-                }
+                let text = self
+                    .icons
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| name.to_owned());
+                Text::new(attr, text).markup(true)
+            })
+            .collect();
+        Ok(texts)
+    }
+}
+
+// Returns whether each of the `number` desktops should be shown: all of
+// them, unless the Pager is restricted to a monitor.
+fn desktops_on_monitor(conn: &ewmh::Connection, screen_idx: i32, monitor_idx: Option<usize>, number: usize) -> Vec<bool> {
+    let monitor = match monitor_idx.and_then(|idx| monitor(conn, screen_idx, idx)) {
+        Some(monitor) => monitor,
+        None => return vec![true; number],
+    };
+    let viewports = match ewmh::get_desktop_viewport(conn, screen_idx).get_reply() {
+        Ok(reply) => reply,
+        Err(_) => return vec![true; number],
+    };
+    let viewports = viewports.desktop_viewports();
+
+    (0..number)
+        .map(|i| {
+            viewports
+                .get(i)
+                .map_or(true, |viewport| monitor.contains(viewport.x as i32, viewport.y as i32))
+        })
+        .collect()
+}
+
+// Looks up the geometry of the monitor with the given index.
+fn monitor(conn: &ewmh::Connection, screen_idx: i32, idx: usize) -> Option<Monitor> {
+    let root = conn.get_setup().roots().nth(screen_idx as usize)?.root();
+    monitors(conn, root).ok()?.get(idx).copied()
+}
+
+// Returns the desktops that have at least one window on them. Sticky windows
+// are on every desktop, so they don't make a desktop occupied.
+fn occupied_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Result<HashSet<u32>> {
+    let clients = ewmh::get_client_list(conn, screen_idx).get_reply()?;
+    let desktops = clients
+        .windows()
+        .iter()
+        .filter_map(|&window| ewmh::get_wm_desktop(conn, window).get_reply().ok())
+        .filter(|&desktop| desktop != ALL_DESKTOPS)
+        .collect();
+    Ok(desktops)
+}
+
+// Returns the desktops that are showing on some monitor, from
+// `_NET_VISIBLE_DESKTOPS` if the WM sets it. Otherwise, they're guessed from
+// the windows that are mapped, see `mapped_desktops()`.
+fn visible_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Result<HashSet<u32>> {
+    match wm_visible_desktops(conn, screen_idx) {
+        Some(desktops) => Ok(desktops),
+        None => mapped_desktops(conn, screen_idx),
+    }
+}
+
+// Reads `_NET_VISIBLE_DESKTOPS`, which some multi-monitor WMs set on the root
+// window to the desktop shown on each monitor. It isn't part of EWMH, so it
+// isn't watched for changes (which would warn that it's unsupported with
+// most WMs), but it usually changes along with `_NET_CURRENT_DESKTOP`, which
+// is watched.
+fn wm_visible_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Option<HashSet<u32>> {
+    let root = conn.get_setup().roots().nth(screen_idx as usize)?.root();
+    let atom = xcb::intern_atom(conn, true, "_NET_VISIBLE_DESKTOPS")
+        .get_reply()
+        .ok()?
+        .atom();
+    if atom == xcb::ATOM_NONE {
+        return None;
+    }
+    let reply = xcb::get_property(conn, false, root, atom, xcb::ATOM_CARDINAL, 0, u32::MAX)
+        .get_reply()
+        .ok()?;
+    if reply.format() != 32 || reply.value_len() == 0 {
+        return None;
+    }
+    Some(reply.value::<u32>().iter().copied().collect())
+}
+
+// Returns the desktops that have at least one mapped window on them, i.e.
+// those that are showing on some monitor, for WMs that unmap the windows of
+// hidden desktops. Windows on all desktops are ignored.
+//
+// Every window's requests are sent before waiting for any of the replies, so
+// that this takes two round-trips to the X server however many windows
+// there are.
+fn mapped_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Result<HashSet<u32>> {
+    let clients = ewmh::get_client_list(conn, screen_idx).get_reply()?;
+    let cookies: Vec<_> = clients
+        .windows()
+        .iter()
+        .map(|&window| {
+            (
+                xcb::get_window_attributes(conn, window),
+                ewmh::get_wm_desktop(conn, window),
+            )
+        })
+        .collect();
+    let desktops = cookies
+        .into_iter()
+        .filter(|(attrs, _)| {
+            attrs.get_reply().map_or(false, |attrs| {
+                attrs.map_state() == xcb::MAP_STATE_VIEWABLE as u8
             })
-            .collect()
+        })
+        .filter_map(|(_, desktop)| desktop.get_reply().ok())
+        .filter(|&desktop| desktop != ALL_DESKTOPS)
+        .collect();
+    Ok(desktops)
+}
+
+// Finds the desktop `step` desktops away from `current`, wrapping around at
+// either end. If `occupied` is given, desktops not in it are skipped.
+fn next_desktop(current: u32, number: u32, step: i64, occupied: Option<&HashSet<u32>>) -> Option<u32> {
+    let number = i64::from(number);
+    (1..=number)
+        .map(|i| (i64::from(current) + step * i).rem_euclid(number) as u32)
+        .find(|desktop| occupied.map_or(true, |occupied| occupied.contains(desktop)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::next_desktop;
+    use std::collections::HashSet;
+
+    #[test]
+    fn wraps_around_in_both_directions() {
+        assert_eq!(next_desktop(2, 4, 1, None), Some(3));
+        assert_eq!(next_desktop(3, 4, 1, None), Some(0));
+        assert_eq!(next_desktop(1, 4, -1, None), Some(0));
+        assert_eq!(next_desktop(0, 4, -1, None), Some(3));
+    }
+
+    #[test]
+    fn skips_empty_desktops() {
+        let occupied: HashSet<u32> = [0, 2, 3].iter().copied().collect();
+        assert_eq!(next_desktop(0, 5, 1, Some(&occupied)), Some(2));
+        assert_eq!(next_desktop(3, 5, 1, Some(&occupied)), Some(0));
+        assert_eq!(next_desktop(0, 5, -1, Some(&occupied)), Some(3));
+        assert_eq!(next_desktop(2, 5, -1, Some(&occupied)), Some(0));
+    }
+
+    #[test]
+    fn stays_put_when_no_other_desktop_is_occupied() {
+        let occupied: HashSet<u32> = [1].iter().copied().collect();
+        assert_eq!(next_desktop(1, 4, 1, Some(&occupied)), Some(1));
+        assert_eq!(next_desktop(1, 4, -1, Some(&occupied)), Some(1));
+        assert_eq!(next_desktop(1, 4, 1, Some(&HashSet::new())), None);
+    }
+
+    #[test]
+    fn handles_a_single_desktop() {
+        assert_eq!(next_desktop(0, 1, 1, None), Some(0));
+        assert_eq!(next_desktop(0, 1, -1, None), Some(0));
+    }
+
+    #[test]
+    fn handles_no_desktops() {
+        assert_eq!(next_desktop(0, 0, 1, None), None);
+        assert_eq!(next_desktop(0, 0, -1, None), None);
     }
 }
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use futures::Stream;
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use tokio_stream::StreamExt;
+
+use crate::text::Text;
+
+use super::{WidgetStream, WidgetStreamI};
+
+/// A data source shared between several widgets.
+///
+/// Widgets usually poll their own data source, so two widgets showing the
+/// same data (e.g. a CPU graph and a CPU percentage) do the same work twice.
+/// A `SharedSource` polls a single stream and fans each value out to every
+/// widget created from it:
+///
+/// ```no_run
+/// # use cnx::text::{Attributes, Text};
+/// # use cnx::widgets::SharedSource;
+/// # use std::time::Duration;
+/// # use tokio_stream::{wrappers::IntervalStream, StreamExt};
+/// let loadavg = IntervalStream::new(tokio::time::interval(Duration::from_secs(5)))
+///     .map(|_| std::fs::read_to_string("/proc/loadavg").unwrap_or_default());
+/// let source = SharedSource::new(loadavg);
+///
+/// let text = |text: String| Text::new(Attributes::default(), text);
+/// let one_minute = source.widget(move |loadavg: String| {
+///     vec![text(loadavg.split(' ').next().unwrap_or("?").to_owned())]
+/// });
+/// let five_minutes = source.widget(move |loadavg: String| {
+///     vec![text(loadavg.split(' ').nth(1).unwrap_or("?").to_owned())]
+/// });
+/// ```
+///
+/// The source is polled by whichever widget gets to it first, so needs no
+/// task of its own. A widget that falls behind only sees the latest value.
+/// Widgets created after the source has yielded start with its latest value.
+pub struct SharedSource<T> {
+    inner: Rc<RefCell<Inner<T>>>,
+}
+
+struct Inner<T> {
+    source: Pin<Box<dyn Stream<Item = T>>>,
+    last: Option<T>,
+    ended: bool,
+    // One slot per subscription, or `None` once it has been dropped.
+    slots: Vec<Option<Slot<T>>>,
+}
+
+struct Slot<T> {
+    pending: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T: Clone + 'static> SharedSource<T> {
+    /// Creates a source that fans out the values of `source`.
+    pub fn new<S: Stream<Item = T> + 'static>(source: S) -> Self {
+        SharedSource {
+            inner: Rc::new(RefCell::new(Inner {
+                source: Box::pin(source),
+                last: None,
+                ended: false,
+                slots: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns a stream of the source's values, for widgets that want to
+    /// combine them with something else.
+    pub fn subscribe(&self) -> Subscription<T> {
+        let mut inner = self.inner.borrow_mut();
+        let slot = Slot {
+            pending: inner.last.clone(),
+            waker: None,
+        };
+        inner.slots.push(Some(slot));
+        Subscription {
+            inner: Rc::clone(&self.inner),
+            idx: inner.slots.len() - 1,
+        }
+    }
+
+    /// Creates a widget that shows each of the source's values, rendered to
+    /// texts by `render`.
+    pub fn widget<F>(&self, render: F) -> WidgetStream<SharedWidget<T, F>, impl Stream<Item = WidgetStreamI>>
+    where
+        F: Fn(T) -> Vec<Text> + 'static,
+    {
+        let widget = SharedWidget {
+            subscription: self.subscribe(),
+            render,
+        };
+        WidgetStream::new(widget, SharedWidget::into_stream)
+    }
+}
+
+/// A stream of a [`SharedSource`]'s values. Created by
+/// [`SharedSource::subscribe()`].
+pub struct Subscription<T> {
+    inner: Rc<RefCell<Inner<T>>>,
+    idx: usize,
+}
+
+impl<T: Clone> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let idx = self.idx;
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        if let Some(value) = inner.slots[idx].as_mut().and_then(|slot| slot.pending.take()) {
+            return Poll::Ready(Some(value));
+        }
+        if inner.ended {
+            return Poll::Ready(None);
+        }
+
+        match inner.source.as_mut().poll_next(cx) {
+            Poll::Ready(Some(value)) => {
+                // Hand the value to every other subscription, waking them if
+                // they're waiting for it.
+                inner.last = Some(value.clone());
+                for (i, slot) in inner.slots.iter_mut().enumerate() {
+                    if i == idx {
+                        continue;
+                    }
+                    if let Some(slot) = slot {
+                        slot.pending = Some(value.clone());
+                        if let Some(waker) = slot.waker.take() {
+                            waker.wake();
+                        }
+                    }
+                }
+                Poll::Ready(Some(value))
+            }
+            Poll::Ready(None) => {
+                inner.ended = true;
+                for slot in inner.slots.iter_mut().flatten() {
+                    if let Some(waker) = slot.waker.take() {
+                        waker.wake();
+                    }
+                }
+                Poll::Ready(None)
+            }
+            // The source will wake us, but we also need waking if another
+            // subscription polls the source first and takes its value.
+            Poll::Pending => {
+                if let Some(slot) = inner.slots[idx].as_mut() {
+                    slot.waker = Some(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.inner.borrow_mut().slots[self.idx] = None;
+    }
+}
+
+/// A widget that renders the values of a [`SharedSource`]. Created by
+/// [`SharedSource::widget()`].
+pub struct SharedWidget<T, F> {
+    subscription: Subscription<T>,
+    render: F,
+}
+
+impl<T: Clone + 'static, F: Fn(T) -> Vec<Text> + 'static> SharedWidget<T, F> {
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let render = self.render;
+        Ok(self.subscription.map(move |value| Ok(render(value))))
+    }
+}
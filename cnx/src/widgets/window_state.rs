@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use futures::Stream;
+use std::rc::Rc;
+use xcb_util::{ewmh, icccm};
+
+use crate::text::Text;
+use crate::xcb::ALL_DESKTOPS;
+
+use super::ewmh_widget::on_property_change;
+use super::{WidgetStream, WidgetStreamI};
+
+/// The state of a window, from its `_NET_WM_STATE` [`EWMH`] property.
+///
+/// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WindowState {
+    /// `_NET_WM_STATE_FULLSCREEN`
+    pub fullscreen: bool,
+    /// `_NET_WM_STATE_MAXIMIZED_VERT`
+    pub maximized_vert: bool,
+    /// `_NET_WM_STATE_MAXIMIZED_HORZ`
+    pub maximized_horz: bool,
+    /// Whether the window is shown on all desktops, either with
+    /// `_NET_WM_STATE_STICKY` or a `_NET_WM_DESKTOP` of `0xFFFFFFFF`.
+    pub sticky: bool,
+    /// Whether the window is likely to be floating. EWMH has no state for
+    /// this, so it is guessed from the window being a dialog, utility or
+    /// splash window, or transient for another window, which most tiling WMs
+    /// float.
+    pub floating: bool,
+}
+
+impl WindowState {
+    /// Reads the state of `window`. A window that doesn't exist (any more)
+    /// has the default state.
+    pub fn of(conn: &ewmh::Connection, window: xcb::Window) -> WindowState {
+        let states = ewmh::get_wm_state(conn, window)
+            .get_reply()
+            .map(|reply| reply.atoms().to_vec())
+            .unwrap_or_default();
+        let types = ewmh::get_wm_window_type(conn, window)
+            .get_reply()
+            .map(|reply| reply.atoms().to_vec())
+            .unwrap_or_default();
+        let floating_types = [
+            conn.WM_WINDOW_TYPE_DIALOG(),
+            conn.WM_WINDOW_TYPE_UTILITY(),
+            conn.WM_WINDOW_TYPE_SPLASH(),
+        ];
+        let all_desktops = ewmh::get_wm_desktop(conn, window)
+            .get_reply()
+            .map_or(false, |desktop| desktop == ALL_DESKTOPS);
+        let transient = icccm::get_wm_transient_for(conn, window)
+            .get_reply()
+            .map_or(false, |parent| parent != xcb::NONE);
+
+        WindowState {
+            fullscreen: states.contains(&conn.WM_STATE_FULLSCREEN()),
+            maximized_vert: states.contains(&conn.WM_STATE_MAXIMIZED_VERT()),
+            maximized_horz: states.contains(&conn.WM_STATE_MAXIMIZED_HORZ()),
+            sticky: all_desktops || states.contains(&conn.WM_STATE_STICKY()),
+            floating: transient || types.iter().any(|t| floating_types.contains(t)),
+        }
+    }
+
+    /// Reads the state of the active window (`_NET_ACTIVE_WINDOW`), or the
+    /// default state if no window is active.
+    pub fn active(conn: &ewmh::Connection, screen_idx: i32) -> WindowState {
+        match ewmh::get_active_window(conn, screen_idx).get_reply() {
+            Ok(window) if window != xcb::NONE => WindowState::of(conn, window),
+            _ => WindowState::default(),
+        }
+    }
+}
+
+/// Renders the [`WindowState`] of the currently focused window.
+///
+/// The widget updates whenever the focused window changes, or its
+/// `_NET_WM_STATE` changes, e.g. to show an indicator while the focused window
+/// is floating:
+///
+/// ```no_run
+/// # use cnx::text::{Attributes, Text};
+/// # use cnx::widgets::{ActiveWindowState, WindowState};
+/// let floating = ActiveWindowState::new(|state: WindowState| {
+///     if !state.floating {
+///         return vec![];
+///     }
+///     vec![Text::new(Attributes::default(), "floating".to_owned())]
+/// });
+/// ```
+pub struct ActiveWindowState<F> {
+    render: Rc<F>,
+}
+
+// The widget is restarted if its X connection fails, so it must be `Clone`
+// however `render` is.
+impl<F> Clone for ActiveWindowState<F> {
+    fn clone(&self) -> Self {
+        ActiveWindowState {
+            render: Rc::clone(&self.render),
+        }
+    }
+}
+
+impl<F: Fn(WindowState) -> Vec<Text> + 'static> ActiveWindowState<F> {
+    /// Creates a new widget that calls `render` with the focused window's
+    /// state.
+    pub fn new(render: F) -> WidgetStream<Self, impl Stream<Item = WidgetStreamI>> {
+        WidgetStream::new(
+            Self {
+                render: Rc::new(render),
+            },
+            Self::into_stream,
+        )
+        .restart_on_end()
+    }
+
+    fn into_stream(self) -> Result<impl Stream<Item = WidgetStreamI>> {
+        let properties = &["_NET_ACTIVE_WINDOW", "_NET_WM_STATE", "_NET_WM_DESKTOP"];
+        let screen_idx = 0; // XXX assume
+        let render = self.render;
+        on_property_change("ActiveWindowState", properties, move |conn| {
+            // As in ActiveWindowTitle, register for property changes on each
+            // window that becomes active, so that we see its state change.
+            if let Ok(window) = ewmh::get_active_window(conn, screen_idx).get_reply() {
+                let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)];
+                xcb::change_window_attributes(conn, window, &attributes);
+                conn.flush();
+            }
+            Ok(render(WindowState::active(conn, screen_idx)))
+        })
+        .context("Initialising ActiveWindowState")
+    }
+}
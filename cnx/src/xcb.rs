@@ -7,17 +7,19 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::unix::AsyncFd;
 use tokio_stream::Stream;
+use xcb::xkb;
 use xcb::xproto::{PropertyNotifyEvent, PROPERTY_NOTIFY};
-use xcb_util::ewmh;
+use xcb_util::{ewmh, keysyms::KeySymbols};
 use pin_project_lite::pin_project;
 
 use crate::bar::Bar;
 
-struct BarEventd(Bar);
+// The bars, which all share one connection (so there must be at least one).
+struct BarEventd(Vec<Bar>);
 
 impl AsRawFd for BarEventd {
     fn as_raw_fd(&self) -> RawFd {
-        let conn = &self.0.conn;
+        let conn: &xcb::Connection = &self.0[0].conn;
         conn.as_raw_fd()
     }
 }
@@ -28,8 +30,8 @@ pub(crate) struct BarEventStream {
 }
 
 impl BarEventStream {
-    pub(crate) fn new(bar: Bar) -> Result<Self> {
-        let eventd = BarEventd(bar);
+    pub(crate) fn new(bars: Vec<Bar>) -> Result<Self> {
+        let eventd = BarEventd(bars);
         let poll = AsyncFd::with_interest(eventd, tokio::io::Interest::READABLE)?;
 
         Ok(Self {
@@ -38,7 +40,7 @@ impl BarEventStream {
         })
     }
 
-    pub(crate) fn bar_mut(&mut self) -> &mut Bar {
+    pub(crate) fn bars_mut(&mut self) -> &mut [Bar] {
         &mut self.poll.get_mut().0
     }
 }
@@ -62,7 +64,7 @@ impl Stream for BarEventStream {
                 Poll::Pending => return Poll::Pending,
             }
         }
-        match self_.poll.get_ref().0.conn.poll_for_event() {
+        match self_.poll.get_ref().0[0].conn.poll_for_event() {
             Some(event) => Poll::Ready(Some(event)),
             None => {
                 self_.would_block = true;
@@ -198,6 +200,81 @@ impl Stream for XcbPropertiesStream {
     }
 }
 
+// Modifiers that are ignored when matching hotkeys, so that they work
+// whether or not Caps Lock or Num Lock (usually Mod2) are on.
+const IGNORED_MODIFIERS: [u16; 4] = [
+    0,
+    xcb::MOD_MASK_LOCK as u16,
+    xcb::MOD_MASK_2 as u16,
+    (xcb::MOD_MASK_LOCK | xcb::MOD_MASK_2) as u16,
+];
+
+// Grabs the key with the given keysym and modifiers on the root window, so
+// that the bar receives a `KEY_PRESS` whenever it is pressed. Returns the
+// keycodes that were grabbed, as a keysym may be on more than one key.
+pub(crate) fn grab_hotkey(
+    conn: &xcb::Connection,
+    root: xcb::Window,
+    modifiers: u16,
+    keysym: xcb::Keysym,
+) -> Result<Vec<xcb::Keycode>> {
+    let keycodes: Vec<xcb::Keycode> = KeySymbols::new(conn).get_keycode(keysym).collect();
+    if keycodes.is_empty() {
+        return Err(anyhow!("No key produces keysym {:#x}", keysym));
+    }
+
+    for &keycode in &keycodes {
+        for ignored in IGNORED_MODIFIERS.iter() {
+            xcb::grab_key_checked(
+                conn,
+                true,
+                root,
+                modifiers | ignored,
+                keycode,
+                xcb::GRAB_MODE_ASYNC as u8,
+                xcb::GRAB_MODE_ASYNC as u8,
+            )
+            .request_check()
+            .context("Failed to grab key, is it bound by another program?")?;
+        }
+    }
+    Ok(keycodes)
+}
+
+// The modifiers of a key press, without those in `IGNORED_MODIFIERS`.
+pub(crate) fn hotkey_modifiers(state: u16) -> u16 {
+    state & !IGNORED_MODIFIERS[3]
+}
+
+// The `hand2` glyph in the X cursor font.
+const XC_HAND2: u16 = 60;
+
+// Creates the pointing hand cursor that is shown over clickable widgets.
+pub(crate) fn create_hand_cursor(conn: &xcb::Connection) -> xcb::Cursor {
+    let font = conn.generate_id();
+    xcb::open_font(conn, font, "cursor");
+    let cursor = conn.generate_id();
+    xcb::create_glyph_cursor(
+        conn,
+        cursor,
+        font,
+        font,
+        XC_HAND2,
+        XC_HAND2 + 1,
+        0,
+        0,
+        0,
+        0xffff,
+        0xffff,
+        0xffff,
+    );
+    xcb::close_font(conn, font);
+    cursor
+}
+
+// The `_NET_WM_DESKTOP` of a window that is shown on all desktops (sticky).
+pub(crate) const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
 // A `Stream` that listens to `PROPERTY_CHANGE` notifications.
 //
 // By default it listens to `PROPERTY_CHANGE` notifications for the provided
@@ -207,18 +284,7 @@ impl Stream for XcbPropertiesStream {
 pub fn xcb_properties_stream(
     properties: &[&str],
 ) -> Result<XcbPropertiesStream> {
-    let (xcb_conn, screen_idx) =
-        xcb::Connection::connect(None).context("Failed to connect to X server")?;
-    let root_window = xcb_conn
-        .get_setup()
-        .roots()
-        .nth(screen_idx as usize)
-        .ok_or_else(|| anyhow!("Invalid screen"))?
-        .root();
-    let ewmh_conn = ewmh::Connection::connect(xcb_conn)
-        .map_err(|(e, _)| e)
-        .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
-    let conn = ewmh_conn;
+    let (conn, root_window) = connect()?;
 
     let only_if_exists = true;
     let properties = properties
@@ -241,3 +307,246 @@ pub fn xcb_properties_stream(
 
     return Ok(stream);
 }
+
+// Opens a new connection to the X server, returning it along with the root
+// window of its default screen.
+fn connect() -> Result<(ewmh::Connection, xcb::Window)> {
+    let (xcb_conn, screen_idx) =
+        xcb::Connection::connect(None).context("Failed to connect to X server")?;
+    let root_window = xcb_conn
+        .get_setup()
+        .roots()
+        .nth(screen_idx as usize)
+        .ok_or_else(|| anyhow!("Invalid screen"))?
+        .root();
+    let conn = ewmh::Connection::connect(xcb_conn)
+        .map_err(|(e, _)| e)
+        .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
+    Ok((conn, root_window))
+}
+
+pin_project! {
+    pub(crate) struct XkbEventStream {
+        first: Option<()>,
+        #[pin]
+        inner: XcbEventStream,
+        root: xcb::Window,
+        first_event: u8,
+        events: u16,
+        state_parts: u16,
+    }
+}
+
+impl XkbEventStream {
+    pub fn conn(&self) -> &ewmh::Connection {
+        self.inner.conn()
+    }
+
+    pub fn root(&self) -> xcb::Window {
+        self.root
+    }
+
+    // Whether `event` is one of the XKB events that were asked for.
+    fn wanted(&self, event: &xcb::GenericEvent) -> bool {
+        if event.response_type() != self.first_event {
+            return false;
+        }
+        // All XKB events share one response type, and are told apart by
+        // their `xkb_type`. Its bit in an event mask is `1 << xkb_type`.
+        let event: &xkb::StateNotifyEvent = unsafe { xcb::cast_event(event) };
+        let xkb_type = event.xkb_type();
+        if self.events & (1 << xkb_type) == 0 {
+            return false;
+        }
+        xkb_type != xkb::STATE_NOTIFY || event.changed() & self.state_parts != 0
+    }
+}
+
+impl Stream for XkbEventStream {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Some(v) = self.first.take() {
+            return Poll::Ready(Some(v));
+        }
+
+        loop {
+            match ready!(self.as_mut().project().inner.poll_next(cx)) {
+                Some(e) => {
+                    if self.wanted(&e) {
+                        return Poll::Ready(Some(()));
+                    }
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+// A `Stream` that listens to XKB events for the core keyboard.
+//
+// It yields once initially and then whenever one of the `events` (e.g.
+// `xkb::EVENT_TYPE_INDICATOR_STATE_NOTIFY`) is received, so that the caller
+// can query the new state. `StateNotify` events are only yielded if one of
+// the `state_parts` (e.g. `xkb::STATE_PART_GROUP_STATE` for the layout)
+// changed.
+pub(crate) fn xkb_event_stream(events: u16, state_parts: u16) -> Result<XkbEventStream> {
+    let (conn, root) = connect()?;
+    let first_event = conn
+        .get_extension_data(xkb::id())
+        .filter(|extension| extension.present())
+        .ok_or_else(|| anyhow!("The X server doesn't support XKB"))?
+        .first_event();
+
+    // XKB requests fail until the client has said which version it speaks.
+    let reply = xkb::use_extension(&conn, xkb::MAJOR_VERSION as u16, xkb::MINOR_VERSION as u16)
+        .get_reply()
+        .context("Failed to initialise XKB")?;
+    if !reply.supported() {
+        return Err(anyhow!(
+            "The X server doesn't support XKB {}.{}",
+            xkb::MAJOR_VERSION,
+            xkb::MINOR_VERSION
+        ));
+    }
+
+    xkb::select_events_checked(
+        &conn,
+        xkb::ID_USE_CORE_KBD as u16,
+        events,
+        0,
+        events,
+        0,
+        0,
+        None,
+    )
+    .request_check()
+    .context("Failed to select XKB events")?;
+
+    Ok(XkbEventStream {
+        first: Some(()),
+        inner: XcbEventStream::new(conn)?,
+        root,
+        first_event,
+        events,
+        state_parts,
+    })
+}
+
+// The geometry of a monitor (an enabled RandR CRTC) within the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Monitor {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Monitor {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (left, top) = (i32::from(self.x), i32::from(self.y));
+        x >= left && x < left + i32::from(self.width) && y >= top && y < top + i32::from(self.height)
+    }
+
+    // Whether any part of `other` is within the monitor.
+    pub fn overlaps(&self, other: &Monitor) -> bool {
+        let (left, top) = (i32::from(self.x), i32::from(self.y));
+        let (other_left, other_top) = (i32::from(other.x), i32::from(other.y));
+        other_left < left + i32::from(self.width)
+            && left < other_left + i32::from(other.width)
+            && other_top < top + i32::from(self.height)
+            && top < other_top + i32::from(other.height)
+    }
+}
+
+// Lists the monitors showing the given root window, in the order RandR
+// reports their CRTCs.
+pub(crate) fn monitors(conn: &xcb::Connection, root: xcb::Window) -> Result<Vec<Monitor>> {
+    let resources = xcb::randr::get_screen_resources_current(conn, root)
+        .get_reply()
+        .context("Failed to get RandR screen resources")?;
+    let timestamp = resources.config_timestamp();
+
+    let mut monitors = Vec::new();
+    for &crtc in resources.crtcs() {
+        if let Some(monitor) = crtc_monitor(conn, crtc, timestamp)? {
+            monitors.push(monitor);
+        }
+    }
+    Ok(monitors)
+}
+
+// Finds the monitor showing the primary RandR output, or `None` if no output
+// is primary (or the server doesn't support RandR).
+pub(crate) fn primary_monitor(conn: &xcb::Connection, root: xcb::Window) -> Option<Monitor> {
+    let output = xcb::randr::get_output_primary(conn, root)
+        .get_reply()
+        .ok()?
+        .output();
+    if output == xcb::NONE {
+        return None;
+    }
+    let timestamp = xcb::randr::get_screen_resources_current(conn, root)
+        .get_reply()
+        .ok()?
+        .config_timestamp();
+    let info = xcb::randr::get_output_info(conn, output, timestamp)
+        .get_reply()
+        .ok()?;
+    if info.crtc() == xcb::NONE {
+        return None;
+    }
+    crtc_monitor(conn, info.crtc(), timestamp).ok()?
+}
+
+// The geometry of a CRTC, or `None` if it's disabled.
+fn crtc_monitor(
+    conn: &xcb::Connection,
+    crtc: xcb::randr::Crtc,
+    timestamp: xcb::Timestamp,
+) -> Result<Option<Monitor>> {
+    let info = xcb::randr::get_crtc_info(conn, crtc, timestamp).get_reply()?;
+    // Disabled CRTCs have no outputs and no size.
+    if info.num_outputs() == 0 || info.width() == 0 || info.height() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Monitor {
+        x: info.x(),
+        y: info.y(),
+        width: info.width(),
+        height: info.height(),
+    }))
+}
+
+// Asks RandR to send `ScreenChangeNotify` events for the given root window,
+// e.g. when a monitor is plugged in or the resolution changes.
+//
+// Returns the response type of those events, or `None` if the server doesn't
+// support RandR.
+pub(crate) fn select_screen_change_events(
+    conn: &xcb::Connection,
+    root: xcb::Window,
+) -> Option<u8> {
+    let extension = conn.get_extension_data(xcb::randr::id())?;
+    if !extension.present() {
+        return None;
+    }
+    xcb::randr::select_input(
+        conn,
+        root,
+        xcb::randr::NOTIFY_MASK_SCREEN_CHANGE as u16,
+    );
+    Some(extension.first_event() + xcb::randr::SCREEN_CHANGE_NOTIFY)
+}
+
+// The new size of the screen, in pixels, from a `ScreenChangeNotify` event.
+pub(crate) fn screen_change_size(event: &xcb::GenericEvent) -> (u16, u16) {
+    let event: &xcb::randr::ScreenChangeNotifyEvent = unsafe { xcb::cast_event(event) };
+    // The size is reported before rotation is applied.
+    let rotated = xcb::randr::ROTATION_ROTATE_90 | xcb::randr::ROTATION_ROTATE_270;
+    if event.rotation() & rotated as u16 != 0 {
+        (event.height(), event.width())
+    } else {
+        (event.width(), event.height())
+    }
+}